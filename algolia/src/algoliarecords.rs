@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct ItemRecord {
@@ -18,12 +19,11 @@ pub struct ItemRecord {
     pub image_urls: Vec<String>,
     pub image_blurred: Option<String>,
     pub reviews: Option<Review>,
-    pub color: Option<String>,
-    pub available_colors: Option<Vec<String>>,
-    pub size: Option<String>,
-    pub available_sizes: Option<Vec<String>>,
+    // The mapped specifications and the available_* lists of the variant dimensions
+    #[serde(flatten)]
+    pub attributes: BTreeMap<String, serde_json::Value>,
     pub variants: Vec<Variant>,
-    pub price: Price,
+    pub price: Option<Price>,
     pub units_in_stock: i32,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
@@ -33,16 +33,58 @@ pub struct ItemRecord {
     pub object_id: String,
 }
 
+// One record per product with the skus nested as variants
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
-pub struct HierarchicalCategories {
-    pub lvl0: String,
-    pub lvl1: String,
-    pub lvl2: String,
+pub struct ProductRecord {
+    pub product_id: i32,
+    #[serde(rename = "parentID")]
+    pub parent_ref: String,
+    pub name: String,
+    pub description: String,
+    pub slug: String,
+    pub brand: String,
+    pub hierarchical_categories: HierarchicalCategories,
+    pub list_categories: Vec<String>,
+    pub category_page_id: Vec<String>,
+    pub image_urls: Vec<String>,
+    pub reviews: Option<Review>,
+    // The mapped specifications and the available_* lists of the variant dimensions
+    #[serde(flatten)]
+    pub attributes: BTreeMap<String, serde_json::Value>,
+    pub variants: Vec<Variant>,
+    pub price_range: Option<PriceRange>,
+    // Sum of the units in stock of the skus
+    pub units_in_stock: i32,
+    pub in_stock: bool,
+    #[serde(rename = "objectID")]
+    pub object_id: String,
+}
+
+// The objectID of the records sent to Algolia
+pub trait AlgoliaRecord: Serialize {
+    fn object_id(&self) -> &str;
+}
+
+impl AlgoliaRecord for ItemRecord {
+    fn object_id(&self) -> &str {
+        &self.object_id
+    }
+}
+
+impl AlgoliaRecord for ProductRecord {
+    fn object_id(&self) -> &str {
+        &self.object_id
+    }
 }
 
+// lvl0..lvlN - one level per category of the product category path
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(transparent)]
+pub struct HierarchicalCategories(pub BTreeMap<String, String>);
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct Review {
-    pub rating: i32,
+    pub rating: f32,
     pub count: i32,
     pub bayesian_avg: f32,
 }
@@ -57,9 +99,15 @@ pub struct Color {
 pub struct Variant {
     #[serde(rename = "sku")]
     pub sku_ref: String,
-    pub abbreviated_size: Option<String>,
-    pub abbreviated_color: Option<String>,
+    // The value of each variant dimension (ex: abbreviated_color)
+    #[serde(flatten)]
+    pub attributes: BTreeMap<String, String>,
     pub in_stock: bool,
+    // Only set on the variants of product records
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub price: Option<Price>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub units_in_stock: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -70,3 +118,10 @@ pub struct Price {
     pub discount_level: f32,
     pub on_sales: bool,
 }
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct PriceRange {
+    pub currency: String,
+    pub min: f32,
+    pub max: f32,
+}
@@ -0,0 +1,53 @@
+use crate::algoliarecords::HierarchicalCategories;
+
+const SEPARATOR: &str = " > ";
+
+// lvl0 is the department and each level adds the next category to the path (ex: Men > Shirts)
+pub fn get_hierarchical_categories(path: &[String]) -> HierarchicalCategories {
+    HierarchicalCategories(
+        full_paths(path)
+            .into_iter()
+            .enumerate()
+            .map(|(level, full_path)| (format!("lvl{}", level), full_path))
+            .collect(),
+    )
+}
+
+pub fn get_list_categories(path: &[String]) -> Vec<String> {
+    path.to_vec()
+}
+
+// The full path of every level below the department
+pub fn get_category_page_ids(path: &[String]) -> Vec<String> {
+    full_paths(path).into_iter().skip(1).collect()
+}
+
+fn full_paths(path: &[String]) -> Vec<String> {
+    (1..=path.len())
+        .map(|depth| path[..depth].join(SEPARATOR))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vtex::model::ProductCategories;
+
+    #[test]
+    fn categories_of_any_depth() {
+        let categories: ProductCategories = serde_json::from_str(
+            r#"{"4": "Polo", "3": "Short Sleeve", "2": "Shirts", "1": "Men"}"#,
+        )
+        .unwrap();
+        let path = categories.path("/1/2/3/4/");
+        let hierarchical = get_hierarchical_categories(&path);
+        assert_eq!(hierarchical.0.len(), 4);
+        assert_eq!(hierarchical.0["lvl3"], "Men > Shirts > Short Sleeve > Polo");
+        assert_eq!(get_list_categories(&path)[3], "Polo");
+        assert_eq!(get_category_page_ids(&path)[0], "Men > Shirts");
+
+        let department = categories.path("/1/");
+        assert_eq!(get_hierarchical_categories(&department).0["lvl0"], "Men");
+        assert!(get_category_page_ids(&department).is_empty());
+    }
+}
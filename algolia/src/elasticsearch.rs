@@ -0,0 +1,55 @@
+use crate::algoliarecords::AlgoliaRecord;
+use crate::exporter::{self, Exporter};
+use crate::settings::IndexSettings;
+use log::*;
+use serde_json::json;
+use std::io::Write;
+use std::{error::Error, fs::File, io::BufWriter};
+
+const BULK_FILE: &str = "data/elasticsearch_bulk.ndjson";
+
+// The records as a _bulk request body: an index action and the document on each pair of lines.
+// The action has no _index so the file is sent to POST /{index}/_bulk
+pub struct ElasticsearchExporter {
+    out: BufWriter<File>,
+    written: usize,
+}
+
+impl ElasticsearchExporter {
+    pub fn new() -> Result<ElasticsearchExporter, Box<dyn Error>> {
+        Ok(ElasticsearchExporter {
+            out: BufWriter::new(File::create(BULK_FILE)?),
+            written: 0,
+        })
+    }
+}
+
+impl Exporter for ElasticsearchExporter {
+    async fn write_page<T: AlgoliaRecord>(
+        &mut self,
+        records: Vec<T>,
+    ) -> Result<(), Box<dyn Error>> {
+        for record in &records {
+            let document = exporter::document(record, None)?;
+            serde_json::to_writer(
+                &mut self.out,
+                &json!({ "index": { "_id": record.object_id() } }),
+            )?;
+            self.out.write_all(b"\n")?;
+            serde_json::to_writer(&mut self.out, &document)?;
+            self.out.write_all(b"\n")?;
+        }
+        self.written += records.len();
+        debug!("documents written: {}", self.written);
+        Ok(())
+    }
+
+    async fn finish(mut self, _settings: &IndexSettings) -> Result<(), Box<dyn Error>> {
+        self.out.flush()?;
+        info!(
+            "Finished writing {} documents to file: {}",
+            self.written, BULK_FILE
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use crate::algoliarecords::AlgoliaRecord;
+use crate::settings::IndexSettings;
+use serde_json::{Map, Value};
+use std::error::Error;
+
+// A search engine the records are exported to. The records are written a page at a time.
+pub trait Exporter {
+    async fn write_page<T: AlgoliaRecord>(&mut self, records: Vec<T>)
+        -> Result<(), Box<dyn Error>>;
+
+    // Finish the output and write the settings (or schema) of the engine
+    async fn finish(self, settings: &IndexSettings) -> Result<(), Box<dyn Error>>;
+}
+
+// The record as a document of the engine - the objectID is written as id_field (if any)
+pub fn document<T: AlgoliaRecord>(
+    record: &T,
+    id_field: Option<&str>,
+) -> Result<Map<String, Value>, Box<dyn Error>> {
+    let mut document = match serde_json::to_value(record)? {
+        Value::Object(document) => document,
+        _ => return Err("record is not a JSON object".into()),
+    };
+    document.remove("objectID");
+    if let Some(id_field) = id_field {
+        document.insert(id_field.to_string(), Value::from(record.object_id()));
+    }
+    Ok(document)
+}
+
+// The attribute names of the settings without the Algolia modifiers (ex: filterOnly(brand))
+pub fn attribute_names(attributes: &[String]) -> Vec<String> {
+    attributes
+        .iter()
+        .map(|attribute| match attribute.split_once('(') {
+            Some((_, name)) => name.trim_end_matches(')').to_string(),
+            None => attribute.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attribute_names_without_modifiers() {
+        let attributes = vec![
+            "brand".to_string(),
+            "filterOnly(category_page_id)".to_string(),
+            "unordered(description)".to_string(),
+        ];
+        assert_eq!(
+            attribute_names(&attributes),
+            vec!["brand", "category_page_id", "description"]
+        );
+    }
+}
@@ -0,0 +1,434 @@
+use crate::algoliarecords::ItemRecord;
+use log::*;
+use std::collections::HashMap;
+use std::io::Write;
+use std::{error::Error, fs::File, io::BufWriter};
+use vtex::model::SkuAndContext;
+use vtex::utils;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FeedFormat {
+    // Google Merchant Center RSS 2.0 feed
+    GoogleXml,
+    // Google Merchant Center tab separated feed
+    GoogleTsv,
+    // Meta (Facebook) catalog CSV
+    MetaCsv,
+}
+
+impl FeedFormat {
+    pub fn from_name(name: &str) -> FeedFormat {
+        match name {
+            "google_tsv" => FeedFormat::GoogleTsv,
+            "meta_csv" => FeedFormat::MetaCsv,
+            _ => FeedFormat::GoogleXml,
+        }
+    }
+
+    fn path(&self) -> &str {
+        match self {
+            FeedFormat::GoogleXml => "data/google_feed.xml",
+            FeedFormat::GoogleTsv => "data/google_feed.tsv",
+            FeedFormat::MetaCsv => "data/meta_catalog.csv",
+        }
+    }
+}
+
+// One item of the feed per sku
+#[derive(Debug, PartialEq, Clone)]
+pub struct FeedItem {
+    pub id: String,
+    pub item_group_id: String,
+    pub title: String,
+    pub description: String,
+    pub link: String,
+    pub image_link: Option<String>,
+    pub additional_image_links: Vec<String>,
+    pub in_stock: bool,
+    pub price: String,
+    pub sale_price: Option<String>,
+    pub gtin: Option<String>,
+    pub brand: String,
+    pub product_type: String,
+    pub shipping_weight: Option<String>,
+}
+
+// The sku as a feed item - None when the sku is not active or has no price
+pub fn build_feed_item(
+    sku_ctx: &SkuAndContext,
+    record: &ItemRecord,
+    store_url: &str,
+) -> Option<FeedItem> {
+    if !sku_ctx.is_active {
+        return None;
+    }
+    let price = record.price.as_ref()?;
+    let format_price = |value: f32| format!("{:.2} {}", value, price.currency);
+    let gtin = sku_ctx
+        .alternate_ids
+        .ean
+        .clone()
+        .filter(|ean| utils::is_gtin(ean))
+        .or_else(|| {
+            sku_ctx
+                .alternate_id_values
+                .iter()
+                .find(|value| utils::is_gtin(value))
+                .cloned()
+        });
+    let mut image_links = record.image_urls.iter();
+    Some(FeedItem {
+        id: record.sku_ref.clone(),
+        item_group_id: sku_ctx.product_ref_id.clone(),
+        title: sku_ctx.name_complete.clone(),
+        description: sku_ctx.product_description.clone(),
+        link: format!("{}{}", store_url.trim_end_matches('/'), sku_ctx.detail_url),
+        image_link: image_links.next().cloned(),
+        // Google allows up to 10 additional images
+        additional_image_links: image_links.take(10).cloned().collect(),
+        in_stock: record.units_in_stock > 0,
+        price: format_price(price.value),
+        sale_price: price.on_sales.then(|| format_price(price.discounted_value)),
+        gtin,
+        brand: sku_ctx.brand_name.clone(),
+        product_type: record.list_categories.join(" > "),
+        shipping_weight: (sku_ctx.dimension.weight > 0.0)
+            .then(|| format!("{} g", sku_ctx.dimension.weight)),
+    })
+}
+
+// The title, link and description are required elements of the RSS 2.0 channel
+fn write_xml_header<W: Write>(out: &mut W, title: &str, store_url: &str) -> std::io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<rss version="2.0" xmlns:g="http://base.google.com/ns/1.0">"#
+    )?;
+    writeln!(out, "<channel>")?;
+    writeln!(out, "<title>{}</title>", escape_xml(title))?;
+    writeln!(out, "<link>{}</link>", escape_xml(store_url))?;
+    writeln!(
+        out,
+        "<description>{}</description>",
+        escape_xml(&format!("Products of {} - {}", title, store_url))
+    )?;
+    Ok(())
+}
+
+enum FeedOutput {
+    Xml(BufWriter<File>),
+    Delimited(Box<csv::Writer<File>>),
+}
+
+// Writes the feed a page at a time
+pub struct FeedWriter {
+    format: FeedFormat,
+    store_url: String,
+    out: FeedOutput,
+    written: usize,
+}
+
+impl FeedWriter {
+    // The title names the channel of the XML feed (ex: the account name)
+    pub fn new(
+        format: FeedFormat,
+        title: &str,
+        store_url: &str,
+    ) -> Result<FeedWriter, Box<dyn Error>> {
+        let out = match format {
+            FeedFormat::GoogleXml => {
+                let mut out = BufWriter::new(File::create(format.path())?);
+                write_xml_header(&mut out, title, store_url)?;
+                FeedOutput::Xml(out)
+            }
+            FeedFormat::GoogleTsv => {
+                let mut wtr = csv::WriterBuilder::new()
+                    .delimiter(b'\t')
+                    .quote_style(csv::QuoteStyle::Never)
+                    .from_path(format.path())?;
+                wtr.write_record(GOOGLE_COLUMNS)?;
+                FeedOutput::Delimited(Box::new(wtr))
+            }
+            FeedFormat::MetaCsv => {
+                let mut wtr = csv::Writer::from_path(format.path())?;
+                wtr.write_record(META_COLUMNS)?;
+                FeedOutput::Delimited(Box::new(wtr))
+            }
+        };
+        Ok(FeedWriter {
+            format,
+            store_url: store_url.to_string(),
+            out,
+            written: 0,
+        })
+    }
+
+    pub fn write_page(
+        &mut self,
+        records: &[ItemRecord],
+        sku_contexts: &HashMap<i32, SkuAndContext>,
+    ) -> Result<(), Box<dyn Error>> {
+        for record in records {
+            let item = match sku_contexts
+                .get(&record.sku_id)
+                .and_then(|sku_ctx| build_feed_item(sku_ctx, record, &self.store_url))
+            {
+                Some(item) => item,
+                None => {
+                    debug!(
+                        "sku_id: {} is not active or has no price. Skipping.",
+                        record.sku_id
+                    );
+                    continue;
+                }
+            };
+            match &mut self.out {
+                FeedOutput::Xml(out) => write_xml_item(out, &item)?,
+                FeedOutput::Delimited(wtr) => {
+                    if self.format == FeedFormat::MetaCsv {
+                        wtr.write_record(meta_row(&item))?;
+                    } else {
+                        let row: Vec<String> = google_row(&item)
+                            .iter()
+                            .map(|value| value.replace(['\t', '\n', '\r'], " "))
+                            .collect();
+                        wtr.write_record(row)?;
+                    }
+                }
+            }
+            self.written += 1;
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<(), Box<dyn Error>> {
+        match self.out {
+            FeedOutput::Xml(mut out) => {
+                writeln!(out, "</channel>")?;
+                writeln!(out, "</rss>")?;
+                out.flush()?;
+            }
+            FeedOutput::Delimited(mut wtr) => wtr.flush()?,
+        }
+        info!(
+            "Finished writing {} feed items to file: {}",
+            self.written,
+            self.format.path()
+        );
+        Ok(())
+    }
+}
+
+const GOOGLE_COLUMNS: [&str; 15] = [
+    "id",
+    "item_group_id",
+    "title",
+    "description",
+    "link",
+    "image_link",
+    "additional_image_link",
+    "availability",
+    "condition",
+    "price",
+    "sale_price",
+    "gtin",
+    "brand",
+    "product_type",
+    "shipping_weight",
+];
+
+fn google_row(item: &FeedItem) -> [String; 15] {
+    [
+        item.id.clone(),
+        item.item_group_id.clone(),
+        item.title.clone(),
+        item.description.clone(),
+        item.link.clone(),
+        item.image_link.clone().unwrap_or_default(),
+        item.additional_image_links.join(","),
+        if item.in_stock {
+            "in_stock"
+        } else {
+            "out_of_stock"
+        }
+        .to_string(),
+        "new".to_string(),
+        item.price.clone(),
+        item.sale_price.clone().unwrap_or_default(),
+        item.gtin.clone().unwrap_or_default(),
+        item.brand.clone(),
+        item.product_type.clone(),
+        item.shipping_weight.clone().unwrap_or_default(),
+    ]
+}
+
+fn write_xml_item(out: &mut BufWriter<File>, item: &FeedItem) -> Result<(), Box<dyn Error>> {
+    writeln!(out, "<item>")?;
+    for (name, value) in GOOGLE_COLUMNS.iter().zip(google_row(item)) {
+        if value.is_empty() {
+            continue;
+        }
+        if *name == "additional_image_link" {
+            for link in &item.additional_image_links {
+                writeln!(out, "<g:{0}>{1}</g:{0}>", name, escape_xml(link))?;
+            }
+        } else {
+            writeln!(out, "<g:{0}>{1}</g:{0}>", name, escape_xml(&value))?;
+        }
+    }
+    writeln!(out, "</item>")?;
+    Ok(())
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+const META_COLUMNS: [&str; 14] = [
+    "id",
+    "title",
+    "description",
+    "availability",
+    "condition",
+    "price",
+    "link",
+    "image_link",
+    "brand",
+    "sale_price",
+    "item_group_id",
+    "gtin",
+    "product_type",
+    "additional_image_link",
+];
+
+fn meta_row(item: &FeedItem) -> [String; 14] {
+    [
+        item.id.clone(),
+        item.title.clone(),
+        item.description.clone(),
+        if item.in_stock {
+            "in stock"
+        } else {
+            "out of stock"
+        }
+        .to_string(),
+        "new".to_string(),
+        item.price.clone(),
+        item.link.clone(),
+        item.image_link.clone().unwrap_or_default(),
+        item.brand.clone(),
+        item.sale_price.clone().unwrap_or_default(),
+        item.item_group_id.clone(),
+        item.gtin.clone().unwrap_or_default(),
+        item.product_type.clone(),
+        item.additional_image_links.join(","),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algoliarecords::Price;
+
+    #[test]
+    fn xml_escape() {
+        assert_eq!(
+            escape_xml(r#"Shirts & "Tees" <Men>"#),
+            "Shirts &amp; &quot;Tees&quot; &lt;Men&gt;"
+        );
+    }
+
+    #[test]
+    fn xml_header_has_channel_title_and_description() {
+        let mut out: Vec<u8> = Vec::new();
+        write_xml_header(&mut out, "mystore", "https://www.mystore.com").unwrap();
+        let header = String::from_utf8(out).unwrap();
+        assert!(header.contains("<title>mystore</title>"));
+        assert!(header.contains("<link>https://www.mystore.com</link>"));
+        assert!(header
+            .contains("<description>Products of mystore - https://www.mystore.com</description>"));
+    }
+
+    fn feed_record(price: Option<Price>) -> ItemRecord {
+        let mut record: ItemRecord = serde_json::from_value(serde_json::json!({
+            "sku_id": 1,
+            "sku": "0094124836",
+            "product_id": 28,
+            "parentID": "P000206871",
+            "name": "Van Heusen Grid Sport Shirt",
+            "description": "",
+            "slug": "van-heusen-grid-sport-shirt-p000206871",
+            "brand": "Van Heusen",
+            "hierarchical_categories": {},
+            "list_categories": ["Men", "Mens Apparel", "Casual Short Sleeve Shirts"],
+            "category_page_id": [],
+            "image_urls": ["https://img/1.jpg", "https://img/2.jpg"],
+            "variants": [],
+            "units_in_stock": 3,
+            "objectID": "1"
+        }))
+        .unwrap();
+        record.price = price;
+        record
+    }
+
+    fn feed_price(on_sales: bool) -> Price {
+        Price {
+            currency: "USD".to_string(),
+            value: 100.0,
+            discounted_value: if on_sales { 75.0 } else { 100.0 },
+            discount_level: if on_sales { 25.0 } else { 0.0 },
+            on_sales,
+        }
+    }
+
+    #[test]
+    fn feed_item_from_sku_and_record() {
+        let mut sku_ctx: SkuAndContext =
+            serde_json::from_str(include_str!("../get_sku_and_context.json")).unwrap();
+        // The RefId is not a GTIN - the gtin falls back to the alternate id values
+        sku_ctx
+            .alternate_id_values
+            .push("7891234567895".to_string());
+        let store_url = "https://www.mystore.com/";
+
+        let item =
+            build_feed_item(&sku_ctx, &feed_record(Some(feed_price(true))), store_url).unwrap();
+        assert_eq!(item.id, "0094124836");
+        assert_eq!(item.item_group_id, "P000206871");
+        assert_eq!(
+            item.link,
+            "https://www.mystore.com/van-heusen-grid-sport-shirt-p000206871/p"
+        );
+        assert_eq!(item.image_link.as_deref(), Some("https://img/1.jpg"));
+        assert_eq!(item.additional_image_links, vec!["https://img/2.jpg"]);
+        assert!(item.in_stock);
+        assert_eq!(item.price, "100.00 USD");
+        assert_eq!(item.sale_price.as_deref(), Some("75.00 USD"));
+        assert_eq!(item.gtin.as_deref(), Some("7891234567895"));
+        assert_eq!(
+            item.product_type,
+            "Men > Mens Apparel > Casual Short Sleeve Shirts"
+        );
+
+        // Not on sale: the price is the selling price and there is no sale_price
+        let mut record = feed_record(Some(feed_price(false)));
+        record.units_in_stock = 0;
+        let item = build_feed_item(&sku_ctx, &record, store_url).unwrap();
+        assert!(!item.in_stock);
+        assert_eq!(item.price, "100.00 USD");
+        assert_eq!(item.sale_price, None);
+
+        // No price and inactive skus are not in the feed
+        assert!(build_feed_item(&sku_ctx, &feed_record(None), store_url).is_none());
+        sku_ctx.is_active = false;
+        assert!(
+            build_feed_item(&sku_ctx, &feed_record(Some(feed_price(false))), store_url).is_none()
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use vtex::model::{Balance, InventoryList};
+
+// Units in stock written for a sku with unlimited quantity in one of the warehouses
+pub const UNLIMITED_STOCK: i32 = 1_000_000;
+
+// The warehouses counted in the stock of a sku
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct StockConfig {
+    // None counts every warehouse
+    pub warehouses: Option<Vec<String>>,
+    // Adds the stock of each warehouse to the records (ex: for store pickup)
+    pub per_warehouse: bool,
+}
+
+impl StockConfig {
+    fn balances<'a>(
+        &'a self,
+        inventory: Option<&'a InventoryList>,
+    ) -> impl Iterator<Item = &'a Balance> {
+        inventory
+            .into_iter()
+            .flat_map(|inventory| inventory.balance.iter())
+            .filter(move |balance| match &self.warehouses {
+                Some(warehouses) => warehouses.contains(&balance.warehouse_id),
+                None => true,
+            })
+    }
+
+    // The sum of the available quantity (total minus reserved) of the warehouses
+    pub fn units_in_stock(&self, inventory: Option<&InventoryList>) -> i32 {
+        self.balances(inventory)
+            .map(available_quantity)
+            .fold(0, i32::saturating_add)
+            .min(UNLIMITED_STOCK)
+    }
+
+    // stock_by_warehouse (available quantity by warehouse_id) and warehouses_in_stock
+    pub fn warehouse_attributes(
+        &self,
+        inventory: Option<&InventoryList>,
+    ) -> BTreeMap<String, serde_json::Value> {
+        let mut attributes = BTreeMap::new();
+        if !self.per_warehouse {
+            return attributes;
+        }
+        let stock: BTreeMap<String, i32> = self
+            .balances(inventory)
+            .map(|balance| (balance.warehouse_id.clone(), available_quantity(balance)))
+            .collect();
+        let in_stock: Vec<String> = stock
+            .iter()
+            .filter(|(_, quantity)| **quantity > 0)
+            .map(|(warehouse_id, _)| warehouse_id.clone())
+            .collect();
+        attributes.insert(
+            "stock_by_warehouse".to_string(),
+            serde_json::to_value(stock).unwrap(),
+        );
+        attributes.insert(
+            "warehouses_in_stock".to_string(),
+            serde_json::Value::from(in_stock),
+        );
+        attributes
+    }
+}
+
+fn available_quantity(balance: &Balance) -> i32 {
+    if balance.has_unlimited_quantity {
+        UNLIMITED_STOCK
+    } else {
+        (balance.total_quantity - balance.reserved_quantity).max(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn balance(warehouse_id: &str, total: i32, reserved: i32, unlimited: bool) -> Balance {
+        Balance {
+            warehouse_id: warehouse_id.to_string(),
+            warehouse_name: warehouse_id.to_string(),
+            total_quantity: total,
+            reserved_quantity: reserved,
+            has_unlimited_quantity: unlimited,
+            time_to_refill: None,
+            date_of_supply_utc: None,
+        }
+    }
+
+    #[test]
+    fn units_in_stock_by_warehouse() {
+        let inventory = InventoryList {
+            sku_id: "1".to_string(),
+            balance: vec![
+                balance("wh1", 10, 3, false),
+                balance("wh2", 2, 5, false),
+                balance("store1", 4, 0, false),
+            ],
+        };
+        let all = StockConfig::default();
+        assert_eq!(all.units_in_stock(Some(&inventory)), 11);
+        assert_eq!(all.units_in_stock(None), 0);
+
+        let stores = StockConfig {
+            warehouses: Some(vec!["wh2".to_string(), "store1".to_string()]),
+            per_warehouse: true,
+        };
+        assert_eq!(stores.units_in_stock(Some(&inventory)), 4);
+        let attributes = stores.warehouse_attributes(Some(&inventory));
+        assert_eq!(
+            attributes["warehouses_in_stock"],
+            serde_json::json!(["store1"])
+        );
+        assert_eq!(
+            attributes["stock_by_warehouse"],
+            serde_json::json!({"store1": 4, "wh2": 0})
+        );
+
+        let unlimited = InventoryList {
+            sku_id: "2".to_string(),
+            balance: vec![balance("wh1", 0, 0, true), balance("wh2", 5, 0, false)],
+        };
+        assert_eq!(all.units_in_stock(Some(&unlimited)), UNLIMITED_STOCK);
+    }
+}
@@ -1,28 +1,50 @@
-use algoliarecords::{HierarchicalCategories, Price, Review, Variant};
-use futures::{join, stream, StreamExt};
+use algoliarecords::{Price, PriceRange, ProductRecord, Review, Variant};
+use clap::{crate_version, App, Arg};
+use elasticsearch::ElasticsearchExporter;
+use exporter::Exporter;
+use feed::{FeedFormat, FeedWriter};
+use futures::join;
+use inventory::StockConfig;
 use log::*;
-use rand::Rng;
+use mapping::MappingConfig;
+use meilisearch::MeilisearchExporter;
+use output::{AlgoliaExporter, OutputFormat};
+use reviews::ReviewPrior;
 use serde::{Deserialize, Serialize};
+use settings::SettingsBuilder;
 use std::io::Write;
-use std::sync::{Arc, Mutex};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     env,
     error::Error,
-    fs::File,
-    io::BufWriter,
     sync::Once,
-    time::{Duration, Instant},
+    time::Duration,
 };
+use typesense::TypesenseExporter;
+use upload::AlgoliaConfig;
 
-use reqwest::{header, Client, StatusCode};
-use vtex::model::{Image, InventoryList, PriceGet, SkuAndContext, SkuSpecification};
+use reqwest::{header, Client};
+use vtex::model::{Image, InventoryList, SkuAndContext};
+use vtex::snapshot::{CatalogSource, Snapshot};
+use vtex::utils;
 
 use crate::algoliarecords::ItemRecord;
 
 mod algoliarecords;
-
-const CONCURRENT_REQUESTS: usize = 12;
+mod categories;
+mod elasticsearch;
+mod exporter;
+mod feed;
+mod inventory;
+mod mapping;
+mod meilisearch;
+mod output;
+mod pricing;
+mod reviews;
+mod settings;
+mod snapshot;
+mod typesense;
+mod upload;
 
 static INIT: Once = Once::new();
 
@@ -38,271 +60,6 @@ pub fn setup() {
     })
 }
 
-pub async fn get_all_sku_ids_by_page(page: i32, client: &Client, sku_ids: &mut Vec<i32>) -> i32 {
-    let url = "https://michaelvb.vtexcommercestable.com.br/api/catalog_system/pvt/sku/stockkeepingunitids?page={page}&pagesize=1000".to_string().replace("{page}", page.to_string().as_str());
-    let response = client.get(url).send().await.unwrap();
-
-    // println!("response.status: {}", response.status());
-    match response.status() {
-        StatusCode::OK => {
-            let response_text = response.text().await.unwrap();
-            let ids = response_text.replace("[", "").replace("]", "");
-            let iter = ids.split(',');
-            let mut x = 0;
-            // println!("ids: {:?}", ids);
-            // let mut ids_response: Vec<i32> = Vec::new();
-            for v in iter {
-                sku_ids.push(v.parse::<i32>().unwrap());
-                x += 1;
-            }
-            x
-        }
-        _ => {
-            panic!(
-                "Status Code: [{:?}] Error: [{:#?}]",
-                response.status(),
-                response.text().await
-            )
-        }
-    }
-}
-
-fn build_get_sku_urls(sku_ids: &[i32]) -> Vec<String> {
-    let url = "https://michaelvb.vtexcommercestable.com.br/api/catalog_system/pvt/sku/stockkeepingunitbyid/{skuId}?sc=1".to_string();
-    let mut urls: Vec<String> = Vec::with_capacity(sku_ids.len());
-    for sku_id in sku_ids {
-        let url = url.replace("{skuId}", sku_id.to_string().as_str());
-        urls.push(url);
-    }
-    debug!("sku urls.len(): {}", urls.len());
-    urls
-}
-
-pub async fn get_sku_and_context(sku_id: &i32, client: &Client) -> SkuAndContext {
-    let url = "https://michaelvb.vtexcommercestable.com.br/api/catalog_system/pvt/sku/stockkeepingunitbyid/{skuId}?sc=1".to_string().replace("{skuId}", sku_id.to_string().as_str());
-    let response = client.get(url).send().await.unwrap();
-
-    // println!("response.status: {}", response.status());
-    match response.status() {
-        StatusCode::OK => {
-            let response_text = response.text().await.unwrap();
-            // println!("response_text: {:?}", response_text);
-            let result: Result<SkuAndContext, serde_json::Error> =
-                serde_json::from_str(&response_text);
-            match result {
-                Ok(sku_and_context) => {
-                    // println!("sku_and_context: {:?}", sku_and_context);
-                    sku_and_context
-                }
-                Err(e) => {
-                    // println!("deserialize product error: {:?}", e);
-                    panic!("deserialize product error: {:?}", e)
-                }
-            }
-        }
-        _ => {
-            panic!(
-                "Status Code: [{:?}] Error: [{:#?}]",
-                response.status(),
-                response.text().await
-            )
-        }
-    }
-}
-
-fn build_get_price_urls(sku_ids: &[i32]) -> Vec<String> {
-    let url = "https://api.vtex.com/michaelvb/pricing/prices/{skuId}".to_string();
-    let mut urls: Vec<String> = Vec::with_capacity(sku_ids.len());
-    for sku_id in sku_ids {
-        let url = url.replace("{skuId}", sku_id.to_string().as_str());
-        urls.push(url);
-    }
-    debug!("price urls.len(): {}", urls.len());
-    urls
-}
-
-fn build_price_for_algolia(vtex_price: &PriceGet) -> Price {
-    Price {
-        value: vtex_price.base_price.unwrap(),
-        currency: "USD".to_string(),
-        on_sales: false,
-        discount_level: -100.00,
-        discounted_value: 0.00,
-    }
-}
-
-pub async fn get_price(sku_id: &i32, client: &Client) -> Price {
-    let url = "https://api.vtex.com/michaelvb/pricing/prices/{skuId}"
-        .to_string()
-        .replace("{skuId}", sku_id.to_string().as_str());
-    let response = client.get(url).send().await.unwrap();
-
-    // println!("response.status: {}", response.status());
-    match response.status() {
-        StatusCode::OK => {
-            let response_text = response.text().await.unwrap();
-            // println!("response_text: {:?}", response_text);
-            let result: Result<vtex::model::PriceGet, serde_json::Error> =
-                serde_json::from_str(&response_text);
-            match result {
-                Ok(vtex_price) => {
-                    // println!("vtex_price: {:?}", vtex_price);
-                    Price {
-                        value: vtex_price.base_price.unwrap(),
-                        currency: "USD".to_string(),
-                        on_sales: false,
-                        discount_level: -100.00,
-                        discounted_value: 0.00,
-                    }
-                }
-                Err(e) => {
-                    // println!("deserialize product error: {:?}", e);
-                    panic!("deserialize Price error: {:?}", e)
-                }
-            }
-        }
-        _ => {
-            panic!(
-                "Status Code: [{:?}] Error: [{:#?}]",
-                response.status(),
-                response.text().await
-            )
-        }
-    }
-}
-
-fn build_get_inventory_urls(sku_ids: &[i32]) -> Vec<String> {
-    let url =
-        "https://michaelvb.vtexcommercestable.com.br/api/logistics/pvt/inventory/skus/{skuId}"
-            .to_string();
-    let mut urls: Vec<String> = Vec::with_capacity(sku_ids.len());
-    for sku_id in sku_ids {
-        let url = url.replace("{skuId}", sku_id.to_string().as_str());
-        urls.push(url);
-    }
-    debug!("inventory urls.len(): {}", urls.len());
-    urls
-}
-
-fn get_inventory_for_algolia(vtex_inventory: &InventoryList) -> i32 {
-    let mut quantity = 0;
-    for balance in &vtex_inventory.balance {
-        if balance.warehouse_id.eq("warehouse1") {
-            quantity = balance.total_quantity;
-        }
-    }
-    quantity
-}
-
-pub async fn get_inventory(sku_id: &i32, client: &Client) -> i32 {
-    let url =
-        "https://michaelvb.vtexcommercestable.com.br/api/logistics/pvt/inventory/skus/{skuId}"
-            .to_string()
-            .replace("{skuId}", sku_id.to_string().as_str());
-    let response = client.get(url).send().await.unwrap();
-
-    // println!("response.status: {}", response.status());
-    match response.status() {
-        StatusCode::OK => {
-            let response_text = response.text().await.unwrap();
-            // println!("response_text: {:?}", response_text);
-            let result: Result<vtex::model::InventoryList, serde_json::Error> =
-                serde_json::from_str(&response_text);
-            match result {
-                Ok(vtex_inventory) => {
-                    // println!("vtex_inventory: {:?}", vtex_inventory);
-                    let mut quantity = 0;
-                    for balance in vtex_inventory.balance {
-                        if balance.warehouse_id.eq("wareshouse1") {
-                            quantity = balance.total_quantity;
-                        }
-                    }
-                    quantity
-                }
-                Err(e) => {
-                    // println!("deserialize product error: {:?}", e);
-                    panic!("deserialize InventoryList error: {:?}", e)
-                }
-            }
-        }
-        _ => {
-            panic!(
-                "Status Code: [{:?}] Error: [{:#?}]",
-                response.status(),
-                response.text().await
-            )
-        }
-    }
-}
-
-fn get_hierarchical_categories(
-    categories: &serde_json::Value,
-    product_category_ids: &str,
-) -> HierarchicalCategories {
-    let cats = categories.as_object().unwrap();
-    let lvl_keys: Vec<&str> = product_category_ids.split('/').collect();
-    debug!("lvl_keys: {:?}", lvl_keys);
-    // let mut cat_names: Vec<String> = Vec::new();
-    // use a LinkedHashSet to preserve order
-    let mut cat_names = HashMap::new();
-    for cat in cats {
-        // println!("cat: {:?}", cat.1.as_str().unwrap());
-        cat_names.insert(cat.0.as_str(), cat.1.as_str().unwrap().to_string());
-    }
-    debug!("cat_names: {:?}", cat_names);
-    // Pull the records in order off the back of the HashSet - this is how
-    // VTEX provides the categories - lvl2, lvl1, lvl0
-    let lvl0 = cat_names.get(lvl_keys[1]).unwrap().to_string();
-    let lvl1 = lvl0.clone() + " > " + cat_names.get(lvl_keys[2]).unwrap().as_str();
-    let lvl2 = lvl1.to_owned() + " > " + cat_names.get(lvl_keys[3]).unwrap().as_str();
-    HierarchicalCategories { lvl0, lvl1, lvl2 }
-}
-
-fn get_list_categories(categories: &serde_json::Value, product_category_ids: &str) -> Vec<String> {
-    let cats = categories.as_object().unwrap();
-    let lvl_keys: Vec<&str> = product_category_ids.split('/').collect();
-    debug!("lvl_keys: {:?}", lvl_keys);
-    // let mut cat_names: Vec<String> = Vec::new();
-    // use a LinkedHashSet to preserve order
-    let mut cat_names = HashMap::new();
-    for cat in cats {
-        // println!("cat: {:?}", cat.1.as_str().unwrap());
-        cat_names.insert(cat.0.as_str(), cat.1.as_str().unwrap().to_string());
-    }
-    debug!("cat_names: {:?}", cat_names);
-    // Pull the records in order off the back of the HashSet - this is how
-    // VTEX provides the categories - lvl2, lvl1, lvl0
-    let lvl0 = cat_names.get(lvl_keys[1]).unwrap().to_string();
-    let lvl1 = cat_names.get(lvl_keys[2]).unwrap().to_string();
-    let lvl2 = cat_names.get(lvl_keys[3]).unwrap().to_string();
-
-    vec![lvl0, lvl1, lvl2]
-}
-
-fn get_category_page_ids(
-    categories: &serde_json::Value,
-    product_category_ids: &str,
-) -> Vec<String> {
-    let cats = categories.as_object().unwrap();
-    let lvl_keys: Vec<&str> = product_category_ids.split('/').collect();
-    debug!("lvl_keys: {:?}", lvl_keys);
-    // let mut cat_names: Vec<String> = Vec::new();
-    // use a LinkedHashSet to preserve order
-    let mut cat_names = HashMap::new();
-    for cat in cats {
-        // println!("cat: {:?}", cat.1.as_str().unwrap());
-        cat_names.insert(cat.0.as_str(), cat.1.as_str().unwrap().to_string());
-    }
-    debug!("cat_names: {:?}", cat_names);
-    // Pull the records in order off the back of the HashSet - this is how
-    // VTEX provides the categories - lvl2, lvl1, lvl0
-    let lvl0 = cat_names.get(lvl_keys[1]).unwrap().to_string();
-    let lvl1 = lvl0 + " > " + cat_names.get(lvl_keys[2]).unwrap().as_str();
-    let lvl2 = lvl1.to_owned() + " > " + cat_names.get(lvl_keys[3]).unwrap().as_str();
-
-    vec![lvl1, lvl2]
-}
-
 fn get_image_urls(images: &Option<Vec<Image>>) -> Vec<String> {
     let mut result: Vec<String> = Vec::new();
     for image in images.as_ref().unwrap() {
@@ -311,195 +68,12 @@ fn get_image_urls(images: &Option<Vec<Image>>) -> Vec<String> {
     result
 }
 
-fn get_color(sku_specs: &Option<Vec<SkuSpecification>>) -> Option<String> {
-    let mut color: String = String::new();
-    for spec in sku_specs.as_ref().unwrap() {
-        if spec.field_name.eq("Color") {
-            color = spec.field_values[0].clone();
-        }
-    }
-    if !color.is_empty() {
-        Some(color)
-    } else {
-        None
-    }
-}
-
-fn get_size(sku_specs: &Option<Vec<SkuSpecification>>) -> Option<String> {
-    let mut size: String = String::new();
-    for spec in sku_specs.as_ref().unwrap() {
-        if spec.field_name.eq("Size") {
-            size = spec.field_values[0].clone();
-        }
-    }
-    if !size.is_empty() {
-        Some(size)
-    } else {
-        None
-    }
-}
-
-// fn get_variants(sku_ctx: &SkuAndContext) -> Vec<Variant> {
-//     let variant = Variant {
-//         sku_ref: sku_ctx.alternate_ids.ref_id.clone(),
-//         in_stock: true,
-//         abbreviated_color: get_color(&sku_ctx.sku_specifications),
-//         abbreviated_size: get_size(&sku_ctx.sku_specifications),
-//     };
-//     vec![variant]
-// }
-
-async fn get_all_sku_ids(client: &Client) -> Vec<i32> {
-    let start = Instant::now();
-    info!("Start get_all_sku_ids()");
-    // Get all the skus
-    let sku_ids: &mut Vec<i32> = &mut Vec::new();
-    let recs = &mut 1000;
-    let page = &mut 1;
-
-    while *recs == 1000 {
-        *recs = get_all_sku_ids_by_page(*page, client, sku_ids).await;
-        *page += 1;
-    }
-    let duration = start.elapsed();
-    info!(
-        "Finished get_all_sku_ids: {} records in {:?}",
-        sku_ids.len(),
-        duration
-    );
-    sku_ids.to_vec()
-}
-
-async fn get_item_records(sku_ids: &[i32], client: &Client) -> HashMap<i32, SkuAndContext> {
-    info!("Starting get_item_records()");
-    // Build the urls
-    let urls = build_get_sku_urls(sku_ids);
-    debug!("after call to build_get_sku_urls");
-    let item_recs: Arc<Mutex<HashMap<i32, SkuAndContext>>> = Arc::new(Mutex::new(HashMap::new()));
-    let bodies = stream::iter(urls)
-        .map(|url| {
-            let client = &client;
-            async move {
-                let resp = client.get(url.clone()).send().await?;
-
-                // let sctx: SkuAndContext = resp.json().await?;
-                debug!("end of async move - url: {}", url);
-                // resp.text().await
-                resp.json::<SkuAndContext>().await
-            }
-        })
-        .buffer_unordered(CONCURRENT_REQUESTS);
-    bodies
-        .for_each(|b| async {
-            let item_recs = item_recs.clone();
-            match b {
-                Ok(b) => {
-                    // let result: Result<SkuAndContext, serde_json::Error> = serde_json::from_str(b).unwrap();
-                    let sku_ctx: SkuAndContext = b;
-                    let mut item_recs = item_recs.lock().unwrap();
-                    item_recs.insert(sku_ctx.id, sku_ctx.clone());
-                    debug!("Got: {:?}", sku_ctx)
-                }
-                Err(e) => error!("Got an error: {}", e),
-            }
-        })
-        .await;
-
-    let ir = item_recs.lock().unwrap().clone();
-    info!(
-        "finished get_item_records(): item_recs.len(): {:?}",
-        ir.len()
-    );
-    ir
-}
-
-async fn get_price_records(sku_ids: &[i32], client: &Client) -> HashMap<i32, PriceGet> {
-    info!("Starting get_price_records()");
-    // build the urls
-    let urls = build_get_price_urls(sku_ids);
-    let price_recs: Arc<Mutex<HashMap<i32, PriceGet>>> = Arc::new(Mutex::new(HashMap::new()));
-    let bodies = stream::iter(urls)
-        .map(|url| {
-            let client = &client;
-            async move {
-                let resp = client.get(url.clone()).send().await?;
-                resp.json::<PriceGet>().await
-            }
-        })
-        .buffer_unordered(CONCURRENT_REQUESTS);
-    bodies
-        .for_each(|b| async {
-            let price_recs = price_recs.clone();
-            match b {
-                Ok(b) => {
-                    let price_list: PriceGet = b;
-                    let mut price_recs = price_recs.lock().unwrap();
-                    price_recs.insert(
-                        price_list.item_id.parse::<i32>().unwrap(),
-                        price_list.clone(),
-                    );
-                    debug!("Got price_list.item_id: {:?}", price_list.item_id)
-                }
-                Err(e) => error!("Got an error: {}", e),
-            }
-        })
-        .await;
-
-    let pr = price_recs.lock().unwrap().clone();
-    info!(
-        "finished get_price_records(): price_recs.len(): {:?}",
-        pr.len()
-    );
-    pr
-}
-
-async fn get_inventory_records(sku_ids: &[i32], client: &Client) -> HashMap<i32, InventoryList> {
-    info!("Starting get_inventory_records()");
-    // build the urls
-    let urls = build_get_inventory_urls(sku_ids);
-    let inventory_recs: Arc<Mutex<HashMap<i32, InventoryList>>> =
-        Arc::new(Mutex::new(HashMap::new()));
-    let bodies = stream::iter(urls)
-        .map(|url| {
-            let client = &client;
-            async move {
-                let resp = client.get(url.clone()).send().await?;
-                resp.json::<InventoryList>().await
-            }
-        })
-        .buffer_unordered(CONCURRENT_REQUESTS);
-    bodies
-        .for_each(|b| async {
-            let inventory_recs = inventory_recs.clone();
-            match b {
-                Ok(b) => {
-                    let inventory_list: InventoryList = b;
-                    let mut inventory_recs = inventory_recs.lock().unwrap();
-                    inventory_recs.insert(
-                        inventory_list.sku_id.parse::<i32>().unwrap(),
-                        inventory_list.clone(),
-                    );
-                    debug!("Got inventory_list.sku_id: {:?}", inventory_list.sku_id)
-                }
-                Err(e) => error!("Got an error: {}", e),
-            }
-        })
-        .await;
-
-    let invr = inventory_recs.lock().unwrap().clone();
-    info!(
-        "finished get_inventory_records(): inventory_recs.len(): {:?}",
-        invr.len()
-    );
-    invr
-}
-
-// Struct to hold key attributes (Size, Color) and variants
+// Struct to hold the available values of each variant dimension and the variants
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 struct ProductVariant {
     product_ref_id: String,
-    available_sizes: Option<Vec<String>>,
-    available_colors: Option<Vec<String>>,
+    // keyed by the available attribute name (ex: available_colors)
+    available: BTreeMap<String, Vec<String>>,
     variants: Vec<Variant>,
 }
 
@@ -507,6 +81,8 @@ fn build_product_variant_map(
     sku_ids: &[i32],
     item_records: &HashMap<i32, SkuAndContext>,
     inventory_records: &HashMap<i32, InventoryList>,
+    mapping: &MappingConfig,
+    stock: &StockConfig,
 ) -> HashMap<String, ProductVariant> {
     info!("Start build_product_variant_map()");
     let mut product_variants: HashMap<String, ProductVariant> =
@@ -515,98 +91,40 @@ fn build_product_variant_map(
     for sku_id in sku_ids {
         // Lookup key values
         let item_record = item_records.get(sku_id).unwrap();
-        let sku_specs = &item_record.sku_specifications;
-        let color = get_color(&sku_specs.clone());
-        let size = get_size(sku_specs);
-        let inventory_record = inventory_records.get(sku_id).unwrap();
-        let in_stock: bool;
-        if get_inventory_for_algolia(inventory_record) > 0 {
-            in_stock = true;
-        } else {
-            in_stock = false;
-        };
+        let in_stock = stock.units_in_stock(inventory_records.get(sku_id)) > 0;
         let variant: Variant = Variant {
             sku_ref: item_record.alternate_ids.ref_id.clone(),
-            abbreviated_color: color.clone(),
-            abbreviated_size: size.clone(),
+            attributes: mapping.variant_attributes(item_record),
             in_stock,
+            price: None,
+            units_in_stock: None,
         };
-        // First Product Variant or not
-        if !product_variants.contains_key(&item_record.product_ref_id) {
-            let mut available_colors = Vec::new();
-            let mut available_sizes = Vec::new();
-            let mut variants = Vec::new();
-            if let Some(color) = color {
-                available_colors.push(color);
-            }
-            // if color.is_some() {
-            //     available_colors.push(color.unwrap().clone())
-            // };
-            // available_colors.push(color.clone());
-            // let option_avail_colors: Option<Vec<String>> =
-            //     available_colors.m
-            if let Some(size) = size {
-                available_sizes.push(size);
-            }
-            // if size.is_some() {
-            //     available_sizes.push(size.unwrap().clone())
-            // };
-            // available_sizes.push(size.clone());
-            variants.push(variant);
-            let prod_variant: ProductVariant = ProductVariant {
+        let product_variant = product_variants
+            .entry(item_record.product_ref_id.clone())
+            .or_insert_with(|| ProductVariant {
                 product_ref_id: item_record.product_ref_id.clone(),
-                available_colors: Some(available_colors),
-                available_sizes: Some(available_sizes),
-                variants,
-            };
-            product_variants.insert(item_record.product_ref_id.clone(), prod_variant);
-            debug!(
-                "inserted new product_variant: {}",
-                item_record.product_ref_id
-            );
-        } else {
-            let product_variant = product_variants
-                .get_mut(&item_record.product_ref_id)
+                available: mapping
+                    .variant_dimensions()
+                    .map(|dimension| (dimension.available_attribute(), Vec::new()))
+                    .collect(),
+                variants: Vec::new(),
+            });
+        for dimension in mapping.variant_dimensions() {
+            let available = product_variant
+                .available
+                .get_mut(&dimension.available_attribute())
                 .unwrap();
-            if product_variant.available_colors.is_some() {
-                if let Some(color) = color {
-                    //product_variant.available_colors.as_ref().unwrap().push(color.unwrap());
-                    if !product_variant
-                        .available_colors
-                        .as_ref()
-                        .unwrap()
-                        .contains(&color)
-                    {
-                        product_variant
-                            .available_colors
-                            .as_mut()
-                            .unwrap()
-                            .push(color);
-                    }
-                }
-            }
-            if product_variant.available_sizes.is_some() {
-                if let Some(size) = size {
-                    // product_variant.available_sizes.unwrap().push(size.unwrap());
-                    if !product_variant
-                        .available_sizes
-                        .as_ref()
-                        .unwrap()
-                        .contains(&size)
-                    {
-                        product_variant.available_sizes.as_mut().unwrap().push(size);
-                    }
+            for value in dimension.values(item_record) {
+                if !available.contains(&value) {
+                    available.push(value);
                 }
             }
-            // let v: &mut Vec<Variant> = product_variant.variants.as_mut();
-            // info!("product_variant value: {:?}", v);
-            // v.push(variant);
-            product_variant.variants.push(variant);
-            debug!(
-                "updated product_variant: {}",
-                product_variant.product_ref_id
-            );
         }
+        product_variant.variants.push(variant);
+        debug!(
+            "updated product_variant: {}",
+            product_variant.product_ref_id
+        );
     }
     info!(
         "Finished build_product_variant_map(): {} records",
@@ -638,83 +156,424 @@ fn build_product_variant_map(
 //     info!("skus with missing images: {}", x);
 // }
 
-fn generate_review() -> Review {
-    let mut rng = rand::thread_rng();
-    let bay_avg = format!("{:.1$}", rng.gen_range(0.0..5.0), 2)
-        .parse::<f32>()
-        .unwrap();
-    // let rating = format!("{:.1$}", bay_avg, 0);
-    Review {
-        rating: bay_avg.round() as i32,
-        count: rng.gen_range(0..200),
-        bayesian_avg: bay_avg,
+// Group the sku records by product. The variants get the price and stock of their sku.
+fn build_product_records(
+    item_records: &[ItemRecord],
+    product_variants: &HashMap<String, ProductVariant>,
+    sku_contexts: &HashMap<i32, SkuAndContext>,
+    mapping: &MappingConfig,
+) -> Vec<ProductRecord> {
+    info!("Starting product record build");
+    let mut product_recs: Vec<ProductRecord> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let item_by_sku_ref: HashMap<&str, &ItemRecord> = item_records
+        .iter()
+        .map(|item| (item.sku_ref.as_str(), item))
+        .collect();
+    for item in item_records {
+        if positions.contains_key(&item.parent_ref) {
+            let product_rec = &mut product_recs[positions[&item.parent_ref]];
+            for image_url in &item.image_urls {
+                if !product_rec.image_urls.contains(image_url) {
+                    product_rec.image_urls.push(image_url.clone());
+                }
+            }
+            continue;
+        }
+        let product_variant = product_variants.get(&item.parent_ref).unwrap();
+        // Product specifications are the same for all the skus of the product
+        let mut attributes = mapping.product_attributes(sku_contexts.get(&item.sku_id).unwrap());
+        for (name, values) in &product_variant.available {
+            attributes.insert(name.clone(), serde_json::Value::from(values.clone()));
+        }
+        let variants: Vec<Variant> = product_variant
+            .variants
+            .iter()
+            .map(|variant| {
+                let sku_item = item_by_sku_ref.get(variant.sku_ref.as_str());
+                Variant {
+                    price: sku_item.and_then(|sku_item| sku_item.price.clone()),
+                    units_in_stock: sku_item.map(|sku_item| sku_item.units_in_stock),
+                    ..variant.clone()
+                }
+            })
+            .collect();
+        // Store pickup: the product is in stock in a warehouse when any of its skus is
+        let warehouses_in_stock: BTreeSet<&str> = product_variant
+            .variants
+            .iter()
+            .filter_map(|variant| item_by_sku_ref.get(variant.sku_ref.as_str()))
+            .filter_map(|sku_item| sku_item.attributes.get("warehouses_in_stock"))
+            .filter_map(|warehouses| warehouses.as_array())
+            .flatten()
+            .filter_map(|warehouse_id| warehouse_id.as_str())
+            .collect();
+        if item.attributes.contains_key("warehouses_in_stock") {
+            attributes.insert(
+                "warehouses_in_stock".to_string(),
+                serde_json::Value::from(warehouses_in_stock.into_iter().collect::<Vec<&str>>()),
+            );
+        }
+        let prices: Vec<&Price> = variants.iter().filter_map(|v| v.price.as_ref()).collect();
+        let price_range = prices.first().map(|first| PriceRange {
+            currency: first.currency.clone(),
+            min: prices
+                .iter()
+                .map(|p| pricing::current_value(p))
+                .fold(f32::MAX, f32::min),
+            max: prices
+                .iter()
+                .map(|p| pricing::current_value(p))
+                .fold(f32::MIN, f32::max),
+        });
+        let units_in_stock: i32 = variants
+            .iter()
+            .filter_map(|v| v.units_in_stock)
+            .fold(0, i32::saturating_add)
+            .min(inventory::UNLIMITED_STOCK);
+        let in_stock = variants.iter().any(|v| v.in_stock);
+
+        positions.insert(item.parent_ref.clone(), product_recs.len());
+        product_recs.push(ProductRecord {
+            product_id: item.product_id,
+            parent_ref: item.parent_ref.clone(),
+            name: item.name.clone(),
+            description: item.description.clone(),
+            slug: item.slug.clone(),
+            brand: item.brand.clone(),
+            hierarchical_categories: item.hierarchical_categories.clone(),
+            list_categories: item.list_categories.clone(),
+            category_page_id: item.category_page_id.clone(),
+            image_urls: item.image_urls.clone(),
+            reviews: item.reviews.clone(),
+            attributes,
+            variants,
+            price_range,
+            units_in_stock,
+            in_stock,
+            object_id: item.parent_ref.clone(),
+        });
     }
+    info!("Finished building product records: {}", product_recs.len());
+    product_recs
 }
 
-pub async fn run() -> Result<(), Box<dyn Error>> {
-    info!("Start of run()");
-    dotenv::dotenv().expect("Failed to read .env file");
-
-    let vtex_api_key =
-        env::var("VTEX_API_APPKEY").expect("Failed to parse VTEX_API_APPKEY in .env");
-    let vtex_api_apptoken =
-        env::var("VTEX_API_APPTOKEN").expect("Failed to parse VTEX_API_APPTOKEN in .env");
+struct Command {
+    mapping_file: Option<String>,
+    upload: bool,
+    atomic: bool,
+    batch_size: usize,
+    incremental: bool,
+    snapshot_file: String,
+    granularity: String,
+    currency: String,
+    sales_channel: Option<String>,
+    warehouses: Option<Vec<String>>,
+    warehouse_stock: bool,
+    reviews_file: Option<String>,
+    review_prior_rating: Option<f32>,
+    review_prior_count: f32,
+    format: String,
+    page_size: i32,
+    apply_settings: bool,
+    engine: String,
+    index_name: String,
+    feed: Option<String>,
+    store_url: Option<String>,
+    catalog_snapshot: Option<String>,
+}
 
-    // Setup the HTTP client
-    let mut headers = header::HeaderMap::new();
-    headers.insert(
-        "X-VTEX-API-AppKey",
-        header::HeaderValue::from_str(&vtex_api_key)?,
-    );
-    headers.insert(
-        "X-VTEX-API-AppToken",
-        header::HeaderValue::from_str(&vtex_api_apptoken)?,
-    );
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(10))
-        .default_headers(headers)
-        .build()?;
+impl Command {
+    fn get_command() -> Command {
+        let matches = App::new("VTEX Algolia")
+            .version(crate_version!())
+            .author("VTEX")
+            .about("Command line interface to build Algolia records from VTEX")
+            .arg(Arg::with_name("MAPPING_FILE")
+                .short("m")
+                .long("mapping_file")
+                .value_name("MAPPING_FILE")
+                .help("Sets the JSON file mapping VTEX specifications to Algolia attributes - default is Color and Size")
+                .takes_value(true))
+            .arg(Arg::with_name("UPLOAD")
+                .long("upload")
+                .help("Sends the records to the Algolia index set in the .env file")
+                .takes_value(false))
+            .arg(Arg::with_name("ATOMIC")
+                .long("atomic")
+                .help("upload: sends the records to a temporary index and moves it over the index")
+                .takes_value(false))
+            .arg(Arg::with_name("BATCH_SIZE")
+                .short("b")
+                .long("batch_size")
+                .value_name("BATCH_SIZE")
+                .help("upload: Sets the number of records per batch - default is 1000")
+                .takes_value(true))
+            .arg(Arg::with_name("INCREMENTAL")
+                .long("incremental")
+                .help("Only writes (and uploads) the records that changed since the last run and deletes the records that are gone")
+                .takes_value(false))
+            .arg(Arg::with_name("SNAPSHOT_FILE")
+                .long("snapshot_file")
+                .value_name("SNAPSHOT_FILE")
+                .help("incremental: Sets the file with the hash of each record of the last run - default is data/algolia_snapshot.json")
+                .takes_value(true))
+            .arg(Arg::with_name("GRANULARITY")
+                .short("g")
+                .long("granularity")
+                .value_name("GRANULARITY")
+                .possible_values(&["sku", "product"])
+                .help("Sets one record per sku (with parentID for distinct) or one record per product with the skus as variants - default is sku")
+                .takes_value(true))
+            .arg(Arg::with_name("CURRENCY")
+                .long("currency")
+                .value_name("CURRENCY")
+                .help("Sets the currency of the prices - default is USD")
+                .takes_value(true))
+            .arg(Arg::with_name("SALES_CHANNEL")
+                .long("sales_channel")
+                .value_name("TRADE_POLICY_ID")
+                .help("Uses the computed price of the trade policy (sales channel) instead of the base price")
+                .takes_value(true))
+            .arg(Arg::with_name("WAREHOUSES")
+                .long("warehouses")
+                .value_name("WAREHOUSE_IDS")
+                .help("Sets the comma separated warehouse ids counted in the units in stock - default is all the warehouses")
+                .use_delimiter(true)
+                .takes_value(true))
+            .arg(Arg::with_name("WAREHOUSE_STOCK")
+                .long("warehouse_stock")
+                .help("Adds the stock by warehouse and the warehouses in stock to the records (ex: for store pickup)")
+                .takes_value(false))
+            .arg(Arg::with_name("REVIEWS_FILE")
+                .short("r")
+                .long("reviews_file")
+                .value_name("REVIEWS_FILE")
+                .help("Sets the CSV or JSON file with the Rating and Count of the reviews by ProductRefId - default is no reviews")
+                .takes_value(true))
+            .arg(Arg::with_name("REVIEW_PRIOR_RATING")
+                .long("review_prior_rating")
+                .value_name("REVIEW_PRIOR_RATING")
+                .help("reviews: Sets the prior rating of the bayesian average - default is the average rating of all the reviews")
+                .takes_value(true))
+            .arg(Arg::with_name("REVIEW_PRIOR_COUNT")
+                .long("review_prior_count")
+                .value_name("REVIEW_PRIOR_COUNT")
+                .help("reviews: Sets the weight (number of reviews) of the prior rating - default is 10")
+                .takes_value(true))
+            .arg(Arg::with_name("FORMAT")
+                .short("f")
+                .long("format")
+                .value_name("FORMAT")
+                .possible_values(&["json", "ndjson", "chunks"])
+                .help("Sets the output: one JSON array, one record per line (NDJSON) or one JSON array file per page - default is json")
+                .takes_value(true))
+            .arg(Arg::with_name("PAGE_SIZE")
+                .short("p")
+                .long("page_size")
+                .value_name("PAGE_SIZE")
+                .help("Sets the number of products read, written and uploaded at a time (max 250) - default is 250")
+                .takes_value(true))
+            .arg(Arg::with_name("APPLY_SETTINGS")
+                .long("apply_settings")
+                .help("Applies the index settings written to data/algolia_settings.json to the Algolia index set in the .env file")
+                .takes_value(false))
+            .arg(Arg::with_name("ENGINE")
+                .short("e")
+                .long("engine")
+                .value_name("ENGINE")
+                .possible_values(&["algolia", "elasticsearch", "typesense", "meilisearch"])
+                .help("Sets the search engine the records are exported to - default is algolia")
+                .takes_value(true))
+            .arg(Arg::with_name("INDEX_NAME")
+                .long("index_name")
+                .value_name("INDEX_NAME")
+                .help("typesense: Sets the name of the collection in the schema - default is products")
+                .takes_value(true))
+            .arg(Arg::with_name("FEED")
+                .long("feed")
+                .value_name("FEED")
+                .possible_values(&["google_xml", "google_tsv", "meta_csv"])
+                .help("Also writes a product feed of the skus: Google Merchant Center XML or TSV or a Meta catalog CSV")
+                .takes_value(true))
+            .arg(Arg::with_name("STORE_URL")
+                .long("store_url")
+                .value_name("STORE_URL")
+                .help("feed: Sets the URL of the store for the product links - default is https://{accountName}.{environment}.com.br")
+                .takes_value(true))
+            .arg(Arg::with_name("CATALOG_SNAPSHOT")
+                .long("from_snapshot")
+                .value_name("CATALOG_SNAPSHOT")
+                .help("Reads the skus, prices and inventory from a catalog snapshot (vtex_impex snapshot) instead of the VTEX API")
+                .takes_value(true))
+            .get_matches();
+
+        Command {
+            mapping_file: matches.value_of("MAPPING_FILE").map(|m| m.to_string()),
+            upload: matches.is_present("UPLOAD"),
+            atomic: matches.is_present("ATOMIC"),
+            batch_size: matches
+                .value_of("BATCH_SIZE")
+                .unwrap_or("1000")
+                .parse::<usize>()
+                .expect("BATCH_SIZE must be a positive integer. Default is 1000"),
+            incremental: matches.is_present("INCREMENTAL"),
+            granularity: matches.value_of("GRANULARITY").unwrap_or("sku").to_string(),
+            snapshot_file: matches
+                .value_of("SNAPSHOT_FILE")
+                .unwrap_or("data/algolia_snapshot.json")
+                .to_string(),
+            currency: matches.value_of("CURRENCY").unwrap_or("USD").to_string(),
+            sales_channel: matches.value_of("SALES_CHANNEL").map(|s| s.to_string()),
+            warehouses: matches
+                .values_of("WAREHOUSES")
+                .map(|w| w.map(|w| w.trim().to_string()).collect()),
+            warehouse_stock: matches.is_present("WAREHOUSE_STOCK"),
+            reviews_file: matches.value_of("REVIEWS_FILE").map(|r| r.to_string()),
+            review_prior_rating: matches.value_of("REVIEW_PRIOR_RATING").map(|r| {
+                r.parse::<f32>()
+                    .expect("REVIEW_PRIOR_RATING must be a number")
+            }),
+            review_prior_count: matches
+                .value_of("REVIEW_PRIOR_COUNT")
+                .unwrap_or("10")
+                .parse::<f32>()
+                .expect("REVIEW_PRIOR_COUNT must be a number. Default is 10"),
+            format: matches.value_of("FORMAT").unwrap_or("json").to_string(),
+            page_size: matches
+                .value_of("PAGE_SIZE")
+                .unwrap_or("250")
+                .parse::<i32>()
+                .ok()
+                .filter(|page_size| (1..=250).contains(page_size))
+                .expect("PAGE_SIZE must be between 1 and 250. Default is 250"),
+            apply_settings: matches.is_present("APPLY_SETTINGS"),
+            engine: matches.value_of("ENGINE").unwrap_or("algolia").to_string(),
+            index_name: matches
+                .value_of("INDEX_NAME")
+                .unwrap_or("products")
+                .to_string(),
+            feed: matches.value_of("FEED").map(|f| f.to_string()),
+            store_url: matches.value_of("STORE_URL").map(|s| s.to_string()),
+            catalog_snapshot: matches.value_of("CATALOG_SNAPSHOT").map(|s| s.to_string()),
+        }
+    }
+}
 
-    // Get all the sku_ids in VTEX
-    let sku_ids = get_all_sku_ids(&client).await;
-    // // Get SkuAndContext records
-    // get_item_records(&sku_ids, &client).await;
-    // // Get Price records
-    // get_price_records(&sku_ids, &client).await;
-    // // Get Inventory records
-    // get_inventory_records(&sku_ids, &client).await;
+// The clients and the configuration used to build each page of records
+struct PageContext<'a> {
+    catalog: &'a CatalogSource<'a>,
+    client: &'a Client,
+    account_name: &'a str,
+    environment: &'a str,
+    cmd: &'a Command,
+    mapping: &'a MappingConfig,
+    stock: &'a StockConfig,
+    reviews: &'a HashMap<String, Review>,
+}
 
+// Build the sku records of a page of sku_ids - with the variants and the skus used for product records
+async fn build_item_records(
+    sku_ids: &[i32],
+    ctx: &PageContext<'_>,
+) -> Result<
+    (
+        Vec<ItemRecord>,
+        HashMap<String, ProductVariant>,
+        HashMap<i32, SkuAndContext>,
+    ),
+    Box<dyn Error>,
+> {
+    let (client, account_name) = (ctx.client, ctx.account_name);
+    let (cmd, mapping, stock, reviews) = (ctx.cmd, ctx.mapping, ctx.stock, ctx.reviews);
     //Run concurrently
-    let ir = get_item_records(&sku_ids, &client);
+    let ir = ctx.catalog.item_records(sku_ids);
     // Get Price records
-    let pr = get_price_records(&sku_ids, &client);
+    let pr = ctx.catalog.price_records(sku_ids);
     // Get Inventory records
-    let invr = get_inventory_records(&sku_ids, &client);
+    let invr = ctx.catalog.inventory_records(sku_ids);
     // join! all the futures to run concurrently
     let (ir, pr, invr) = join!(ir, pr, invr);
+    let (ir, pr, invr) = (ir?, pr?, invr?);
     debug!("inventory map: {:?}", invr);
+    // The computed price of the trade policy for all the skus - or for the skus without a
+    // base price or cost price (ex: priced by a price rule) from the default trade policy
+    let computed_sku_ids: Vec<i32> = match &cmd.sales_channel {
+        Some(_) => sku_ids.to_vec(),
+        None => sku_ids
+            .iter()
+            .filter(|sku_id| pricing::selling_price(pr.get(sku_id), None).is_none())
+            .cloned()
+            .collect(),
+    };
+    let trade_policy_id = cmd.sales_channel.as_deref().unwrap_or("1");
+    // A snapshot has no computed prices - a sku priced only by a price rule can not be exported
+    let computed_prices = if computed_sku_ids.is_empty() {
+        HashMap::new()
+    } else if cmd.catalog_snapshot.is_some() {
+        let price_rule_sku_ids: Vec<&i32> = computed_sku_ids
+            .iter()
+            .filter(|sku_id| pr.contains_key(sku_id))
+            .collect();
+        if !price_rule_sku_ids.is_empty() {
+            return Err(format!(
+                "sku_ids: {:?} have no base price or cost price - their price rules are not in the catalog snapshot, export without --from_snapshot",
+                price_rule_sku_ids
+            )
+            .into());
+        }
+        HashMap::new()
+    } else {
+        utils::get_computed_price_records(&computed_sku_ids, client, account_name, trade_policy_id)
+            .await?
+    };
     // Generate the list of sku's with missing images
     // gen_skus_missing_images_file(&sku_ids, &ir);
+    // Skip the skus that no longer exist - a failed read fails the page
+    let sku_ids: Vec<i32> = sku_ids
+        .iter()
+        .filter(|sku_id| {
+            let found = ir.contains_key(sku_id);
+            if !found {
+                warn!("sku_id: {} not found. Skipping.", sku_id);
+            }
+            found
+        })
+        .cloned()
+        .collect();
 
     let mut algolia_recs: Vec<ItemRecord> = Vec::with_capacity(sku_ids.len());
-    let path = "data/algolia_records.json";
-    let out_file = File::create(path)?;
-    let mut buf_wtr = BufWriter::new(out_file);
-
     // Need to add the builds for the variants, sizes and colors
-    let product_variants = build_product_variant_map(&sku_ids, &ir, &invr);
+    let product_variants = build_product_variant_map(&sku_ids, &ir, &invr, mapping, stock);
     // Build the Algolia Records
-    info!("Starting algolia record build");
+    debug!("Starting algolia record build");
     for sku_id in sku_ids {
         let sku_ctx = ir.get(&sku_id).unwrap();
-        let price_get = pr.get(&sku_id).unwrap();
-        let inventory_list = invr.get(&sku_id).unwrap();
+        let price = pricing::build_price_for_algolia(
+            pr.get(&sku_id),
+            computed_prices.get(&sku_id),
+            &cmd.currency,
+        );
+        if price.is_none() {
+            warn!("no price found for sku_id: {}", sku_id);
+        }
+        let inventory_list = invr.get(&sku_id);
         debug!("product_variants: {:?}", product_variants);
         debug!("Retrieving product variant: {}", sku_ctx.product_ref_id);
         let product_variant = product_variants
             .get(&sku_ctx.product_ref_id.clone())
             .unwrap();
 
+        // The mapped specifications plus the available values of the variant dimensions
+        let mut attributes = mapping.record_attributes(sku_ctx);
+        for (name, values) in &product_variant.available {
+            attributes.insert(name.clone(), serde_json::Value::from(values.clone()));
+        }
+        attributes.extend(stock.warehouse_attributes(inventory_list));
+        let category_path = sku_ctx
+            .product_categories
+            .path(&sku_ctx.product_category_ids);
+
         // Build the Algolia Record
         let algolia_record = ItemRecord {
             sku_id: sku_ctx.id,
@@ -725,28 +584,16 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
             description: sku_ctx.product_description.clone(),
             slug: sku_ctx.detail_url.clone(),
             brand: sku_ctx.brand_name.clone(),
-            hierarchical_categories: get_hierarchical_categories(
-                &sku_ctx.product_categories,
-                &sku_ctx.product_category_ids,
-            ),
-            list_categories: get_list_categories(
-                &sku_ctx.product_categories,
-                &sku_ctx.product_category_ids,
-            ),
-            category_page_id: get_category_page_ids(
-                &sku_ctx.product_categories,
-                &sku_ctx.product_category_ids,
-            ),
+            hierarchical_categories: categories::get_hierarchical_categories(&category_path),
+            list_categories: categories::get_list_categories(&category_path),
+            category_page_id: categories::get_category_page_ids(&category_path),
             image_urls: get_image_urls(&sku_ctx.images),
             image_blurred: None,
-            reviews: Some(generate_review()),
-            color: get_color(&sku_ctx.sku_specifications),
-            available_colors: product_variant.available_colors.clone(),
-            size: get_size(&sku_ctx.sku_specifications),
-            available_sizes: product_variant.available_sizes.clone(),
+            reviews: reviews.get(&sku_ctx.product_ref_id).cloned(),
+            attributes,
             variants: product_variant.variants.clone(),
-            price: build_price_for_algolia(price_get),
-            units_in_stock: get_inventory_for_algolia(inventory_list),
+            price,
+            units_in_stock: stock.units_in_stock(inventory_list),
             created_at: None,
             updated_at: None,
             related_products: None,
@@ -755,11 +602,192 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
         };
         algolia_recs.push(algolia_record);
     }
-    info!("Finished building algolia records: {}", algolia_recs.len());
-    let result = serde_json::to_string_pretty(&algolia_recs)?;
-    buf_wtr.write_all(result.as_bytes())?;
-    buf_wtr.flush()?;
-    info!("Finished writing algolia records to file: {}", path);
+    debug!("Finished building algolia records: {}", algolia_recs.len());
+    Ok((algolia_recs, product_variants, ir))
+}
+
+// Page through the products and export the records - all the skus of a product are on the same page
+async fn export<E: Exporter>(mut exporter: E, ctx: &PageContext<'_>) -> Result<(), Box<dyn Error>> {
+    let cmd = ctx.cmd;
+    let mut settings_builder = SettingsBuilder::default();
+    let mut feed = match &cmd.feed {
+        Some(feed) => {
+            let store_url = cmd.store_url.clone().unwrap_or_else(|| {
+                "https://{accountName}.{environment}.com.br"
+                    .replace("{accountName}", ctx.account_name)
+                    .replace("{environment}", ctx.environment)
+            });
+            Some(FeedWriter::new(
+                FeedFormat::from_name(feed),
+                ctx.account_name,
+                &store_url,
+            )?)
+        }
+        None => None,
+    };
+    let mut from = 1;
+    loop {
+        let to = from + cmd.page_size - 1;
+        let page = ctx.catalog.product_and_sku_ids(from, to).await?;
+        let mut sku_ids: Vec<i32> = page.data.values().flatten().cloned().collect();
+        sku_ids.sort_unstable();
+        info!(
+            "products: {} to {} of {} skus: {}",
+            from,
+            to,
+            page.range.total,
+            sku_ids.len()
+        );
+        if !sku_ids.is_empty() {
+            let (algolia_recs, product_variants, ir) = build_item_records(&sku_ids, ctx).await?;
+            for sku_ctx in ir.values() {
+                settings_builder.add(sku_ctx, ctx.mapping);
+            }
+            if let Some(feed) = feed.as_mut() {
+                feed.write_page(&algolia_recs, &ir)?;
+            }
+            if cmd.granularity.eq("product") {
+                let product_recs =
+                    build_product_records(&algolia_recs, &product_variants, &ir, ctx.mapping);
+                exporter.write_page(product_recs).await?;
+            } else {
+                exporter.write_page(algolia_recs).await?;
+            }
+        }
+        if to >= page.range.total {
+            break;
+        }
+        from = to + 1;
+    }
+
+    if let Some(feed) = feed {
+        feed.finish()?;
+    }
+
+    // The facets, searchable attributes, ranking and distinct for the records
+    let index_settings = settings_builder.build(
+        ctx.mapping,
+        &cmd.granularity,
+        cmd.reviews_file.is_some(),
+        cmd.warehouse_stock,
+    );
+    exporter.finish(&index_settings).await
+}
+
+pub async fn run() -> Result<(), Box<dyn Error>> {
+    info!("Start of run()");
+    dotenv::dotenv().expect("Failed to read .env file");
+
+    let vtex_api_key =
+        env::var("VTEX_API_APPKEY").expect("Failed to parse VTEX_API_APPKEY in .env");
+    let vtex_api_apptoken =
+        env::var("VTEX_API_APPTOKEN").expect("Failed to parse VTEX_API_APPTOKEN in .env");
+    let account_name = env::var("ACCOUNT_NAME").expect("Failed to parse ACCOUNT_NAME");
+    let environment = env::var("ENVIRONMENT").expect("Failed to parse ENVIRONMENT");
+
+    // The specifications to map to Algolia attributes
+    let cmd = Command::get_command();
+    if cmd.engine.ne("algolia") && (cmd.upload || cmd.incremental || cmd.apply_settings) {
+        return Err(
+            "--upload, --incremental and --apply_settings are only supported for algolia".into(),
+        );
+    }
+    if cmd.catalog_snapshot.is_some() && cmd.sales_channel.is_some() {
+        return Err(
+            "--sales_channel reads the computed prices of the trade policy, which are not in a catalog snapshot (--from_snapshot)".into(),
+        );
+    }
+    if cmd.incremental && cmd.atomic {
+        return Err(
+            "--atomic rebuilds the whole index and can not be combined with --incremental".into(),
+        );
+    }
+    let mapping = match &cmd.mapping_file {
+        Some(mapping_file) => MappingConfig::from_file(mapping_file)?,
+        None => MappingConfig::default(),
+    };
+    info!("facet attributes: {:?}", mapping.facet_attributes());
+    let stock = StockConfig {
+        warehouses: cmd.warehouses.clone(),
+        per_warehouse: cmd.warehouse_stock,
+    };
+    // Products without reviews are written without reviews
+    let reviews = match &cmd.reviews_file {
+        Some(reviews_file) => reviews::read_reviews(
+            reviews_file,
+            ReviewPrior {
+                rating: cmd.review_prior_rating,
+                count: cmd.review_prior_count,
+            },
+        )?,
+        None => HashMap::new(),
+    };
+
+    // Setup the HTTP client
+    let mut headers = header::HeaderMap::new();
+    headers.insert(
+        "X-VTEX-API-AppKey",
+        header::HeaderValue::from_str(&vtex_api_key)?,
+    );
+    headers.insert(
+        "X-VTEX-API-AppToken",
+        header::HeaderValue::from_str(&vtex_api_apptoken)?,
+    );
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .default_headers(headers)
+        .build()?;
+
+    let snapshot = match &cmd.catalog_snapshot {
+        Some(catalog_snapshot) => Some(Snapshot::open(catalog_snapshot)?),
+        None => None,
+    };
+    let catalog = match &snapshot {
+        Some(snapshot) => CatalogSource::Snapshot(snapshot),
+        None => CatalogSource::Api {
+            client: &client,
+            account_name: &account_name,
+            environment: &environment,
+        },
+    };
+    let ctx = PageContext {
+        catalog: &catalog,
+        client: &client,
+        account_name: &account_name,
+        environment: &environment,
+        cmd: &cmd,
+        mapping: &mapping,
+        stock: &stock,
+        reviews: &reviews,
+    };
+    match cmd.engine.as_str() {
+        "elasticsearch" => export(ElasticsearchExporter::new()?, &ctx).await?,
+        "typesense" => export(TypesenseExporter::new(&cmd.index_name)?, &ctx).await?,
+        "meilisearch" => export(MeilisearchExporter::new(cmd.batch_size), &ctx).await?,
+        _ => {
+            let config = if cmd.upload || cmd.apply_settings {
+                Some(AlgoliaConfig::from_env())
+            } else {
+                None
+            };
+            let snapshot_file = if cmd.incremental {
+                Some(cmd.snapshot_file.clone())
+            } else {
+                None
+            };
+            let exporter = AlgoliaExporter::new(
+                OutputFormat::from_name(&cmd.format),
+                snapshot_file,
+                config
+                    .as_ref()
+                    .filter(|_| cmd.upload)
+                    .map(|config| (config, cmd.batch_size, cmd.atomic)),
+                config.clone().filter(|_| cmd.apply_settings),
+            )
+            .await?;
+            export(exporter, &ctx).await?
+        }
+    }
 
     Ok(())
 }
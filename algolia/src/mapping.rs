@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::{error::Error, fs::File};
+use vtex::model::{SkuAndContext, SkuSpecification};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecificationSource {
+    #[default]
+    Sku,
+    Product,
+}
+
+// Maps a VTEX specification to an Algolia attribute
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct AttributeMapping {
+    // Name of the specification in VTEX (ex: Colour)
+    pub specification: String,
+    #[serde(default)]
+    pub source: SpecificationSource,
+    // Name of the attribute on the Algolia record (ex: color)
+    pub attribute: String,
+    #[serde(default)]
+    pub facet: bool,
+    // Variant dimensions are added to each variant and listed per product
+    #[serde(default)]
+    pub variant: bool,
+    // Defaults to available_{attribute}s
+    #[serde(default)]
+    pub available_attribute: Option<String>,
+    // Defaults to abbreviated_{attribute}
+    #[serde(default)]
+    pub variant_attribute: Option<String>,
+}
+
+impl AttributeMapping {
+    fn new(specification: &str, attribute: &str) -> AttributeMapping {
+        AttributeMapping {
+            specification: specification.to_string(),
+            source: SpecificationSource::Sku,
+            attribute: attribute.to_string(),
+            facet: true,
+            variant: true,
+            available_attribute: None,
+            variant_attribute: None,
+        }
+    }
+
+    pub fn available_attribute(&self) -> String {
+        self.available_attribute
+            .clone()
+            .unwrap_or(format!("available_{}s", self.attribute))
+    }
+
+    pub fn variant_attribute(&self) -> String {
+        self.variant_attribute
+            .clone()
+            .unwrap_or(format!("abbreviated_{}", self.attribute))
+    }
+
+    // The values of the specification for the sku or its product
+    pub fn values(&self, sku_ctx: &SkuAndContext) -> Vec<String> {
+        let specs = match self.source {
+            SpecificationSource::Sku => &sku_ctx.sku_specifications,
+            SpecificationSource::Product => &sku_ctx.product_specifications,
+        };
+        spec_values(specs, &self.specification)
+    }
+
+    // The specification is set as a filter in VTEX for the sku or its product
+    pub fn is_filter(&self, sku_ctx: &SkuAndContext) -> bool {
+        let specs = match self.source {
+            SpecificationSource::Sku => &sku_ctx.sku_specifications,
+            SpecificationSource::Product => &sku_ctx.product_specifications,
+        };
+        specs
+            .iter()
+            .flatten()
+            .any(|spec| spec.field_name.eq(&self.specification) && spec.is_filter)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct MappingConfig {
+    pub attributes: Vec<AttributeMapping>,
+}
+
+impl Default for MappingConfig {
+    // Color and Size SKU specifications as variant dimensions
+    fn default() -> Self {
+        MappingConfig {
+            attributes: vec![
+                AttributeMapping::new("Color", "color"),
+                AttributeMapping::new("Size", "size"),
+            ],
+        }
+    }
+}
+
+impl MappingConfig {
+    pub fn from_file(file_path: &str) -> Result<MappingConfig, Box<dyn Error>> {
+        let input = File::open(file_path)?;
+        let config: MappingConfig = serde_json::from_reader(input)?;
+        Ok(config)
+    }
+
+    pub fn variant_dimensions(&self) -> impl Iterator<Item = &AttributeMapping> {
+        self.attributes.iter().filter(|mapping| mapping.variant)
+    }
+
+    pub fn facet_attributes(&self) -> Vec<String> {
+        self.attributes
+            .iter()
+            .filter(|mapping| mapping.facet)
+            .map(|mapping| mapping.attribute.clone())
+            .collect()
+    }
+
+    // A single value is written as a string, multiple values as a list
+    pub fn record_attributes(
+        &self,
+        sku_ctx: &SkuAndContext,
+    ) -> BTreeMap<String, serde_json::Value> {
+        let mut attributes = BTreeMap::new();
+        for mapping in &self.attributes {
+            let mut values = mapping.values(sku_ctx);
+            let value = match values.len() {
+                0 => continue,
+                1 => serde_json::Value::String(values.remove(0)),
+                _ => serde_json::Value::from(values),
+            };
+            attributes.insert(mapping.attribute.clone(), value);
+        }
+        attributes
+    }
+
+    // Only the product specifications - used for product records
+    pub fn product_attributes(
+        &self,
+        sku_ctx: &SkuAndContext,
+    ) -> BTreeMap<String, serde_json::Value> {
+        let mut attributes = self.record_attributes(sku_ctx);
+        for mapping in &self.attributes {
+            if mapping.source == SpecificationSource::Sku {
+                attributes.remove(&mapping.attribute);
+            }
+        }
+        attributes
+    }
+
+    pub fn variant_attributes(&self, sku_ctx: &SkuAndContext) -> BTreeMap<String, String> {
+        let mut attributes = BTreeMap::new();
+        for mapping in self.variant_dimensions() {
+            if let Some(value) = mapping.values(sku_ctx).into_iter().next() {
+                attributes.insert(mapping.variant_attribute(), value);
+            }
+        }
+        attributes
+    }
+}
+
+fn spec_values(specs: &Option<Vec<SkuSpecification>>, name: &str) -> Vec<String> {
+    specs
+        .iter()
+        .flatten()
+        .filter(|spec| spec.field_name.eq(name))
+        .flat_map(|spec| spec.field_values.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mapping_config_defaults() {
+        let config: MappingConfig = serde_json::from_str(
+            r#"{"attributes": [
+                {"specification": "Colour", "attribute": "color", "variant": true},
+                {"specification": "Material", "attribute": "material", "source": "product", "facet": true}
+            ]}"#,
+        )
+        .unwrap();
+        let colour = &config.attributes[0];
+        assert_eq!(colour.source, SpecificationSource::Sku);
+        assert_eq!(colour.available_attribute(), "available_colors");
+        assert_eq!(colour.variant_attribute(), "abbreviated_color");
+        assert_eq!(config.attributes[1].source, SpecificationSource::Product);
+        assert_eq!(config.variant_dimensions().count(), 1);
+        assert_eq!(config.facet_attributes(), vec!["material".to_string()]);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::algoliarecords::AlgoliaRecord;
+use crate::exporter::{self, Exporter};
+use crate::settings::IndexSettings;
+use log::*;
+use serde_json::json;
+use std::{error::Error, fs::File, io::BufWriter};
+
+const SETTINGS_FILE: &str = "data/meilisearch_settings.json";
+
+// The records as batches of documents (a JSON array per file with the objectID as id)
+// for POST /indexes/{index}/documents, and the index settings
+pub struct MeilisearchExporter {
+    batch_size: usize,
+    batches: usize,
+    written: usize,
+}
+
+impl MeilisearchExporter {
+    pub fn new(batch_size: usize) -> MeilisearchExporter {
+        MeilisearchExporter {
+            batch_size: batch_size.max(1),
+            batches: 0,
+            written: 0,
+        }
+    }
+}
+
+pub fn index_settings(settings: &IndexSettings) -> serde_json::Value {
+    json!({
+        "filterableAttributes": exporter::attribute_names(&settings.attributes_for_faceting),
+        "searchableAttributes": exporter::attribute_names(&settings.searchable_attributes),
+        "sortableAttributes": ["units_in_stock", "reviews.bayesian_avg"],
+        "distinctAttribute": settings.attribute_for_distinct
+    })
+}
+
+impl Exporter for MeilisearchExporter {
+    async fn write_page<T: AlgoliaRecord>(
+        &mut self,
+        records: Vec<T>,
+    ) -> Result<(), Box<dyn Error>> {
+        for batch in records.chunks(self.batch_size) {
+            let documents = batch
+                .iter()
+                .map(|record| exporter::document(record, Some("id")))
+                .collect::<Result<Vec<_>, _>>()?;
+            self.batches += 1;
+            let path = format!("data/meilisearch_documents_{:04}.json", self.batches);
+            serde_json::to_writer(BufWriter::new(File::create(&path)?), &documents)?;
+            self.written += documents.len();
+            debug!("batch: {} written to file: {}", self.batches, path);
+        }
+        Ok(())
+    }
+
+    async fn finish(self, settings: &IndexSettings) -> Result<(), Box<dyn Error>> {
+        info!(
+            "Finished writing {} documents in {} batches",
+            self.written, self.batches
+        );
+        let settings_file = File::create(SETTINGS_FILE)?;
+        serde_json::to_writer_pretty(BufWriter::new(settings_file), &index_settings(settings))?;
+        info!("index settings written to file: {}", SETTINGS_FILE);
+        Ok(())
+    }
+}
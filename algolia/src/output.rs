@@ -0,0 +1,192 @@
+use crate::algoliarecords::AlgoliaRecord;
+use crate::exporter::Exporter;
+use crate::settings::IndexSettings;
+use crate::snapshot::{self, Snapshot};
+use crate::upload::{self, AlgoliaConfig, IndexUpload};
+use log::*;
+use std::io::Write;
+use std::{error::Error, fs::File, io::BufWriter};
+
+// json: one array in data/algolia_records.json
+// ndjson: one record per line in data/algolia_records.ndjson
+// chunks: one compact array per page in data/algolia_records_{page}.json
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OutputFormat {
+    Json,
+    Ndjson,
+    Chunks,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> OutputFormat {
+        match name {
+            "ndjson" => OutputFormat::Ndjson,
+            "chunks" => OutputFormat::Chunks,
+            _ => OutputFormat::Json,
+        }
+    }
+}
+
+// Writes (and uploads) the records a page at a time so only one page is held in memory.
+// In incremental mode only the records that changed since the last run are kept.
+pub struct AlgoliaExporter {
+    format: OutputFormat,
+    out: Option<BufWriter<File>>,
+    pages: usize,
+    written: usize,
+    snapshot_file: Option<String>,
+    previous_snapshot: Option<Snapshot>,
+    current_snapshot: Snapshot,
+    upload: Option<IndexUpload>,
+    // The index the settings are applied to
+    settings_config: Option<AlgoliaConfig>,
+}
+
+impl AlgoliaExporter {
+    pub async fn new(
+        format: OutputFormat,
+        snapshot_file: Option<String>,
+        upload: Option<(&AlgoliaConfig, usize, bool)>,
+        settings_config: Option<AlgoliaConfig>,
+    ) -> Result<AlgoliaExporter, Box<dyn Error>> {
+        let out = match format {
+            OutputFormat::Json => {
+                let mut out = BufWriter::new(File::create("data/algolia_records.json")?);
+                out.write_all(b"[")?;
+                Some(out)
+            }
+            OutputFormat::Ndjson => {
+                Some(BufWriter::new(File::create("data/algolia_records.ndjson")?))
+            }
+            OutputFormat::Chunks => None,
+        };
+        let previous_snapshot = match &snapshot_file {
+            Some(snapshot_file) => Some(Snapshot::load(snapshot_file)?),
+            None => None,
+        };
+        let upload = match upload {
+            Some((config, batch_size, atomic)) => {
+                Some(IndexUpload::start(config, batch_size, atomic).await?)
+            }
+            None => None,
+        };
+        Ok(AlgoliaExporter {
+            format,
+            out,
+            pages: 0,
+            written: 0,
+            snapshot_file,
+            previous_snapshot,
+            current_snapshot: Snapshot::default(),
+            upload,
+            settings_config,
+        })
+    }
+}
+
+impl Exporter for AlgoliaExporter {
+    async fn write_page<T: AlgoliaRecord>(
+        &mut self,
+        mut records: Vec<T>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.pages += 1;
+        if let Some(previous_snapshot) = &self.previous_snapshot {
+            let mut changed: Vec<bool> = Vec::with_capacity(records.len());
+            for record in &records {
+                let hash = snapshot::record_hash(record)?;
+                changed.push(previous_snapshot.hashes.get(record.object_id()) != Some(&hash));
+                self.current_snapshot
+                    .hashes
+                    .insert(record.object_id().to_string(), hash);
+            }
+            let mut changed = changed.into_iter();
+            records.retain(|_| changed.next().unwrap_or(true));
+        }
+
+        match self.format {
+            OutputFormat::Json => {
+                let out = self.out.as_mut().unwrap();
+                for record in &records {
+                    out.write_all(if self.written == 0 { b"\n" } else { b",\n" })?;
+                    serde_json::to_writer_pretty(&mut *out, record)?;
+                    self.written += 1;
+                }
+            }
+            OutputFormat::Ndjson => {
+                let out = self.out.as_mut().unwrap();
+                for record in &records {
+                    serde_json::to_writer(&mut *out, record)?;
+                    out.write_all(b"\n")?;
+                    self.written += 1;
+                }
+            }
+            OutputFormat::Chunks => {
+                if !records.is_empty() {
+                    let path = format!("data/algolia_records_{:04}.json", self.pages);
+                    let mut out = BufWriter::new(File::create(&path)?);
+                    serde_json::to_writer(&mut out, &records)?;
+                    out.flush()?;
+                    self.written += records.len();
+                    debug!("page: {} written to file: {}", self.pages, path);
+                }
+            }
+        }
+        info!(
+            "page: {} records: {} total records written: {}",
+            self.pages,
+            records.len(),
+            self.written
+        );
+
+        if let Some(upload) = self.upload.as_mut() {
+            upload.send_records(&records).await?;
+        }
+        Ok(())
+    }
+
+    // Write the deletes of the objectIDs that are gone, finish the file and the upload and
+    // write (and apply) the index settings
+    async fn finish(mut self, settings: &IndexSettings) -> Result<(), Box<dyn Error>> {
+        if let Some(mut out) = self.out.take() {
+            if self.format == OutputFormat::Json {
+                out.write_all(b"\n]")?;
+            }
+            out.flush()?;
+        }
+        info!("Finished writing algolia records: {}", self.written);
+
+        let mut deleted_object_ids: Vec<String> = Vec::new();
+        if let Some(previous_snapshot) = &self.previous_snapshot {
+            let (changed, deleted) = previous_snapshot.changes(&self.current_snapshot);
+            info!(
+                "changed records: {} deleted records: {}",
+                changed.len(),
+                deleted.len()
+            );
+            let deletes_path = "data/algolia_deletes.json";
+            let deletes_file = File::create(deletes_path)?;
+            serde_json::to_writer_pretty(BufWriter::new(deletes_file), &deleted)?;
+            info!(
+                "Finished writing deleted objectIDs to file: {}",
+                deletes_path
+            );
+            deleted_object_ids = deleted;
+        }
+
+        if let Some(mut upload) = self.upload.take() {
+            upload.send_deletes(&deleted_object_ids).await?;
+            upload.finish().await?;
+        }
+
+        // The snapshot is saved once the changes have been written (and uploaded)
+        if let Some(snapshot_file) = &self.snapshot_file {
+            self.current_snapshot.save(snapshot_file)?;
+        }
+
+        settings.save("data/algolia_settings.json")?;
+        if let Some(settings_config) = &self.settings_config {
+            upload::set_settings(settings_config, settings).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use crate::algoliarecords::Price;
+use vtex::model::{ComputedPrice, PriceGet};
+
+// The selling price of the sku: the computed price of the trade policy when present,
+// otherwise the base price or the cost price with the markup applied
+pub fn selling_price(price: Option<&PriceGet>, computed: Option<&ComputedPrice>) -> Option<f32> {
+    if let Some(computed) = computed {
+        return Some(computed.selling_price);
+    }
+    let price = price?;
+    price.base_price.or_else(|| {
+        price
+            .cost_price
+            .map(|cost_price| cost_price * (1.0 + price.markup.unwrap_or(0.0) / 100.0))
+    })
+}
+
+// A sku is on sale when the list price is above the selling price.
+// value is the list price when on sale, discounted_value is always the selling price.
+pub fn build_price_for_algolia(
+    price: Option<&PriceGet>,
+    computed: Option<&ComputedPrice>,
+    currency: &str,
+) -> Option<Price> {
+    let selling = selling_price(price, computed)?;
+    let list = computed
+        .and_then(|computed| computed.list_price)
+        .or_else(|| price.and_then(|price| price.list_price));
+    let algolia_price = match list {
+        Some(list) if list > selling => Price {
+            currency: currency.to_string(),
+            value: list,
+            discounted_value: selling,
+            discount_level: ((list - selling) * 100.0 / list).round(),
+            on_sales: true,
+        },
+        _ => Price {
+            currency: currency.to_string(),
+            value: selling,
+            discounted_value: selling,
+            discount_level: 0.0,
+            on_sales: false,
+        },
+    };
+    Some(algolia_price)
+}
+
+// The price the sku is sold at - used for the price range of the product
+pub fn current_value(price: &Price) -> f32 {
+    price.discounted_value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn price_on_sale_and_markup() {
+        let on_sale = PriceGet {
+            item_id: "1".to_string(),
+            markup: None,
+            list_price: Some(100.0),
+            base_price: Some(75.0),
+            cost_price: None,
+        };
+        let price = build_price_for_algolia(Some(&on_sale), None, "EUR").unwrap();
+        assert!(price.on_sales);
+        assert_eq!(price.currency, "EUR");
+        assert_eq!(price.value, 100.0);
+        assert_eq!(price.discounted_value, 75.0);
+        assert_eq!(price.discount_level, 25.0);
+
+        // No base price: resolved from the cost price and the markup
+        let markup = PriceGet {
+            item_id: "2".to_string(),
+            markup: Some(50.0),
+            list_price: None,
+            base_price: None,
+            cost_price: Some(10.0),
+        };
+        let price = build_price_for_algolia(Some(&markup), None, "USD").unwrap();
+        assert!(!price.on_sales);
+        assert_eq!(price.value, 15.0);
+        assert_eq!(price.discounted_value, 15.0);
+        assert_eq!(price.discount_level, 0.0);
+        assert_eq!(current_value(&price), 15.0);
+
+        // The trade policy price overrides the base price
+        let computed = ComputedPrice {
+            trade_policy_id: "2".to_string(),
+            list_price: None,
+            cost_price: None,
+            selling_price: 90.0,
+            price_valid_until: None,
+        };
+        let price = build_price_for_algolia(Some(&on_sale), Some(&computed), "USD").unwrap();
+        assert_eq!(current_value(&price), 90.0);
+        assert_eq!(price.discount_level, 10.0);
+        assert!(build_price_for_algolia(None, None, "USD").is_none());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::algoliarecords::Review;
+use log::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::{error::Error, fs::File};
+
+// The reviews of a product - a product can be on more than one row (ex: one row per source)
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct ReviewRecord {
+    pub product_ref_id: String,
+    // Average rating (0 to 5) of the reviews
+    pub rating: f32,
+    pub count: i32,
+}
+
+// The prior of the bayesian average: products with few reviews are pulled toward the prior rating
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ReviewPrior {
+    // None is the average rating of all the reviews
+    pub rating: Option<f32>,
+    pub count: f32,
+}
+
+// Read the reviews from a CSV file or a JSON file (a list of records) - keyed by product RefId
+pub fn read_reviews(
+    file_path: &str,
+    prior: ReviewPrior,
+) -> Result<HashMap<String, Review>, Box<dyn Error>> {
+    let input = File::open(file_path)?;
+    let records: Vec<ReviewRecord> = if file_path.ends_with(".json") {
+        serde_json::from_reader(input)?
+    } else {
+        let mut rdr = csv::Reader::from_reader(input);
+        let mut records: Vec<ReviewRecord> = Vec::new();
+        let mut e = 0;
+        for line in rdr.deserialize() {
+            match line {
+                Ok(record) => records.push(record),
+                Err(err) => {
+                    error!("Error parsing row: {:?}", err);
+                    e += 1;
+                }
+            }
+        }
+        info!(
+            "Records successfully read: {}. Records not read (errors): {}",
+            records.len(),
+            e
+        );
+        records
+    };
+    Ok(build_reviews(&records, prior))
+}
+
+pub fn build_reviews(records: &[ReviewRecord], prior: ReviewPrior) -> HashMap<String, Review> {
+    // Total of the ratings and the count by product
+    let mut totals: HashMap<&str, (f32, i32)> = HashMap::new();
+    for record in records.iter().filter(|record| record.count > 0) {
+        let total = totals
+            .entry(record.product_ref_id.as_str())
+            .or_insert((0.0, 0));
+        total.0 += record.rating * record.count as f32;
+        total.1 += record.count;
+    }
+    let prior_rating = prior.rating.unwrap_or_else(|| {
+        let (sum, count) = totals
+            .values()
+            .fold((0.0, 0), |acc, total| (acc.0 + total.0, acc.1 + total.1));
+        if count == 0 {
+            0.0
+        } else {
+            sum / count as f32
+        }
+    });
+    info!(
+        "reviews for {} products. prior rating: {:.2} prior count: {}",
+        totals.len(),
+        prior_rating,
+        prior.count
+    );
+
+    totals
+        .into_iter()
+        .map(|(product_ref_id, (sum, count))| {
+            let rating = sum / count as f32;
+            let bayesian_avg = (prior_rating * prior.count + sum) / (prior.count + count as f32);
+            let review = Review {
+                rating: (rating * 100.0).round() / 100.0,
+                count,
+                bayesian_avg: (bayesian_avg * 100.0).round() / 100.0,
+            };
+            (product_ref_id.to_string(), review)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(product_ref_id: &str, rating: f32, count: i32) -> ReviewRecord {
+        ReviewRecord {
+            product_ref_id: product_ref_id.to_string(),
+            rating,
+            count,
+        }
+    }
+
+    #[test]
+    fn bayesian_average_with_prior() {
+        let records = vec![
+            record("P1", 5.0, 1),
+            record("P2", 4.0, 30),
+            record("P2", 5.0, 10),
+            record("P3", 3.0, 0),
+        ];
+        let prior = ReviewPrior {
+            rating: Some(3.0),
+            count: 10.0,
+        };
+        let reviews = build_reviews(&records, prior);
+        assert_eq!(reviews.len(), 2);
+        // One 5 star review is pulled toward the prior
+        assert_eq!(reviews["P1"].bayesian_avg, 3.18);
+        assert_eq!(reviews["P1"].rating, 5.0);
+        // 40 reviews averaging 4.25
+        assert_eq!(reviews["P2"].count, 40);
+        assert_eq!(reviews["P2"].rating, 4.25);
+        assert_eq!(reviews["P2"].bayesian_avg, 4.0);
+        assert!(!reviews.contains_key("P3"));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::mapping::MappingConfig;
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::io::{BufWriter, Write};
+use std::{error::Error, fs::File};
+use vtex::model::SkuAndContext;
+
+// The index settings for the records
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexSettings {
+    pub attributes_for_faceting: Vec<String>,
+    pub searchable_attributes: Vec<String>,
+    pub custom_ranking: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute_for_distinct: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distinct: Option<bool>,
+}
+
+impl IndexSettings {
+    pub fn save(&self, file_path: &str) -> Result<(), Box<dyn Error>> {
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+        info!("index settings written to file: {}", file_path);
+        Ok(())
+    }
+}
+
+// Collects the filter specifications and the depth of the categories while the records are built
+#[derive(Debug, Default)]
+pub struct SettingsBuilder {
+    filter_attributes: BTreeSet<String>,
+    category_depth: usize,
+}
+
+impl SettingsBuilder {
+    pub fn add(&mut self, sku_ctx: &SkuAndContext, mapping: &MappingConfig) {
+        for attribute in &mapping.attributes {
+            if attribute.is_filter(sku_ctx) {
+                self.filter_attributes.insert(attribute.attribute.clone());
+            }
+        }
+        let depth = sku_ctx
+            .product_categories
+            .path(&sku_ctx.product_category_ids)
+            .len();
+        self.category_depth = self.category_depth.max(depth);
+    }
+
+    pub fn build(
+        &self,
+        mapping: &MappingConfig,
+        granularity: &str,
+        reviews: bool,
+        per_warehouse: bool,
+    ) -> IndexSettings {
+        let mut facets: Vec<String> = vec!["brand".to_string()];
+        facets.extend(
+            (0..self.category_depth).map(|level| format!("hierarchical_categories.lvl{}", level)),
+        );
+        facets.push("list_categories".to_string());
+        facets.push("filterOnly(category_page_id)".to_string());
+        // The attributes set as facets in the mapping file or as filters in VTEX
+        for attribute in &mapping.attributes {
+            if attribute.facet || self.filter_attributes.contains(&attribute.attribute) {
+                facets.push(attribute.attribute.clone());
+            }
+        }
+        facets.extend(
+            mapping
+                .variant_dimensions()
+                .map(|dimension| dimension.available_attribute()),
+        );
+        if per_warehouse {
+            facets.push("warehouses_in_stock".to_string());
+        }
+        let mut unique = BTreeSet::new();
+        facets.retain(|facet| unique.insert(facet.clone()));
+
+        let mut searchable_attributes: Vec<String> = vec![
+            "name".to_string(),
+            "brand".to_string(),
+            "list_categories".to_string(),
+        ];
+        let sku_records = granularity.ne("product");
+        if sku_records {
+            searchable_attributes.push("sku".to_string());
+        }
+        searchable_attributes.push("unordered(description)".to_string());
+
+        let custom_ranking = if reviews {
+            vec![
+                "desc(reviews.bayesian_avg)".to_string(),
+                "desc(reviews.count)".to_string(),
+            ]
+        } else {
+            Vec::new()
+        };
+
+        IndexSettings {
+            attributes_for_faceting: facets,
+            searchable_attributes,
+            custom_ranking,
+            // The skus of a product are grouped by parentID
+            attribute_for_distinct: sku_records.then(|| "parentID".to_string()),
+            distinct: sku_records.then_some(true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_for_sku_records() {
+        let builder = SettingsBuilder {
+            filter_attributes: ["size".to_string()].into_iter().collect(),
+            category_depth: 2,
+        };
+        let mut mapping = MappingConfig::default();
+        mapping.attributes[1].facet = false;
+        let settings = builder.build(&mapping, "sku", true, false);
+        assert_eq!(
+            settings.attributes_for_faceting,
+            vec![
+                "brand",
+                "hierarchical_categories.lvl0",
+                "hierarchical_categories.lvl1",
+                "list_categories",
+                "filterOnly(category_page_id)",
+                "color",
+                "size",
+                "available_colors",
+                "available_sizes",
+            ]
+        );
+        assert_eq!(
+            settings.attribute_for_distinct,
+            Some("parentID".to_string())
+        );
+        assert_eq!(settings.custom_ranking[0], "desc(reviews.bayesian_avg)");
+
+        let settings = builder.build(&mapping, "product", false, true);
+        assert_eq!(settings.distinct, None);
+        assert!(settings.custom_ranking.is_empty());
+        assert!(!settings.searchable_attributes.contains(&"sku".to_string()));
+        assert!(settings
+            .attributes_for_faceting
+            .contains(&"warehouses_in_stock".to_string()));
+    }
+}
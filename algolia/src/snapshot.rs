@@ -0,0 +1,103 @@
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufWriter, Write};
+use std::{error::Error, fs, fs::File, path::Path};
+
+// Hash of the content of each record of the last run - keyed by objectID
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct Snapshot {
+    pub hashes: BTreeMap<String, String>,
+}
+
+impl Snapshot {
+    // A missing snapshot file is the first run - every record is new
+    pub fn load(file_path: &str) -> Result<Snapshot, Box<dyn Error>> {
+        if !Path::new(file_path).exists() {
+            info!(
+                "snapshot file: {} not found. Starting a new snapshot",
+                file_path
+            );
+            return Ok(Snapshot::default());
+        }
+        let input = File::open(file_path)?;
+        let snapshot: Snapshot = serde_json::from_reader(input)?;
+        info!("snapshot records: {}", snapshot.hashes.len());
+        Ok(snapshot)
+    }
+
+    // Written to a temporary file that replaces the snapshot once complete, so a failed write
+    // leaves the previous snapshot in place
+    pub fn save(&self, file_path: &str) -> Result<(), Box<dyn Error>> {
+        let tmp_path = format!("{}.tmp", file_path);
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        fs::rename(&tmp_path, file_path)?;
+        info!("snapshot written to file: {}", file_path);
+        Ok(())
+    }
+
+    // The objectIDs that are new or changed and the objectIDs that are no longer present
+    pub fn changes(&self, current: &Snapshot) -> (Vec<String>, Vec<String>) {
+        let changed: Vec<String> = current
+            .hashes
+            .iter()
+            .filter(|(object_id, hash)| self.hashes.get(*object_id) != Some(*hash))
+            .map(|(object_id, _)| object_id.clone())
+            .collect();
+        let deleted: Vec<String> = self
+            .hashes
+            .keys()
+            .filter(|object_id| !current.hashes.contains_key(*object_id))
+            .cloned()
+            .collect();
+        (changed, deleted)
+    }
+}
+
+pub fn record_hash<T: Serialize>(record: &T) -> Result<String, Box<dyn Error>> {
+    let json = serde_json::to_vec(record)?;
+    Ok(format!("{:016x}", fnv1a(&json)))
+}
+
+// FNV-1a is used as it is stable between runs and Rust versions (unlike DefaultHasher)
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_changes() {
+        let previous = Snapshot {
+            hashes: [
+                ("A".to_string(), record_hash(&"shirt").unwrap()),
+                ("B".to_string(), record_hash(&"hat").unwrap()),
+                ("C".to_string(), record_hash(&"sock").unwrap()),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let current = Snapshot {
+            hashes: [
+                ("A".to_string(), record_hash(&"shirt").unwrap()),
+                ("B".to_string(), record_hash(&"red hat").unwrap()),
+                ("D".to_string(), record_hash(&"shoe").unwrap()),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let (changed, deleted) = previous.changes(&current);
+        assert_eq!(changed, vec!["B".to_string(), "D".to_string()]);
+        assert_eq!(deleted, vec!["C".to_string()]);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::algoliarecords::AlgoliaRecord;
+use crate::exporter::{self, Exporter};
+use crate::settings::IndexSettings;
+use log::*;
+use serde_json::json;
+use std::io::Write;
+use std::{error::Error, fs::File, io::BufWriter};
+
+const DOCUMENTS_FILE: &str = "data/typesense_documents.jsonl";
+const SCHEMA_FILE: &str = "data/typesense_schema.json";
+
+// The records as a JSONL import (one document per line with the objectID as id)
+// and the collection schema with the facets of the settings
+pub struct TypesenseExporter {
+    collection: String,
+    out: BufWriter<File>,
+    written: usize,
+}
+
+impl TypesenseExporter {
+    pub fn new(collection: &str) -> Result<TypesenseExporter, Box<dyn Error>> {
+        Ok(TypesenseExporter {
+            collection: collection.to_string(),
+            out: BufWriter::new(File::create(DOCUMENTS_FILE)?),
+            written: 0,
+        })
+    }
+}
+
+// The facets are declared and every other field is detected on import
+pub fn collection_schema(collection: &str, settings: &IndexSettings) -> serde_json::Value {
+    let mut fields: Vec<serde_json::Value> =
+        exporter::attribute_names(&settings.attributes_for_faceting)
+            .into_iter()
+            .map(|name| json!({ "name": name, "type": "string*", "facet": true, "optional": true }))
+            .collect();
+    fields.push(json!({ "name": ".*", "type": "auto" }));
+    json!({
+        "name": collection,
+        "fields": fields,
+        "enable_nested_fields": true
+    })
+}
+
+impl Exporter for TypesenseExporter {
+    async fn write_page<T: AlgoliaRecord>(
+        &mut self,
+        records: Vec<T>,
+    ) -> Result<(), Box<dyn Error>> {
+        for record in &records {
+            serde_json::to_writer(&mut self.out, &exporter::document(record, Some("id"))?)?;
+            self.out.write_all(b"\n")?;
+        }
+        self.written += records.len();
+        debug!("documents written: {}", self.written);
+        Ok(())
+    }
+
+    async fn finish(mut self, settings: &IndexSettings) -> Result<(), Box<dyn Error>> {
+        self.out.flush()?;
+        info!(
+            "Finished writing {} documents to file: {}",
+            self.written, DOCUMENTS_FILE
+        );
+        let schema_file = File::create(SCHEMA_FILE)?;
+        serde_json::to_writer_pretty(
+            BufWriter::new(schema_file),
+            &collection_schema(&self.collection, settings),
+        )?;
+        info!("collection schema written to file: {}", SCHEMA_FILE);
+        Ok(())
+    }
+}
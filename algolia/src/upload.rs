@@ -0,0 +1,404 @@
+use log::*;
+use reqwest::{header, Client, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{env, error::Error, time::Duration};
+
+const MAX_ATTEMPTS: u32 = 5;
+
+// Connection to the Algolia index - read from the .env file
+#[derive(Debug, Clone)]
+pub struct AlgoliaConfig {
+    pub app_id: String,
+    pub api_key: String,
+    pub index_name: String,
+    // Defaults to https://{appId}.algolia.net - can point to a local stand-in for testing
+    pub host: String,
+}
+
+impl AlgoliaConfig {
+    pub fn from_env() -> AlgoliaConfig {
+        let app_id = env::var("ALGOLIA_APP_ID").expect("Failed to parse ALGOLIA_APP_ID in .env");
+        let api_key = env::var("ALGOLIA_API_KEY").expect("Failed to parse ALGOLIA_API_KEY in .env");
+        let index_name =
+            env::var("ALGOLIA_INDEX_NAME").expect("Failed to parse ALGOLIA_INDEX_NAME in .env");
+        let host = env::var("ALGOLIA_HOST")
+            .unwrap_or_else(|_| "https://{appId}.algolia.net".replace("{appId}", &app_id));
+        AlgoliaConfig {
+            app_id,
+            api_key,
+            index_name,
+            host: host.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn client(&self) -> Result<Client, Box<dyn Error>> {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            "X-Algolia-Application-Id",
+            header::HeaderValue::from_str(&self.app_id)?,
+        );
+        headers.insert(
+            "X-Algolia-API-Key",
+            header::HeaderValue::from_str(&self.api_key)?,
+        );
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(60))
+            .default_headers(headers)
+            .build()?;
+        Ok(client)
+    }
+
+    fn index_url(&self, index_name: &str, path: &str) -> String {
+        "{host}/1/indexes/{indexName}{path}"
+            .replace("{host}", &self.host)
+            .replace("{indexName}", index_name)
+            .replace("{path}", path)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TaskResponse {
+    #[serde(rename = "taskID")]
+    task_id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TaskStatus {
+    status: String,
+}
+
+// An upload to the index with the batch API - the records can be sent in pages.
+// With atomic the records are sent to a temporary index that is moved over the index on finish.
+pub struct IndexUpload {
+    client: Client,
+    config: AlgoliaConfig,
+    index_name: String,
+    batch_size: usize,
+    atomic: bool,
+    task_ids: Vec<i64>,
+    records: usize,
+    deletes: usize,
+}
+
+impl IndexUpload {
+    pub async fn start(
+        config: &AlgoliaConfig,
+        batch_size: usize,
+        atomic: bool,
+    ) -> Result<IndexUpload, Box<dyn Error>> {
+        info!("Starting upload to Algolia index: {}", config.index_name);
+        let client = config.client()?;
+
+        let index_name = if atomic {
+            let tmp_index_name = format!("{}_tmp", config.index_name);
+            // The copy does not touch the records - clear the records left in the temporary
+            // index by an interrupted upload
+            let task_id = clear_index(&client, config, &tmp_index_name).await?;
+            wait_for_task(&client, config, &tmp_index_name, task_id).await?;
+            // Keep the settings, synonyms and rules of the index
+            let task_id = operation(
+                &client,
+                config,
+                &config.index_name,
+                json!({
+                    "operation": "copy",
+                    "destination": tmp_index_name,
+                    "scope": ["settings", "synonyms", "rules"]
+                }),
+            )
+            .await?;
+            wait_for_task(&client, config, &config.index_name, task_id).await?;
+            tmp_index_name
+        } else {
+            config.index_name.clone()
+        };
+
+        Ok(IndexUpload {
+            client,
+            config: config.clone(),
+            index_name,
+            batch_size,
+            atomic,
+            task_ids: Vec::new(),
+            records: 0,
+            deletes: 0,
+        })
+    }
+
+    pub async fn send_records<T: Serialize>(
+        &mut self,
+        records: &[T],
+    ) -> Result<(), Box<dyn Error>> {
+        let requests: Vec<serde_json::Value> = records
+            .iter()
+            .map(|record| json!({ "action": "updateObject", "body": record }))
+            .collect();
+        self.send(&requests).await?;
+        self.records += records.len();
+        Ok(())
+    }
+
+    pub async fn send_deletes(&mut self, object_ids: &[String]) -> Result<(), Box<dyn Error>> {
+        let requests: Vec<serde_json::Value> = object_ids
+            .iter()
+            .map(|object_id| json!({ "action": "deleteObject", "body": { "objectID": object_id } }))
+            .collect();
+        self.send(&requests).await?;
+        self.deletes += object_ids.len();
+        Ok(())
+    }
+
+    async fn send(&mut self, requests: &[serde_json::Value]) -> Result<(), Box<dyn Error>> {
+        for chunk in requests.chunks(self.batch_size.max(1)) {
+            let task_id = send_batch(&self.client, &self.config, &self.index_name, chunk).await?;
+            info!(
+                "batch: {} records: {} taskID: {}",
+                self.task_ids.len() + 1,
+                chunk.len(),
+                task_id
+            );
+            self.task_ids.push(task_id);
+        }
+        Ok(())
+    }
+
+    // Wait for the batches to be published and move the temporary index over the index
+    pub async fn finish(self) -> Result<(), Box<dyn Error>> {
+        for task_id in &self.task_ids {
+            wait_for_task(&self.client, &self.config, &self.index_name, *task_id).await?;
+        }
+
+        if self.atomic {
+            let task_id = operation(
+                &self.client,
+                &self.config,
+                &self.index_name,
+                json!({ "operation": "move", "destination": self.config.index_name }),
+            )
+            .await?;
+            wait_for_task(&self.client, &self.config, &self.index_name, task_id).await?;
+            info!(
+                "moved index: {} to {}",
+                self.index_name, self.config.index_name
+            );
+        }
+        info!(
+            "Finished upload to Algolia: {} records, {} deletes",
+            self.records, self.deletes
+        );
+
+        Ok(())
+    }
+}
+
+// Replace the settings of the index
+pub async fn set_settings<T: Serialize>(
+    config: &AlgoliaConfig,
+    settings: &T,
+) -> Result<(), Box<dyn Error>> {
+    let client = config.client()?;
+    let url = config.index_url(&config.index_name, "/settings");
+    let response = send_with_retry(client.put(url).json(settings)).await?;
+    let task: TaskResponse = response.json().await?;
+    wait_for_task(&client, config, &config.index_name, task.task_id).await?;
+    info!("settings applied to Algolia index: {}", config.index_name);
+    Ok(())
+}
+
+async fn send_batch(
+    client: &Client,
+    config: &AlgoliaConfig,
+    index_name: &str,
+    requests: &[serde_json::Value],
+) -> Result<i64, Box<dyn Error>> {
+    let url = config.index_url(index_name, "/batch");
+    let response = send_with_retry(client.post(url).json(&json!({ "requests": requests }))).await?;
+    let task: TaskResponse = response.json().await?;
+    Ok(task.task_id)
+}
+
+// Delete the records of the index - the settings, synonyms and rules are kept
+async fn clear_index(
+    client: &Client,
+    config: &AlgoliaConfig,
+    index_name: &str,
+) -> Result<i64, Box<dyn Error>> {
+    let url = config.index_url(index_name, "/clear");
+    let response = send_with_retry(client.post(url)).await?;
+    let task: TaskResponse = response.json().await?;
+    Ok(task.task_id)
+}
+
+async fn operation(
+    client: &Client,
+    config: &AlgoliaConfig,
+    index_name: &str,
+    body: serde_json::Value,
+) -> Result<i64, Box<dyn Error>> {
+    let url = config.index_url(index_name, "/operation");
+    let response = send_with_retry(client.post(url).json(&body)).await?;
+    let task: TaskResponse = response.json().await?;
+    Ok(task.task_id)
+}
+
+async fn wait_for_task(
+    client: &Client,
+    config: &AlgoliaConfig,
+    index_name: &str,
+    task_id: i64,
+) -> Result<(), Box<dyn Error>> {
+    let url = config.index_url(index_name, &format!("/task/{}", task_id));
+    loop {
+        let response = send_with_retry(client.get(&url)).await?;
+        let task: TaskStatus = response.json().await?;
+        debug!("taskID: {} status: {}", task_id, task.status);
+        if task.status.eq("published") {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+// Retry on connection errors, throttling and server errors with an exponential backoff
+async fn send_with_retry(request: RequestBuilder) -> Result<Response, Box<dyn Error>> {
+    let mut attempt = 1;
+    loop {
+        let result = request
+            .try_clone()
+            .ok_or("request can not be retried")?
+            .send()
+            .await;
+        let retry_reason = match result {
+            Ok(response) if response.status().is_success() => return Ok(response),
+            Ok(response)
+                if response.status().is_server_error()
+                    || response.status() == StatusCode::TOO_MANY_REQUESTS =>
+            {
+                format!("response: {}", response.status())
+            }
+            Ok(response) => {
+                let status = response.status();
+                let text = response.text().await?;
+                return Err(format!("response: {} message: {}", status, text).into());
+            }
+            Err(err) => err.to_string(),
+        };
+        if attempt >= MAX_ATTEMPTS {
+            return Err(format!("failed after {} attempts: {}", attempt, retry_reason).into());
+        }
+        let backoff = Duration::from_millis(250 * 2u64.pow(attempt));
+        warn!(
+            "attempt {} failed: {}. Retrying in {:?}",
+            attempt, retry_reason, backoff
+        );
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // A local stand-in for Algolia: every write returns a task that is already published.
+    // The requests are recorded as "METHOD path" with the body
+    async fn serve() -> (String, Arc<Mutex<Vec<(String, String)>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let host = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request: Vec<u8> = Vec::new();
+                let mut buf = [0u8; 4096];
+                // Read the headers and the body of the Content-Length
+                let (head, body) = loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length = head
+                            .lines()
+                            .find_map(|line| {
+                                line.to_lowercase()
+                                    .strip_prefix("content-length:")
+                                    .map(|length| length.trim().parse::<usize>().unwrap())
+                            })
+                            .unwrap_or(0);
+                        if body.len() >= length || n == 0 {
+                            break (head.to_string(), body.to_string());
+                        }
+                    }
+                };
+                let request_line: Vec<&str> = head.lines().next().unwrap().split(' ').collect();
+                let (method, path) = (request_line[0], request_line[1]);
+                let response = if method.eq("GET") {
+                    r#"{"status":"published"}"#
+                } else {
+                    r#"{"taskID":1}"#
+                };
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((format!("{} {}", method, path), body));
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.len(),
+                    response
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        (host, requests)
+    }
+
+    #[tokio::test]
+    async fn atomic_upload_clears_copies_batches_and_moves() {
+        let (host, requests) = serve().await;
+        env::set_var("ALGOLIA_APP_ID", "app");
+        env::set_var("ALGOLIA_API_KEY", "key");
+        env::set_var("ALGOLIA_INDEX_NAME", "products");
+        env::set_var("ALGOLIA_HOST", &host);
+        let config = AlgoliaConfig::from_env();
+
+        let mut upload = IndexUpload::start(&config, 2, true).await.unwrap();
+        let records = vec![
+            json!({ "objectID": "1" }),
+            json!({ "objectID": "2" }),
+            json!({ "objectID": "3" }),
+        ];
+        upload.send_records(&records).await.unwrap();
+        upload.finish().await.unwrap();
+
+        // The writes in order - the task polls are left out
+        let requests = requests.lock().unwrap();
+        let writes: Vec<&(String, String)> = requests
+            .iter()
+            .filter(|(request, _)| request.starts_with("POST"))
+            .collect();
+        let paths: Vec<&str> = writes.iter().map(|(request, _)| request.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "POST /1/indexes/products_tmp/clear",
+                "POST /1/indexes/products/operation",
+                "POST /1/indexes/products_tmp/batch",
+                "POST /1/indexes/products_tmp/batch",
+                "POST /1/indexes/products_tmp/operation",
+            ]
+        );
+        assert!(writes[1].1.contains(r#""operation":"copy""#));
+        assert!(writes[1].1.contains(r#""destination":"products_tmp""#));
+        assert!(writes[4].1.contains(r#""operation":"move""#));
+        assert!(writes[4].1.contains(r#""destination":"products""#));
+        // Every task is waited for on the index it was created on
+        assert!(requests
+            .iter()
+            .any(|(request, _)| request.eq("GET /1/indexes/products_tmp/task/1")));
+    }
+}
@@ -0,0 +1,274 @@
+use log::*;
+use reqwest::Client;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::num::NonZeroU32;
+use std::path::Path;
+use vtex::model::{SkuSpecAllowedValue, SkuSpecificationValueAssignment};
+use vtex::utils;
+
+use crate::{
+    productspecassociation, skuspecassociation, specificationgroups, specifications,
+    specificationvalues,
+};
+
+// Build the allowed values (long format) for each product from the SKU attribute sheet
+pub fn gen_allowed_values_file(
+    file_path: String,
+    sku_spec_assignment_file: String,
+    sku_file: String,
+) -> Result<(), Box<dyn Error>> {
+    info!("Starting generation of SKU Specification Allowed Values file");
+    let in_file = File::open(sku_spec_assignment_file)?;
+    let mut reader = csv::Reader::from_reader(in_file);
+    let mut writer = csv::Writer::from_path(file_path)?;
+
+    // Get a lookup HashMap for the product_ref_id for a sku_ref_id
+    let product_ref_id_by_sku_ref_id_lookup = utils::create_sku_product_ref_id_lookup(sku_file);
+    debug!(
+        "product_ref_id_by_sku_ref_id_lookup: {:?}",
+        product_ref_id_by_sku_ref_id_lookup.len()
+    );
+
+    // Position of the specification is the order it first appears in the sheet
+    let mut positions: HashMap<String, i32> = HashMap::new();
+    let mut allowed_values: Vec<SkuSpecAllowedValue> = Vec::new();
+    let mut e = 0;
+    for line in reader.deserialize() {
+        let record: SkuSpecificationValueAssignment = match line {
+            Ok(record) => record,
+            Err(err) => {
+                error!("Error parsing row: {:?}", err);
+                e += 1;
+                continue;
+            }
+        };
+        let product_ref_id = match product_ref_id_by_sku_ref_id_lookup.get(&record.sku_ref_id) {
+            Some(product_ref_id) => product_ref_id,
+            None => {
+                error!(
+                    "sku_ref_id: {} not found in sku file. Skipping record.",
+                    record.sku_ref_id
+                );
+                e += 1;
+                continue;
+            }
+        };
+        let next_position = positions.len() as i32 + 1;
+        let position = *positions
+            .entry(record.name.clone())
+            .or_insert(next_position);
+        let allowed_value = SkuSpecAllowedValue {
+            product_ref_id: product_ref_id.clone(),
+            name: record.name,
+            position,
+            value: record.value.trim().to_string(),
+            field_type: None,
+            group_name: None,
+        };
+        // Don't insert duplicate values
+        if !allowed_values.contains(&allowed_value) {
+            allowed_values.push(allowed_value);
+        }
+    }
+    info!(
+        "Allowed values generated: {}. Records not read (errors): {}",
+        allowed_values.len(),
+        e
+    );
+    utils::write_sku_spec_allowed_values_long(&allowed_values, &mut writer)?;
+    info!("Finished generating SKU Specification Allowed Values file");
+
+    Ok(())
+}
+
+fn path_to_string(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+// Run the whole specification pipeline from a product attribute sheet and a SKU attribute sheet
+// The generated files are written to output_dir so each step can be inspected or re-run
+#[allow(clippy::too_many_arguments)]
+pub async fn load_attributes(
+    output_dir: String,
+    client: &Client,
+    account_name: String,
+    environment: String,
+    prod_specs_assignment_file: String,
+    sku_spec_assignment_file: String,
+    product_file: String,
+    sku_file: String,
+    concurrent_requests: usize,
+    rate_limit: NonZeroU32,
+) -> Result<(), Box<dyn Error>> {
+    info!("Starting attributes load");
+    if !sku_spec_assignment_file.is_empty() && sku_file.is_empty() {
+        return Err("--sku_file must be set when --sku_spec_assigns_file is provided".into());
+    }
+    let output_dir = Path::new(&output_dir);
+    fs::create_dir_all(output_dir)?;
+    // Shared by the lookups of every step
+    let limiter = utils::RequestLimiter::new(concurrent_requests, rate_limit);
+
+    // The SKU allowed values are derived from the SKU attribute sheet
+    let allowed_values_file = if !sku_spec_assignment_file.is_empty() {
+        let allowed_values_file =
+            path_to_string(&output_dir.join("SkuSpecificationAllowedValues.csv"));
+        gen_allowed_values_file(
+            allowed_values_file.clone(),
+            sku_spec_assignment_file.clone(),
+            sku_file.clone(),
+        )?;
+        allowed_values_file
+    } else {
+        "".to_string()
+    };
+
+    // Step 1: Specification Groups
+    let groups_file = path_to_string(&output_dir.join("SpecificationGroups.csv"));
+    specificationgroups::gen_specification_groups_file(
+        groups_file.clone(),
+        client,
+        account_name.clone(),
+        environment.clone(),
+        prod_specs_assignment_file.clone(),
+        allowed_values_file.clone(),
+        product_file.clone(),
+        &limiter,
+    )
+    .await?;
+    specificationgroups::load_specification_groups(
+        groups_file,
+        client,
+        account_name.clone(),
+        environment.clone(),
+        1,
+    )
+    .await?;
+
+    // Step 2: Product Specifications
+    if !prod_specs_assignment_file.is_empty() {
+        let product_specs_file = path_to_string(&output_dir.join("ProductSpecifications.csv"));
+        specifications::gen_product_specifications_file(
+            product_specs_file.clone(),
+            client,
+            account_name.clone(),
+            environment.clone(),
+            prod_specs_assignment_file.clone(),
+            product_file.clone(),
+            &limiter,
+        )
+        .await?;
+        specifications::load_specifications(
+            product_specs_file,
+            client,
+            account_name.clone(),
+            environment.clone(),
+            concurrent_requests,
+        )
+        .await?;
+    }
+
+    // Step 3: SKU Specifications
+    if !sku_spec_assignment_file.is_empty() {
+        let sku_specs_file = path_to_string(&output_dir.join("SkuSpecifications.csv"));
+        specifications::gen_sku_specifications_file(
+            sku_specs_file.clone(),
+            client,
+            account_name.clone(),
+            environment.clone(),
+            allowed_values_file.clone(),
+            product_file.clone(),
+            &limiter,
+        )
+        .await?;
+        specifications::load_specifications(
+            sku_specs_file,
+            client,
+            account_name.clone(),
+            environment.clone(),
+            concurrent_requests,
+        )
+        .await?;
+    }
+
+    // Step 4: Specification Values of the SKU specifications and the product specifications
+    // with a value list
+    let spec_values_file = path_to_string(&output_dir.join("SpecificationValues.csv"));
+    specificationvalues::gen_specification_values_file(
+        spec_values_file.clone(),
+        client,
+        account_name.clone(),
+        environment.clone(),
+        prod_specs_assignment_file.clone(),
+        allowed_values_file,
+        product_file.clone(),
+        &limiter,
+    )
+    .await?;
+    specificationvalues::load_specification_values(
+        spec_values_file,
+        client,
+        account_name.clone(),
+        environment.clone(),
+        concurrent_requests,
+        rate_limit,
+    )
+    .await?;
+
+    // Step 5: Product Specification Associations
+    if !prod_specs_assignment_file.is_empty() {
+        let prod_spec_assoc_file =
+            path_to_string(&output_dir.join("ProductSpecificationAssociations.csv"));
+        productspecassociation::gen_product_spec_association_file(
+            prod_spec_assoc_file.clone(),
+            client,
+            account_name.clone(),
+            environment.clone(),
+            prod_specs_assignment_file,
+            product_file.clone(),
+            &limiter,
+        )
+        .await?;
+        productspecassociation::load_product_spec_associations(
+            prod_spec_assoc_file,
+            client,
+            account_name.clone(),
+            environment.clone(),
+            concurrent_requests,
+            rate_limit,
+        )
+        .await?;
+    }
+
+    // Step 6: SKU Specification Associations
+    if !sku_spec_assignment_file.is_empty() {
+        let sku_spec_assoc_file =
+            path_to_string(&output_dir.join("SkuSpecificationAssociations.csv"));
+        skuspecassociation::gen_sku_spec_association_file(
+            sku_spec_assoc_file.clone(),
+            client,
+            account_name.clone(),
+            environment.clone(),
+            sku_spec_assignment_file,
+            product_file,
+            sku_file,
+            &limiter,
+        )
+        .await?;
+        skuspecassociation::load_sku_spec_associations(
+            sku_spec_assoc_file,
+            client,
+            account_name,
+            environment,
+            concurrent_requests,
+            rate_limit,
+        )
+        .await?;
+    }
+
+    info!("Finished attributes load");
+
+    Ok(())
+}
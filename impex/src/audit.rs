@@ -0,0 +1,365 @@
+use futures::{stream, StreamExt};
+use log::*;
+use reqwest::Client;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::{error::Error, fs::File, io::BufWriter};
+use vtex::model::{
+    CatalogIssue, Dimension, InventoryList, IssueSeverity, IssueType, PriceGet, SkuAndContext,
+    SkuSpecification, SpecificationField,
+};
+use vtex::snapshot::CatalogSource;
+use vtex::utils::{self, RequestLimiter};
+
+// Check all the SKUs in VTEX for data quality issues and write the issues as a work queue.
+// The file is written as JSON when it ends in .json, otherwise as CSV
+pub async fn audit_catalog(
+    file_path: String,
+    catalog: &CatalogSource<'_>,
+    concurrent_requests: usize,
+) -> Result<(), Box<dyn Error>> {
+    info!("Starting catalog audit");
+    let sku_ids = catalog.sku_ids().await?;
+    let item_records = catalog.item_records(&sku_ids).await?;
+    // A failed read stops the audit - a missing price or inventory record is a NoPrice or NoStock
+    let price_records = catalog.price_records(&sku_ids).await?;
+    let inventory_records = catalog.inventory_records(&sku_ids).await?;
+    let required_fields = get_required_fields(&item_records, catalog, concurrent_requests).await?;
+
+    let mut issues: Vec<CatalogIssue> = Vec::new();
+    for (sku_id, sku_ctx) in &item_records {
+        let category_fields = leaf_category(&sku_ctx.product_category_ids)
+            .and_then(|category_id| required_fields.get(category_id))
+            .map(|fields| fields.as_slice())
+            .unwrap_or_default();
+        issues.extend(audit_sku(
+            sku_ctx,
+            price_records.get(sku_id),
+            inventory_records.get(sku_id),
+            category_fields,
+        ));
+    }
+    issues.sort_by_key(|issue| (issue.severity, issue.issue, issue.sku_id));
+
+    if file_path.ends_with(".json") {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(&file_path)?), &issues)?;
+    } else {
+        let mut writer = csv::Writer::from_path(&file_path)?;
+        for issue in &issues {
+            writer.serialize(issue)?;
+        }
+        // Flush the records
+        writer.flush()?;
+    }
+
+    let mut totals: BTreeMap<IssueType, usize> = BTreeMap::new();
+    for issue in &issues {
+        *totals.entry(issue.issue).or_default() += 1;
+    }
+    for (issue_type, total) in &totals {
+        info!("{:?}: {}", issue_type, total);
+    }
+    info!(
+        "skus audited: {} issues written: {}",
+        item_records.len(),
+        issues.len()
+    );
+    info!("finished catalog audit");
+
+    Ok(())
+}
+
+// The issues of an active SKU. Inactive SKUs are not audited
+pub fn audit_sku(
+    sku_ctx: &SkuAndContext,
+    price: Option<&PriceGet>,
+    inventory: Option<&InventoryList>,
+    required_fields: &[SpecificationField],
+) -> Vec<CatalogIssue> {
+    let mut issues: Vec<CatalogIssue> = Vec::new();
+    if !sku_ctx.is_active {
+        return issues;
+    }
+    let mut add = |issue: IssueType, details: Option<String>| {
+        issues.push(CatalogIssue {
+            severity: severity(issue),
+            issue,
+            sku_id: sku_ctx.id,
+            sku_ref_id: sku_ctx.alternate_ids.ref_id.clone(),
+            product_ref_id: sku_ctx.product_ref_id.clone(),
+            details,
+        })
+    };
+
+    if sku_ctx
+        .images
+        .as_ref()
+        .is_none_or(|images| images.is_empty())
+    {
+        add(IssueType::NoImages, None);
+    }
+    if !has_price(price) {
+        add(IssueType::NoPrice, None);
+    }
+    if !has_stock(inventory) {
+        add(
+            IssueType::NoStock,
+            Some("no stock in any warehouse".to_string()),
+        );
+    }
+    if !sku_ctx.is_product_active {
+        add(
+            IssueType::InactiveProduct,
+            Some(format!("product_id: {} is not active", sku_ctx.product_id)),
+        );
+    }
+    match sku_ctx.alternate_ids.ean.as_deref().map(str::trim) {
+        None | Some("") => add(IssueType::MissingEan, None),
+        Some(ean) if !utils::is_gtin(ean) => add(
+            IssueType::MissingEan,
+            Some(format!("not a valid GTIN: {}", ean)),
+        ),
+        _ => {}
+    }
+    let zero_fields = zero_dimensions(&sku_ctx.dimension);
+    if !zero_fields.is_empty() {
+        add(IssueType::MissingDimensions, Some(zero_fields.join(",")));
+    }
+    if sku_ctx.product_description.trim().is_empty() {
+        add(IssueType::EmptyDescription, None);
+    }
+    for field in required_fields {
+        let specifications = if field.is_stock_keeping_unit {
+            &sku_ctx.sku_specifications
+        } else {
+            &sku_ctx.product_specifications
+        };
+        if !has_specification(specifications, field.field_id) {
+            add(
+                IssueType::MissingRequiredSpecification,
+                Some(field.name.clone()),
+            );
+        }
+    }
+
+    issues
+}
+
+pub fn severity(issue: IssueType) -> IssueSeverity {
+    match issue {
+        IssueType::NoImages | IssueType::NoPrice | IssueType::MissingRequiredSpecification => {
+            IssueSeverity::Error
+        }
+        IssueType::NoStock
+        | IssueType::InactiveProduct
+        | IssueType::MissingDimensions
+        | IssueType::EmptyDescription => IssueSeverity::Warning,
+        IssueType::MissingEan => IssueSeverity::Info,
+    }
+}
+
+// A price can be computed from the base price or the cost price and markup
+fn has_price(price: Option<&PriceGet>) -> bool {
+    price.is_some_and(|price| price.base_price.is_some() || price.cost_price.is_some())
+}
+
+fn has_stock(inventory: Option<&InventoryList>) -> bool {
+    inventory.is_some_and(|inventory| {
+        inventory.balance.iter().any(|balance| {
+            balance.has_unlimited_quantity || balance.total_quantity > balance.reserved_quantity
+        })
+    })
+}
+
+// The dimensions (and weight) that are not set
+fn zero_dimensions(dimension: &Dimension) -> Vec<&'static str> {
+    [
+        ("weight", dimension.weight),
+        ("height", dimension.height),
+        ("length", dimension.length),
+        ("width", dimension.width),
+    ]
+    .into_iter()
+    .filter(|(_, value)| *value <= 0.0)
+    .map(|(name, _)| name)
+    .collect()
+}
+
+fn has_specification(specifications: &Option<Vec<SkuSpecification>>, field_id: i32) -> bool {
+    specifications.as_ref().is_some_and(|specifications| {
+        specifications.iter().any(|specification| {
+            specification.field_id == field_id
+                && specification
+                    .field_values
+                    .iter()
+                    .any(|value| !value.trim().is_empty())
+        })
+    })
+}
+
+// The category the product is assigned to - the last id of the path (ex: /1/2/3/)
+fn leaf_category(product_category_ids: &str) -> Option<&str> {
+    product_category_ids.split('/').rfind(|id| !id.is_empty())
+}
+
+// The active required specification fields keyed by category id
+async fn get_required_fields(
+    item_records: &HashMap<i32, SkuAndContext>,
+    catalog: &CatalogSource<'_>,
+    concurrent_requests: usize,
+) -> Result<HashMap<String, Vec<SpecificationField>>, Box<dyn Error>> {
+    info!("Starting get_required_fields()");
+    let category_ids: HashSet<&str> = item_records
+        .values()
+        .filter_map(|sku_ctx| leaf_category(&sku_ctx.product_category_ids))
+        .collect();
+    let category_fields = match catalog {
+        CatalogSource::Api {
+            client,
+            account_name,
+            environment,
+        } => {
+            get_category_fields(
+                category_ids,
+                client,
+                account_name,
+                environment,
+                concurrent_requests,
+            )
+            .await?
+        }
+        CatalogSource::Snapshot(snapshot) => {
+            let mut category_fields: HashMap<String, Vec<SpecificationField>> = HashMap::new();
+            for category_id in category_ids {
+                category_fields.insert(
+                    category_id.to_string(),
+                    snapshot.spec_fields_for_category(category_id)?,
+                );
+            }
+            category_fields
+        }
+    };
+
+    let required_fields: HashMap<String, Vec<SpecificationField>> = category_fields
+        .into_iter()
+        .map(|(category_id, fields)| {
+            let required: Vec<SpecificationField> = fields
+                .into_iter()
+                .filter(|field| field.is_required && field.is_active)
+                .collect();
+            (category_id, required)
+        })
+        .collect();
+    info!(
+        "finished get_required_fields(): categories: {}",
+        required_fields.len()
+    );
+    Ok(required_fields)
+}
+
+// The active specification fields of the categories from the API
+async fn get_category_fields(
+    category_ids: HashSet<&str>,
+    client: &Client,
+    account_name: &str,
+    environment: &str,
+    concurrent_requests: usize,
+) -> Result<HashMap<String, Vec<SpecificationField>>, String> {
+    let limiter = &RequestLimiter::with_concurrency(concurrent_requests);
+    let results: Vec<Result<(String, Vec<i32>), String>> = stream::iter(category_ids)
+        .map(|category_id| async move {
+            let specs = utils::get_spec_fields_for_category(
+                client,
+                account_name,
+                environment,
+                category_id,
+                limiter,
+            )
+            .await?;
+            let field_ids: Vec<i32> = specs
+                .iter()
+                .filter(|spec| spec.is_active)
+                .map(|spec| spec.field_id)
+                .collect();
+            Ok((category_id.to_string(), field_ids))
+        })
+        .buffer_unordered(limiter.concurrency)
+        .collect()
+        .await;
+    let category_specs: Vec<(String, Vec<i32>)> = results.into_iter().collect::<Result<_, _>>()?;
+
+    // Fields can be shared by several categories so each field is read once
+    let field_ids: HashSet<i32> = category_specs
+        .iter()
+        .flat_map(|(_, field_ids)| field_ids.iter().copied())
+        .collect();
+    let results: Vec<Result<(i32, SpecificationField), String>> = stream::iter(field_ids)
+        .map(|field_id| async move {
+            let field = utils::get_specification_field(
+                client,
+                account_name,
+                environment,
+                &field_id.to_string(),
+                limiter,
+            )
+            .await?;
+            Ok((field_id, field))
+        })
+        .buffer_unordered(limiter.concurrency)
+        .collect()
+        .await;
+    let fields: HashMap<i32, SpecificationField> = results.into_iter().collect::<Result<_, _>>()?;
+
+    Ok(category_specs
+        .into_iter()
+        .map(|(category_id, field_ids)| {
+            let category_fields: Vec<SpecificationField> = field_ids
+                .iter()
+                .filter_map(|field_id| fields.get(field_id))
+                .cloned()
+                .collect();
+            (category_id, category_fields)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vtex::model::Balance;
+
+    #[test]
+    fn stock_dimensions_and_category() {
+        let balance = |total_quantity: i32, reserved_quantity: i32| Balance {
+            warehouse_id: "1".to_string(),
+            warehouse_name: "warehouse1".to_string(),
+            total_quantity,
+            reserved_quantity,
+            has_unlimited_quantity: false,
+            time_to_refill: None,
+            date_of_supply_utc: None,
+        };
+        let inventory = InventoryList {
+            sku_id: "1".to_string(),
+            balance: vec![balance(0, 0), balance(2, 2)],
+        };
+        assert!(!has_stock(Some(&inventory)));
+        assert!(!has_stock(None));
+        let inventory = InventoryList {
+            sku_id: "1".to_string(),
+            balance: vec![balance(0, 0), balance(3, 1)],
+        };
+        assert!(has_stock(Some(&inventory)));
+
+        let dimension = Dimension {
+            cubicweight: 0.0,
+            height: 10.0,
+            length: 0.0,
+            weight: 0.0,
+            width: 5.0,
+        };
+        assert_eq!(zero_dimensions(&dimension), vec!["weight", "length"]);
+        assert_eq!(leaf_category("/1/12/37/"), Some("37"));
+        assert_eq!(leaf_category(""), None);
+        assert_eq!(severity(IssueType::MissingEan), IssueSeverity::Info);
+    }
+}
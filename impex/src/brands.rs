@@ -4,6 +4,7 @@ use std::fs::File;
 use std::{collections::HashSet, error::Error};
 
 use reqwest::Client;
+use vtex::cache::{self, LookupCache};
 use vtex::model::{Brand, Product};
 
 pub fn gen_brand_file(file_path: String, product_file: String) -> Result<(), Box<dyn Error>> {
@@ -72,6 +73,9 @@ pub async fn load_brands(
     }
     info!("brand records: {:?}", brand_recs.len());
 
+    // Invalidated before the first create so the lookup is rebuilt even when the load fails
+    LookupCache::new(&account_name, &environment).invalidate(&[cache::BRAND_LOOKUP]);
+
     let bodies = stream::iter(brand_recs)
         .map(|record| {
             let client = &client;
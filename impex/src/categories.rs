@@ -4,6 +4,7 @@ use std::collections::HashMap;
 use std::fs::File;
 
 use reqwest::{Client, StatusCode};
+use vtex::cache::{self, LookupCache};
 use vtex::model::Category;
 
 pub async fn load_categories(
@@ -22,6 +23,9 @@ pub async fn load_categories(
     let mut rdr = csv::Reader::from_reader(input);
     let mut category_ids: HashMap<String, i32> = HashMap::new();
 
+    // Invalidated before the first create - a load that stops partway has already created categories
+    LookupCache::new(&account_name, &environment).invalidate(&[cache::CATEGORY_NAME_LOOKUP]);
+
     for line in rdr.deserialize() {
         let record: Category = line?;
         debug!("{:?}", record);
@@ -141,6 +145,9 @@ pub async fn update_categories(
         e
     );
 
+    // Invalidated before the first update so a failed update does not leave a stale lookup
+    LookupCache::new(&account_name, &environment).invalidate(&[cache::CATEGORY_NAME_LOOKUP]);
+
     // Now process the category_recs
     for line in category_recs {
         debug!("{:?}", line);
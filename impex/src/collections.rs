@@ -0,0 +1,519 @@
+use futures::{executor::block_on, stream, StreamExt};
+use governor::{Jitter, Quota, RateLimiter};
+use log::*;
+use reqwest::{Client, StatusCode};
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+use std::{error::Error, fs::File};
+use vtex::model::{
+    Collection, CollectionProduct, CollectionProducts, CollectionSearch, CollectionSku,
+    SubCollection, SubCollectionSku,
+};
+use vtex::utils::{self, RequestLimiter};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MembershipMode {
+    Add,
+    Remove,
+    // Make the collection membership exactly match the file
+    Replace,
+}
+
+#[derive(Debug, PartialEq)]
+enum SkuOperation {
+    Add { sub_collection_id: i32, sku_id: i32 },
+    Remove { sub_collection_id: i32, sku_id: i32 },
+}
+
+pub async fn load_collections(
+    file_path: String,
+    client: &Client,
+    account_name: String,
+    environment: String,
+    concurrent_requests: usize,
+) -> Result<(), Box<dyn Error>> {
+    info!("Starting collection load");
+    let url = "https://{accountName}.{environment}.com.br/api/catalog/pvt/collection/"
+        .replace("{accountName}", &account_name)
+        .replace("{environment}", &environment);
+
+    let input = File::open(file_path)?;
+    let mut rdr = csv::Reader::from_reader(input);
+
+    // Only create the collections that do not exist
+    let collection_lookup = get_collection_lookup(client, &account_name, &environment).await?;
+    let mut collection_recs: Vec<Collection> = Vec::new();
+    for line in rdr.deserialize() {
+        let record: Collection = line?;
+        if collection_lookup.contains_key(&record.name) {
+            info!("collection: {} exists. Skipping.", record.name);
+        } else {
+            collection_recs.push(record);
+        }
+    }
+    info!("collection records: {:?}", collection_recs.len());
+
+    let bodies = stream::iter(collection_recs)
+        .map(|record| {
+            let client = &client;
+            let url = &url;
+            async move {
+                let response = client.post(url).json(&record).send().await?;
+
+                let status = response.status();
+                info!("collection: {:?}: response: {:?}", record.name, status);
+                let text = response.text().await;
+                if status != StatusCode::OK {
+                    error!("text: {:?}", text);
+                }
+                text
+            }
+        })
+        .buffer_unordered(concurrent_requests);
+    bodies
+        .for_each(|b| async {
+            match b {
+                Ok(b) => info!("output: {:?}", b),
+                Err(e) => error!("error: {:?}", e),
+            }
+        })
+        .await;
+
+    info!("Finished loading collections");
+
+    Ok(())
+}
+
+pub async fn update_collection_skus(
+    file_path: String,
+    client: &Client,
+    account_name: String,
+    environment: String,
+    concurrent_requests: usize,
+    rate_limit: NonZeroU32,
+    mode: MembershipMode,
+) -> Result<(), Box<dyn Error>> {
+    info!("Starting collection sku update: {:?}", mode);
+    let input = File::open(file_path)?;
+    let mut rdr = csv::Reader::from_reader(input);
+
+    // Group the sku ref_ids by collection, keeping the order of the file
+    let mut collection_names: Vec<String> = Vec::new();
+    let mut collection_skus: HashMap<String, Vec<String>> = HashMap::new();
+    let mut ref_ids: HashSet<String> = HashSet::new();
+    let mut e = 0;
+    for line in rdr.deserialize() {
+        match line {
+            Ok(record) => {
+                let record: CollectionSku = record;
+                ref_ids.insert(record.sku_ref_id.clone());
+                collection_skus
+                    .entry(record.collection_name.clone())
+                    .or_insert_with(|| {
+                        collection_names.push(record.collection_name.clone());
+                        Vec::new()
+                    })
+                    .push(record.sku_ref_id);
+            }
+            Err(err) => {
+                error!("Error parsing row: {:?}", err);
+                e += 1;
+            }
+        }
+    }
+    info!(
+        "Records successfully read: {}. Records not read (errors): {}",
+        collection_skus
+            .values()
+            .map(|skus| skus.len())
+            .sum::<usize>(),
+        e
+    );
+
+    let collection_lookup = get_collection_lookup(client, &account_name, &environment).await?;
+    let limiter = RequestLimiter::new(concurrent_requests, rate_limit);
+    let sku_id_lookup = utils::get_sku_ids_by_ref_ids_with_retry(
+        ref_ids.into_iter().collect(),
+        client,
+        &account_name,
+        &environment,
+        &limiter,
+    )
+    .await?;
+    debug!("sku_id_lookup: {}", sku_id_lookup.len());
+
+    let mut operations: Vec<SkuOperation> = Vec::new();
+    for collection_name in collection_names {
+        let collection_id = match collection_lookup.get(&collection_name) {
+            Some(collection_id) => *collection_id,
+            None => {
+                error!(
+                    "collection: {} not found. Create it with the collection import action. Skipping.",
+                    collection_name
+                );
+                continue;
+            }
+        };
+        let mut sku_ids: HashSet<i32> = HashSet::new();
+        let mut not_found = 0;
+        for sku_ref_id in collection_skus.get(&collection_name).unwrap() {
+            match sku_id_lookup.get(sku_ref_id) {
+                Some(sku_id) => {
+                    sku_ids.insert(*sku_id);
+                }
+                None => {
+                    error!(
+                        "sku_ref_id: {} not found. Skipping for collection: {}",
+                        sku_ref_id, collection_name
+                    );
+                    not_found += 1;
+                }
+            }
+        }
+        // Don't remove members because some of the skus of the file could not be found
+        if mode == MembershipMode::Replace && not_found > 0 {
+            error!(
+                "collection: {} has {} skus not found in VTEX. Skipping replace.",
+                collection_name, not_found
+            );
+            continue;
+        }
+        let sub_collection_id = get_sub_collection_id(
+            client,
+            &account_name,
+            &environment,
+            collection_id,
+            &collection_name,
+        )
+        .await?;
+        let current_skus = get_collection_sku_ids(
+            client,
+            &account_name,
+            &environment,
+            collection_id,
+            sub_collection_id,
+        )
+        .await?;
+        let collection_operations =
+            plan_sku_operations(sub_collection_id, &current_skus, &sku_ids, mode);
+        info!(
+            "collection: {} current skus: {} operations: {}",
+            collection_name,
+            current_skus.len(),
+            collection_operations.len()
+        );
+        operations.extend(collection_operations);
+    }
+
+    let add_url = "https://{accountName}.{environment}.com.br/api/catalog/pvt/subcollection/{subCollectionId}/stockkeepingunit"
+        .replace("{accountName}", &account_name)
+        .replace("{environment}", &environment);
+    let remove_url = "https://{accountName}.{environment}.com.br/api/catalog/pvt/subcollection/{subCollectionId}/stockkeepingunit/{skuId}"
+        .replace("{accountName}", &account_name)
+        .replace("{environment}", &environment);
+
+    let lim = Arc::new(RateLimiter::direct(Quota::per_second(rate_limit)));
+
+    let bodies = stream::iter(operations)
+        .map(|operation| {
+            let client = &client;
+            let add_url = &add_url;
+            let remove_url = &remove_url;
+            let lim = Arc::clone(&lim);
+            async move {
+                block_on(lim.until_ready_with_jitter(Jitter::up_to(Duration::from_millis(100))));
+
+                let response = match operation {
+                    SkuOperation::Add {
+                        sub_collection_id,
+                        sku_id,
+                    } => {
+                        let url = add_url
+                            .replace("{subCollectionId}", sub_collection_id.to_string().as_str());
+                        client
+                            .post(url)
+                            .json(&SubCollectionSku { sku_id })
+                            .send()
+                            .await?
+                    }
+                    SkuOperation::Remove {
+                        sub_collection_id,
+                        sku_id,
+                    } => {
+                        let url = remove_url
+                            .replace("{subCollectionId}", sub_collection_id.to_string().as_str())
+                            .replace("{skuId}", sku_id.to_string().as_str());
+                        client.delete(url).send().await?
+                    }
+                };
+
+                let status = response.status();
+                info!("operation: {:?}:  response: {:?}", operation, status);
+                let text = response.text().await;
+                if status != StatusCode::OK {
+                    error!("text: {:?}", text);
+                }
+                text
+            }
+        })
+        .buffer_unordered(concurrent_requests);
+    bodies
+        .for_each(|b| async {
+            match b {
+                Ok(b) => debug!("output: {:?}", b),
+                Err(e) => error!("error: {:?}", e),
+            }
+        })
+        .await;
+
+    info!("Finished collection sku update");
+
+    Ok(())
+}
+
+// Work out which skus to add and remove. current_skus are the skus of the managed (inclusive)
+// sub collection - the skus of the other sub collections are left alone
+fn plan_sku_operations(
+    sub_collection_id: i32,
+    current_skus: &HashSet<i32>,
+    sku_ids: &HashSet<i32>,
+    mode: MembershipMode,
+) -> Vec<SkuOperation> {
+    let mut operations: Vec<SkuOperation> = Vec::new();
+    if mode != MembershipMode::Remove {
+        let mut to_add: Vec<i32> = sku_ids
+            .iter()
+            .filter(|sku_id| !current_skus.contains(sku_id))
+            .copied()
+            .collect();
+        to_add.sort_unstable();
+        operations.extend(to_add.into_iter().map(|sku_id| SkuOperation::Add {
+            sub_collection_id,
+            sku_id,
+        }));
+    }
+    let mut to_remove: Vec<i32> = match mode {
+        MembershipMode::Add => Vec::new(),
+        MembershipMode::Remove => current_skus
+            .iter()
+            .filter(|sku_id| sku_ids.contains(sku_id))
+            .copied()
+            .collect(),
+        MembershipMode::Replace => current_skus
+            .iter()
+            .filter(|sku_id| !sku_ids.contains(sku_id))
+            .copied()
+            .collect(),
+    };
+    to_remove.sort_unstable();
+    operations.extend(to_remove.into_iter().map(|sku_id| SkuOperation::Remove {
+        sub_collection_id,
+        sku_id,
+    }));
+    operations
+}
+
+// Lookup of the collection id by collection name
+async fn get_collection_lookup(
+    client: &Client,
+    account_name: &str,
+    environment: &str,
+) -> Result<HashMap<String, i32>, Box<dyn Error>> {
+    let url = "https://{accountName}.{environment}.com.br/api/catalog_system/pvt/collection/search?page={page}&pageSize=100"
+        .replace("{accountName}", account_name)
+        .replace("{environment}", environment);
+    let mut collection_lookup: HashMap<String, i32> = HashMap::new();
+    let mut page = 1;
+    loop {
+        let url = url.replace("{page}", page.to_string().as_str());
+        let search: CollectionSearch = client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        for item in search.items {
+            collection_lookup.insert(item.name, item.id);
+        }
+        if page >= search.paging.pages {
+            break;
+        }
+        page += 1;
+    }
+    debug!("collection_lookup: {}", collection_lookup.len());
+    Ok(collection_lookup)
+}
+
+// Get the inclusive sub collection of a collection, creating it if needed
+async fn get_sub_collection_id(
+    client: &Client,
+    account_name: &str,
+    environment: &str,
+    collection_id: i32,
+    collection_name: &str,
+) -> Result<i32, Box<dyn Error>> {
+    let url = "https://{accountName}.{environment}.com.br/api/catalog/pvt/collection/{collectionId}/subcollection"
+        .replace("{accountName}", account_name)
+        .replace("{environment}", environment)
+        .replace("{collectionId}", collection_id.to_string().as_str());
+    let sub_collections: Vec<SubCollection> = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    if let Some(sub_collection_id) = sub_collections
+        .iter()
+        .find(|sub_collection| sub_collection.sub_collection_type.eq("Inclusive"))
+        .and_then(|sub_collection| sub_collection.id)
+    {
+        return Ok(sub_collection_id);
+    }
+
+    info!(
+        "creating sub collection for collection: {}",
+        collection_name
+    );
+    let url = "https://{accountName}.{environment}.com.br/api/catalog/pvt/subcollection"
+        .replace("{accountName}", account_name)
+        .replace("{environment}", environment);
+    let sub_collection = SubCollection {
+        id: None,
+        collection_id,
+        name: collection_name.to_string(),
+        sub_collection_type: "Inclusive".to_string(),
+        pre_sale: false,
+        release: false,
+    };
+    let created: SubCollection = client
+        .post(url)
+        .json(&sub_collection)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    created
+        .id
+        .ok_or_else(|| "sub collection created without an id".into())
+}
+
+// Get the current skus of the managed sub collection of a collection. The products of the
+// collection include the skus of every sub collection - the skus of an exclusive sub collection
+// are excluded from the collection, not members
+async fn get_collection_sku_ids(
+    client: &Client,
+    account_name: &str,
+    environment: &str,
+    collection_id: i32,
+    sub_collection_id: i32,
+) -> Result<HashSet<i32>, Box<dyn Error>> {
+    let url = "https://{accountName}.{environment}.com.br/api/catalog/pvt/collection/{collectionId}/products?page={page}&pageSize=100"
+        .replace("{accountName}", account_name)
+        .replace("{environment}", environment)
+        .replace("{collectionId}", collection_id.to_string().as_str());
+    let mut sku_ids: HashSet<i32> = HashSet::new();
+    let mut page = 1;
+    loop {
+        let url = url.replace("{page}", page.to_string().as_str());
+        let products: CollectionProducts = client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        sku_ids.extend(sub_collection_sku_ids(&products.data, sub_collection_id));
+        if page >= products.total_page {
+            break;
+        }
+        page += 1;
+    }
+    Ok(sku_ids)
+}
+
+// The skus of the products that belong to the sub collection
+fn sub_collection_sku_ids(products: &[CollectionProduct], sub_collection_id: i32) -> HashSet<i32> {
+    products
+        .iter()
+        .filter(|product| product.sub_collection_id == sub_collection_id)
+        .map(|product| product.sku_id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_sku_operations_by_mode() {
+        // sku 1 and 2 are in the collection, the file has sku 2 and 3
+        let current_skus: HashSet<i32> = [1, 2].into_iter().collect();
+        let sku_ids: HashSet<i32> = [2, 3].into_iter().collect();
+        let add_3 = SkuOperation::Add {
+            sub_collection_id: 50,
+            sku_id: 3,
+        };
+
+        assert_eq!(
+            plan_sku_operations(50, &current_skus, &sku_ids, MembershipMode::Add),
+            vec![SkuOperation::Add {
+                sub_collection_id: 50,
+                sku_id: 3
+            }]
+        );
+        assert_eq!(
+            plan_sku_operations(50, &current_skus, &sku_ids, MembershipMode::Remove),
+            vec![SkuOperation::Remove {
+                sub_collection_id: 50,
+                sku_id: 2
+            }]
+        );
+        assert_eq!(
+            plan_sku_operations(50, &current_skus, &sku_ids, MembershipMode::Replace),
+            vec![
+                add_3,
+                SkuOperation::Remove {
+                    sub_collection_id: 50,
+                    sku_id: 1
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn exclusive_sub_collection_skus_are_not_members() {
+        // sku 1 is in the inclusive sub collection 50, sku 2 in the exclusive sub collection 60
+        let products = vec![
+            CollectionProduct {
+                product_id: 10,
+                sku_id: 1,
+                sub_collection_id: 50,
+            },
+            CollectionProduct {
+                product_id: 20,
+                sku_id: 2,
+                sub_collection_id: 60,
+            },
+        ];
+        let current_skus = sub_collection_sku_ids(&products, 50);
+        assert_eq!(current_skus, [1].into_iter().collect());
+
+        // Removing the excluded sku does not take it out of the exclusive sub collection
+        let sku_ids: HashSet<i32> = [2].into_iter().collect();
+        assert_eq!(
+            plan_sku_operations(50, &current_skus, &sku_ids, MembershipMode::Remove),
+            vec![]
+        );
+        // Replace does not count the excluded sku as a member to remove
+        let sku_ids: HashSet<i32> = [1].into_iter().collect();
+        assert_eq!(
+            plan_sku_operations(50, &current_skus, &sku_ids, MembershipMode::Replace),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use futures::{executor::block_on, stream, StreamExt};
+use governor::{Jitter, Quota, RateLimiter};
+use log::*;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+use std::{error::Error, fs::File};
+use vtex::csvrecords::SkusInactive;
+use vtex::utils;
+
+// Both the explicit list and the full feed (Skus.csv / Products.csv) are read by the RefId column
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RefIdRecord {
+    ref_id: String,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn deactivate_skus(
+    file_path: String,
+    client: &Client,
+    account_name: String,
+    environment: String,
+    full_feed: bool,
+    max_percent: f64,
+    concurrent_requests: usize,
+    rate_limit: NonZeroU32,
+) -> Result<(), Box<dyn Error>> {
+    info!("Starting SKU deactivation");
+
+    // Get the active skus in VTEX by ref_id
+    let sku_ids = utils::get_all_sku_ids(client, &account_name, &environment).await;
+    let item_records =
+        utils::get_item_records(&sku_ids, client, &account_name, &environment).await?;
+    let active: HashMap<String, i32> = item_records
+        .values()
+        .filter(|item| item.is_active)
+        .map(|item| (item.alternate_ids.ref_id.clone(), item.id))
+        .collect();
+
+    // An explicit list can also be a SkusInactive file with only the SkuId column
+    let headers = csv::Reader::from_path(&file_path)?.headers()?.clone();
+    let ref_ids = if !full_feed && !headers.iter().any(|h| h == "RefId") {
+        read_inactive_sku_ids(&file_path)?
+            .iter()
+            .filter_map(|sku_id| match item_records.get(sku_id) {
+                Some(item) => Some(item.alternate_ids.ref_id.clone()),
+                None => {
+                    warn!("sku_id: {} not found in VTEX. Skipping.", sku_id);
+                    None
+                }
+            })
+            .collect()
+    } else {
+        read_ref_ids(&file_path, full_feed)?
+    };
+
+    let to_deactivate = select_for_deactivation(&active, &ref_ids, full_feed);
+    check_threshold(to_deactivate.len(), active.len(), max_percent)?;
+
+    let url = "https://{accountName}.{environment}.com.br/api/catalog/pvt/stockkeepingunit/{skuId}"
+        .replace("{accountName}", &account_name)
+        .replace("{environment}", &environment);
+    let targets: Vec<(String, String)> = to_deactivate
+        .into_iter()
+        .map(|(ref_id, sku_id)| (ref_id, url.replace("{skuId}", sku_id.to_string().as_str())))
+        .collect();
+    set_inactive(targets, client, concurrent_requests, rate_limit).await;
+
+    info!("finished SKU deactivation");
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn deactivate_products(
+    file_path: String,
+    client: &Client,
+    account_name: String,
+    environment: String,
+    full_feed: bool,
+    max_percent: f64,
+    concurrent_requests: usize,
+    rate_limit: NonZeroU32,
+) -> Result<(), Box<dyn Error>> {
+    info!("Starting product deactivation");
+    let ref_ids = read_ref_ids(&file_path, full_feed)?;
+
+    // Products are found through their skus so a product without skus is not considered
+    let sku_ids = utils::get_all_sku_ids(client, &account_name, &environment).await;
+    let item_records =
+        utils::get_item_records(&sku_ids, client, &account_name, &environment).await?;
+    let active: HashMap<String, i32> = item_records
+        .values()
+        .filter(|item| item.is_product_active)
+        .map(|item| (item.product_ref_id.clone(), item.product_id))
+        .collect();
+
+    let to_deactivate = select_for_deactivation(&active, &ref_ids, full_feed);
+    check_threshold(to_deactivate.len(), active.len(), max_percent)?;
+
+    let url = "https://{accountName}.{environment}.com.br/api/catalog/pvt/product/{productId}"
+        .replace("{accountName}", &account_name)
+        .replace("{environment}", &environment);
+    let targets: Vec<(String, String)> = to_deactivate
+        .into_iter()
+        .map(|(ref_id, product_id)| {
+            (
+                ref_id,
+                url.replace("{productId}", product_id.to_string().as_str()),
+            )
+        })
+        .collect();
+    set_inactive(targets, client, concurrent_requests, rate_limit).await;
+
+    info!("finished product deactivation");
+
+    Ok(())
+}
+
+// With a full feed a row that can not be read is an error - its RefId would be deactivated
+fn read_ref_ids(file_path: &str, full_feed: bool) -> Result<HashSet<String>, Box<dyn Error>> {
+    let input = File::open(file_path)?;
+    let mut rdr = csv::Reader::from_reader(input);
+    let mut ref_ids: HashSet<String> = HashSet::new();
+    let mut e = 0;
+    for line in rdr.deserialize() {
+        match line {
+            Ok(record) => {
+                let record: RefIdRecord = record;
+                ref_ids.insert(record.ref_id.trim().to_string());
+            }
+            Err(err) => {
+                error!("Error parsing row: {:?}", err);
+                e += 1;
+            }
+        }
+    }
+    info!(
+        "Records successfully read: {}. Records not read (errors): {}",
+        ref_ids.len(),
+        e
+    );
+    if full_feed && e > 0 {
+        return Err(format!(
+            "{} rows of the feed: {} could not be read. Aborting.",
+            e, file_path
+        )
+        .into());
+    }
+    Ok(ref_ids)
+}
+
+fn read_inactive_sku_ids(file_path: &str) -> Result<Vec<i32>, Box<dyn Error>> {
+    let input = File::open(file_path)?;
+    let mut rdr = csv::Reader::from_reader(input);
+    let mut sku_ids: Vec<i32> = Vec::new();
+    let mut e = 0;
+    for line in rdr.deserialize() {
+        match line {
+            Ok(record) => {
+                let record: SkusInactive = record;
+                sku_ids.push(record.sku_id);
+            }
+            Err(err) => {
+                error!("Error parsing row: {:?}", err);
+                e += 1;
+            }
+        }
+    }
+    info!(
+        "Records successfully read: {}. Records not read (errors): {}",
+        sku_ids.len(),
+        e
+    );
+    Ok(sku_ids)
+}
+
+// With a full feed, deactivate the active items missing from the feed.
+// Otherwise the file is the explicit list of items to deactivate.
+fn select_for_deactivation(
+    active: &HashMap<String, i32>,
+    ref_ids: &HashSet<String>,
+    full_feed: bool,
+) -> Vec<(String, i32)> {
+    let mut to_deactivate: Vec<(String, i32)> = if full_feed {
+        active
+            .iter()
+            .filter(|(ref_id, _)| !ref_ids.contains(*ref_id))
+            .map(|(ref_id, id)| (ref_id.clone(), *id))
+            .collect()
+    } else {
+        for ref_id in ref_ids {
+            if !active.contains_key(ref_id) {
+                warn!("ref_id: {} is not active in VTEX. Skipping.", ref_id);
+            }
+        }
+        active
+            .iter()
+            .filter(|(ref_id, _)| ref_ids.contains(*ref_id))
+            .map(|(ref_id, id)| (ref_id.clone(), *id))
+            .collect()
+    };
+    to_deactivate.sort();
+    to_deactivate
+}
+
+// Abort when more than max_percent of the active items would be deactivated
+fn check_threshold(
+    to_deactivate: usize,
+    active: usize,
+    max_percent: f64,
+) -> Result<(), Box<dyn Error>> {
+    let percent = if active == 0 {
+        0.0
+    } else {
+        to_deactivate as f64 * 100.0 / active as f64
+    };
+    info!(
+        "items to deactivate: {} of {} active ({:.2}%)",
+        to_deactivate, active, percent
+    );
+    if percent > max_percent {
+        return Err(format!(
+            "{} of {} active items ({:.2}%) would be deactivated which is more than the max_percent of {}%. Aborting.",
+            to_deactivate, active, percent, max_percent
+        )
+        .into());
+    }
+    Ok(())
+}
+
+// Read the current record and PUT it back with IsActive=false so no other field is changed
+async fn set_inactive(
+    targets: Vec<(String, String)>,
+    client: &Client,
+    concurrent_requests: usize,
+    rate_limit: NonZeroU32,
+) {
+    let lim = Arc::new(RateLimiter::direct(Quota::per_second(rate_limit)));
+
+    let bodies = stream::iter(targets)
+        .map(|(ref_id, url)| {
+            let client = &client;
+            let lim = Arc::clone(&lim);
+            async move {
+                block_on(lim.until_ready_with_jitter(Jitter::up_to(Duration::from_millis(100))));
+
+                let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
+                let status = response.status();
+                if status != StatusCode::OK {
+                    let text = response.text().await.unwrap_or_default();
+                    return Err(format!(
+                        "ref_id: {} not read: response: {} text: {}",
+                        ref_id, status, text
+                    ));
+                }
+                let mut record: serde_json::Value =
+                    response.json().await.map_err(|e| e.to_string())?;
+                record["IsActive"] = serde_json::Value::Bool(false);
+                // SKUs are activated again by VTEX when ActivateIfPossible is true
+                if record.get("ActivateIfPossible").is_some() {
+                    record["ActivateIfPossible"] = serde_json::Value::Bool(false);
+                }
+
+                block_on(lim.until_ready_with_jitter(Jitter::up_to(Duration::from_millis(100))));
+                let response = client
+                    .put(&url)
+                    .json(&record)
+                    .send()
+                    .await
+                    .map_err(|e| e.to_string())?;
+
+                let status = response.status();
+                info!("ref_id: {:?}: response: {:?}", ref_id, status);
+                let text = response.text().await.map_err(|e| e.to_string());
+                if status != StatusCode::OK {
+                    error!("text: {:?}", text);
+                }
+                text
+            }
+        })
+        .buffer_unordered(concurrent_requests);
+    bodies
+        .for_each(|b| async {
+            match b {
+                Ok(b) => debug!("output: {:?}", b),
+                Err(e) => error!("error: {:?}", e),
+            }
+        })
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_for_deactivation_feed_and_list() {
+        let active: HashMap<String, i32> = [("A".to_string(), 1), ("B".to_string(), 2)]
+            .into_iter()
+            .collect();
+        let ref_ids: HashSet<String> = ["B".to_string(), "C".to_string()].into_iter().collect();
+
+        // Full feed: A is in VTEX but not in the feed
+        assert_eq!(
+            select_for_deactivation(&active, &ref_ids, true),
+            vec![("A".to_string(), 1)]
+        );
+        // Explicit list: only B is active, C is not in VTEX
+        assert_eq!(
+            select_for_deactivation(&active, &ref_ids, false),
+            vec![("B".to_string(), 2)]
+        );
+        assert!(check_threshold(1, 2, 50.0).is_ok());
+        assert!(check_threshold(2, 2, 50.0).is_err());
+    }
+
+    #[test]
+    fn read_ref_ids_fails_on_unread_feed_rows() {
+        let path = std::env::temp_dir().join(format!("vtex-deactivate-{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "RefId,Name\nA,Shirt\nB\n").unwrap();
+
+        // B can not be read - in a full feed it would be deactivated
+        assert!(read_ref_ids(path, true).is_err());
+        let ref_ids = read_ref_ids(path, false).unwrap();
+        assert_eq!(ref_ids, ["A".to_string()].into_iter().collect());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,444 @@
+use futures::{stream, StreamExt};
+use log::*;
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::{error::Error, fs::File};
+use vtex::model::{
+    CatalogDifference, DifferenceType, Inventory, InventoryList, Price, PriceGet, Product, Sku,
+    SkuAndContext,
+};
+use vtex::snapshot::CatalogSource;
+use vtex::utils::RequestLimiter;
+
+// Values of a record keyed by the column name in the source CSV file
+type Fields = BTreeMap<String, String>;
+
+// Compare the source CSV files with the live account and write the differences per RefId
+#[allow(clippy::too_many_arguments)]
+pub async fn report_differences(
+    file_path: String,
+    catalog: &CatalogSource<'_>,
+    product_file: String,
+    sku_file: String,
+    price_file: String,
+    inventory_file: String,
+    concurrent_requests: usize,
+) -> Result<(), Box<dyn Error>> {
+    info!("Starting catalog reconciliation report");
+    if product_file.is_empty()
+        && sku_file.is_empty()
+        && price_file.is_empty()
+        && inventory_file.is_empty()
+    {
+        return Err("at least one of --product_file, --sku_file, --price_file or --inventory_file must be set".into());
+    }
+
+    // All the SKUs in VTEX are used to find the items missing from the source files
+    let sku_ids = catalog.sku_ids().await?;
+    let item_records = catalog.item_records(&sku_ids).await?;
+
+    let mut differences: Vec<CatalogDifference> = Vec::new();
+    if !product_file.is_empty() {
+        differences.extend(
+            diff_products(&product_file, &item_records, catalog, concurrent_requests).await?,
+        );
+    }
+    if !sku_file.is_empty() {
+        differences.extend(diff_skus(&sku_file, &item_records)?);
+    }
+    if !price_file.is_empty() {
+        let price_records = catalog.price_records(&sku_ids).await?;
+        differences.extend(diff_prices(&price_file, &item_records, &price_records)?);
+    }
+    if !inventory_file.is_empty() {
+        let inventory_records = catalog.inventory_records(&sku_ids).await?;
+        differences.extend(diff_inventory(
+            &inventory_file,
+            &item_records,
+            &inventory_records,
+        )?);
+    }
+
+    let mut writer = csv::Writer::from_path(file_path)?;
+    for difference in &differences {
+        writer.serialize(difference)?;
+    }
+    // Flush the records
+    writer.flush()?;
+    info!("differences written: {}", differences.len());
+    info!("finished catalog reconciliation report");
+
+    Ok(())
+}
+
+async fn diff_products(
+    product_file: &str,
+    item_records: &HashMap<i32, SkuAndContext>,
+    catalog: &CatalogSource<'_>,
+    concurrent_requests: usize,
+) -> Result<Vec<CatalogDifference>, Box<dyn Error>> {
+    let source: HashMap<String, Fields> = read_records::<Product>(product_file)?
+        .iter()
+        .filter_map(|product| {
+            product
+                .ref_id
+                .as_ref()
+                .map(|ref_id| (ref_id.trim().to_string(), product_fields(product)))
+        })
+        .collect();
+
+    // Products in VTEX are found through their skus
+    let mut ref_ids: HashSet<String> = item_records
+        .values()
+        .map(|item| item.product_ref_id.clone())
+        .collect();
+    ref_ids.extend(source.keys().cloned());
+    // A failed read fails the report - the product would be reported as missing in VTEX
+    let limiter = &RequestLimiter::with_concurrency(concurrent_requests);
+    let results: Vec<Result<(String, Option<Product>), String>> = stream::iter(ref_ids)
+        .map(|ref_id| async move {
+            let product = catalog.product_by_ref_id(&ref_id, limiter).await?;
+            Ok((ref_id, product))
+        })
+        .buffer_unordered(limiter.concurrency)
+        .collect()
+        .await;
+    let mut vtex: HashMap<String, Fields> = HashMap::new();
+    for result in results {
+        match result? {
+            (ref_id, Some(product)) => {
+                vtex.insert(ref_id, product_fields(&product));
+            }
+            (ref_id, None) => debug!("product ref_id: {} not in VTEX", ref_id),
+        }
+    }
+
+    Ok(compare_records("product", &source, &vtex))
+}
+
+fn diff_skus(
+    sku_file: &str,
+    item_records: &HashMap<i32, SkuAndContext>,
+) -> Result<Vec<CatalogDifference>, Box<dyn Error>> {
+    let source: HashMap<String, Fields> = read_records::<Sku>(sku_file)?
+        .iter()
+        .map(|sku| (sku.ref_id.trim().to_string(), sku_fields(sku)))
+        .collect();
+    let vtex: HashMap<String, Fields> = item_records
+        .values()
+        .map(|item| (item.alternate_ids.ref_id.clone(), sku_context_fields(item)))
+        .collect();
+
+    Ok(compare_records("sku", &source, &vtex))
+}
+
+fn diff_prices(
+    price_file: &str,
+    item_records: &HashMap<i32, SkuAndContext>,
+    price_records: &HashMap<i32, PriceGet>,
+) -> Result<Vec<CatalogDifference>, Box<dyn Error>> {
+    let source: HashMap<String, Fields> = read_records::<Price>(price_file)?
+        .iter()
+        .map(|price| {
+            (
+                price.ref_id.trim().to_string(),
+                price_fields(
+                    &price.markup,
+                    &price.list_price,
+                    &price.base_price,
+                    &price.cost_price,
+                ),
+            )
+        })
+        .collect();
+    let vtex: HashMap<String, Fields> = price_records
+        .iter()
+        .filter_map(|(sku_id, price)| {
+            item_records.get(sku_id).map(|item| {
+                (
+                    item.alternate_ids.ref_id.clone(),
+                    price_fields(
+                        &price.markup,
+                        &price.list_price,
+                        &price.base_price,
+                        &price.cost_price,
+                    ),
+                )
+            })
+        })
+        .collect();
+
+    Ok(compare_records("price", &source, &vtex))
+}
+
+fn diff_inventory(
+    inventory_file: &str,
+    item_records: &HashMap<i32, SkuAndContext>,
+    inventory_records: &HashMap<i32, InventoryList>,
+) -> Result<Vec<CatalogDifference>, Box<dyn Error>> {
+    // The fields are per warehouse: {warehouseId}:quantity and {warehouseId}:unlimitedQuantity
+    let mut source: HashMap<String, Fields> = HashMap::new();
+    for inventory in read_records::<Inventory>(inventory_file)? {
+        let fields = source
+            .entry(inventory.ref_id.trim().to_string())
+            .or_default();
+        fields.insert(
+            format!("{}:quantity", inventory.warehouse_id),
+            inventory.quantity.to_string(),
+        );
+        fields.insert(
+            format!("{}:unlimitedQuantity", inventory.warehouse_id),
+            inventory.unlimited_quantity.to_string(),
+        );
+    }
+
+    // VTEX returns a balance for every warehouse so only skus with stock count as extra
+    let mut vtex: HashMap<String, Fields> = HashMap::new();
+    for (sku_id, inventory_list) in inventory_records {
+        let ref_id = match item_records.get(sku_id) {
+            Some(item) => item.alternate_ids.ref_id.clone(),
+            None => continue,
+        };
+        let has_stock = inventory_list
+            .balance
+            .iter()
+            .any(|balance| balance.total_quantity > 0 || balance.has_unlimited_quantity);
+        if !has_stock && !source.contains_key(&ref_id) {
+            continue;
+        }
+        let mut fields = Fields::new();
+        for balance in &inventory_list.balance {
+            fields.insert(
+                format!("{}:quantity", balance.warehouse_id),
+                balance.total_quantity.to_string(),
+            );
+            fields.insert(
+                format!("{}:unlimitedQuantity", balance.warehouse_id),
+                balance.has_unlimited_quantity.to_string(),
+            );
+        }
+        vtex.insert(ref_id, fields);
+    }
+
+    Ok(compare_records("inventory", &source, &vtex))
+}
+
+// Only the fields with a value in the source are compared
+fn compare_records(
+    object: &str,
+    source: &HashMap<String, Fields>,
+    vtex: &HashMap<String, Fields>,
+) -> Vec<CatalogDifference> {
+    let mut differences: Vec<CatalogDifference> = Vec::new();
+    let mut ref_ids: Vec<&String> = source.keys().chain(vtex.keys()).collect();
+    ref_ids.sort();
+    ref_ids.dedup();
+    for ref_id in ref_ids {
+        match (source.get(ref_id), vtex.get(ref_id)) {
+            (Some(_), None) => differences.push(CatalogDifference {
+                object: object.to_string(),
+                ref_id: ref_id.clone(),
+                difference: DifferenceType::MissingInVtex,
+                field: None,
+                source_value: None,
+                vtex_value: None,
+            }),
+            (None, Some(_)) => differences.push(CatalogDifference {
+                object: object.to_string(),
+                ref_id: ref_id.clone(),
+                difference: DifferenceType::ExtraInVtex,
+                field: None,
+                source_value: None,
+                vtex_value: None,
+            }),
+            (Some(source_fields), Some(vtex_fields)) => {
+                for (field, source_value) in source_fields {
+                    if source_value.is_empty() {
+                        continue;
+                    }
+                    let vtex_value = vtex_fields.get(field).cloned().unwrap_or_default();
+                    if *source_value != vtex_value {
+                        differences.push(CatalogDifference {
+                            object: object.to_string(),
+                            ref_id: ref_id.clone(),
+                            difference: DifferenceType::Changed,
+                            field: Some(field.clone()),
+                            source_value: Some(source_value.clone()),
+                            vtex_value: Some(vtex_value),
+                        });
+                    }
+                }
+            }
+            (None, None) => (),
+        }
+    }
+    info!("{} differences: {}", object, differences.len());
+    differences
+}
+
+fn read_records<T: DeserializeOwned>(file_path: &str) -> Result<Vec<T>, Box<dyn Error>> {
+    let input = File::open(file_path)?;
+    let mut rdr = csv::Reader::from_reader(input);
+    let mut records: Vec<T> = Vec::new();
+    let mut e = 0;
+    for line in rdr.deserialize() {
+        match line {
+            Ok(record) => records.push(record),
+            Err(err) => {
+                error!("Error parsing row: {:?}", err);
+                e += 1;
+            }
+        }
+    }
+    info!(
+        "Records successfully read: {}. Records not read (errors): {}",
+        records.len(),
+        e
+    );
+    Ok(records)
+}
+
+fn value<T: ToString>(value: &Option<T>) -> String {
+    value
+        .as_ref()
+        .map(|v| v.to_string().trim().to_string())
+        .unwrap_or_default()
+}
+
+fn to_fields(values: Vec<(&str, String)>) -> Fields {
+    values
+        .into_iter()
+        .map(|(field, value)| (field.to_string(), value))
+        .collect()
+}
+
+fn product_fields(product: &Product) -> Fields {
+    to_fields(vec![
+        ("Name", product.name.trim().to_string()),
+        ("DepartmentId", value(&product.department_id)),
+        ("CategoryId", value(&product.category_id)),
+        ("BrandId", value(&product.brand_id)),
+        ("LinkId", value(&product.link_id)),
+        ("IsVisible", value(&product.is_visible)),
+        ("Description", value(&product.description)),
+        ("DescriptionShort", value(&product.description_short)),
+        ("KeyWords", value(&product.key_words)),
+        ("Title", value(&product.title)),
+        ("IsActive", value(&product.is_active)),
+        ("TaxCode", value(&product.tax_code)),
+        ("MetaTagDescription", value(&product.meta_tag_description)),
+        ("SupplierId", value(&product.supplier_id)),
+        ("ShowWithoutStock", value(&product.show_without_stock)),
+        ("Score", value(&product.score)),
+    ])
+}
+
+fn sku_fields(sku: &Sku) -> Fields {
+    to_fields(vec![
+        ("ProductRefId", sku.product_ref_id.trim().to_string()),
+        ("IsActive", value(&sku.is_active)),
+        ("Name", sku.name.trim().to_string()),
+        ("PackagedHeight", sku.packaged_height.to_string()),
+        ("PackagedLength", sku.packaged_length.to_string()),
+        ("PackagedWidth", sku.packaged_width.to_string()),
+        ("PackagedWeightKg", sku.packaged_weight_kg.to_string()),
+        ("Height", value(&sku.height)),
+        ("Length", value(&sku.length)),
+        ("Width", value(&sku.width)),
+        ("WeightKg", value(&sku.weight_kg)),
+        ("IsKit", value(&sku.is_kit)),
+        ("ManufacturerCode", value(&sku.manufacturer_code)),
+        ("CommercialConditionId", value(&sku.commercial_condition_id)),
+        ("MeasurementUnit", value(&sku.measurement_unit)),
+        ("UnitMultiplier", value(&sku.unit_multiplier)),
+    ])
+}
+
+// The same columns as sku_fields() taken from the stockkeepingunitbyid response
+fn sku_context_fields(item: &SkuAndContext) -> Fields {
+    to_fields(vec![
+        ("ProductRefId", item.product_ref_id.clone()),
+        ("IsActive", item.is_active.to_string()),
+        ("Name", item.sku_name.trim().to_string()),
+        ("PackagedHeight", item.dimension.height.to_string()),
+        ("PackagedLength", item.dimension.length.to_string()),
+        ("PackagedWidth", item.dimension.width.to_string()),
+        ("PackagedWeightKg", item.dimension.weight.to_string()),
+        ("Height", item.real_dimension.real_height.to_string()),
+        ("Length", item.real_dimension.real_length.to_string()),
+        ("Width", item.real_dimension.real_width.to_string()),
+        ("WeightKg", item.real_dimension.real_weight.to_string()),
+        ("IsKit", item.is_kit.to_string()),
+        ("ManufacturerCode", value(&item.manufacturer_code)),
+        (
+            "CommercialConditionId",
+            item.commercial_condition_id.to_string(),
+        ),
+        ("MeasurementUnit", item.measurement_unit.clone()),
+        ("UnitMultiplier", item.unit_multiplier.to_string()),
+    ])
+}
+
+fn price_fields(
+    markup: &Option<f32>,
+    list_price: &Option<f32>,
+    base_price: &Option<f32>,
+    cost_price: &Option<f32>,
+) -> Fields {
+    to_fields(vec![
+        ("markup", value(markup)),
+        ("listPrice", value(list_price)),
+        ("basePrice", value(base_price)),
+        ("costPrice", value(cost_price)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_records_missing_extra_and_changed() {
+        let source: HashMap<String, Fields> = [
+            (
+                "A".to_string(),
+                to_fields(vec![
+                    ("Name", "Shirt".to_string()),
+                    ("Title", "".to_string()),
+                ]),
+            ),
+            (
+                "B".to_string(),
+                to_fields(vec![("Name", "Hat".to_string())]),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let vtex: HashMap<String, Fields> = [
+            (
+                "A".to_string(),
+                to_fields(vec![
+                    ("Name", "T-Shirt".to_string()),
+                    ("Title", "T-Shirt".to_string()),
+                ]),
+            ),
+            (
+                "C".to_string(),
+                to_fields(vec![("Name", "Sock".to_string())]),
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        let differences = compare_records("product", &source, &vtex);
+        assert_eq!(differences.len(), 3);
+        // The empty Title in the source is not compared
+        assert_eq!(differences[0].difference, DifferenceType::Changed);
+        assert_eq!(differences[0].field, Some("Name".to_string()));
+        assert_eq!(differences[0].vtex_value, Some("T-Shirt".to_string()));
+        assert_eq!(differences[1].ref_id, "B");
+        assert_eq!(differences[1].difference, DifferenceType::MissingInVtex);
+        assert_eq!(differences[2].ref_id, "C");
+        assert_eq!(differences[2].difference, DifferenceType::ExtraInVtex);
+    }
+}
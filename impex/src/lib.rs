@@ -6,10 +6,18 @@ use std::io::Write;
 use std::num::NonZeroU32;
 use std::sync::Once;
 use std::{env, time::Duration};
+use vtex::cache::LookupCache;
+use vtex::snapshot::{CatalogSource, Snapshot};
+use vtex::utils::{self, RequestLimiter};
 
+mod attributes;
+mod audit;
 mod brands;
 mod categories;
+mod collections;
 mod csvrecords;
+mod deactivate;
+mod diff;
 mod inventory;
 mod prices;
 mod products;
@@ -17,8 +25,10 @@ mod productspecassociation;
 mod similarcategories;
 mod skuean;
 mod skufiles;
+mod skukits;
 mod skus;
 mod skuspecassociation;
+mod snapshot;
 mod specificationgroups;
 mod specifications;
 mod specificationvalues;
@@ -50,6 +60,12 @@ struct Command {
     concurrency: usize,
     rate_limit: NonZeroU32,
     skip_cat_lookup: usize,
+    full_feed: bool,
+    max_percent: f64,
+    price_file: String,
+    inventory_file: String,
+    snapshot_file: String,
+    refresh_cache: bool,
 }
 
 arg_enum! {
@@ -74,7 +90,8 @@ arg_enum! {
     #[derive(Debug)]
     #[allow(non_camel_case_types)]
     enum SpecificationGroupActions {
-        import
+        import,
+        genspecgroupsfile
     }
 }
 
@@ -95,7 +112,9 @@ arg_enum! {
     enum SpecificationValueActions {
         import,
         genspecvaluesfile,
-        genspecvaluesfilealternate
+        genspecvaluesfilealternate,
+        convertallowedvaluestolong,
+        convertallowedvaluestowide
     }
 }
 
@@ -104,7 +123,8 @@ arg_enum! {
     #[allow(non_camel_case_types)]
     enum ProductActions {
         import,
-        update
+        update,
+        deactivate
     }
 }
 
@@ -114,7 +134,8 @@ arg_enum! {
     enum SkuActions {
         import,
         update,
-        count
+        count,
+        deactivate
     }
 }
 
@@ -148,6 +169,26 @@ arg_enum! {
     }
 }
 
+arg_enum! {
+    #[derive(Debug)]
+    #[allow(non_camel_case_types)]
+    enum CollectionActions {
+        import,
+        addskus,
+        removeskus,
+        replaceskus
+    }
+}
+
+arg_enum! {
+    #[derive(Debug)]
+    #[allow(non_camel_case_types)]
+    enum SkuKitActions {
+        import,
+        export
+    }
+}
+
 arg_enum! {
     #[derive(Debug)]
     #[allow(non_camel_case_types)]
@@ -181,6 +222,38 @@ arg_enum! {
     }
 }
 
+arg_enum! {
+    #[derive(Debug)]
+    #[allow(non_camel_case_types)]
+    enum AttributeActions {
+        import
+    }
+}
+
+arg_enum! {
+    #[derive(Debug)]
+    #[allow(non_camel_case_types)]
+    enum DiffActions {
+        report
+    }
+}
+
+arg_enum! {
+    #[derive(Debug)]
+    #[allow(non_camel_case_types)]
+    enum AuditActions {
+        report
+    }
+}
+
+arg_enum! {
+    #[derive(Debug)]
+    #[allow(non_camel_case_types)]
+    enum SnapshotActions {
+        create
+    }
+}
+
 impl Command {
     fn get_command() -> Command {
         // Retrieve variables from the command line
@@ -188,6 +261,12 @@ impl Command {
         .version(crate_version!())
         .author("VTEX")
         .about("Command line interface to import / export data into VTEX")
+        .arg(Arg::with_name("REFRESH_CACHE")
+            .long("refresh_cache")
+            .alias("refresh-cache")
+            .global(true)
+            .help("Rebuilds the cached category, brand, field and field value lookups (data/cache) - they expire after LOOKUP_CACHE_TTL seconds, default 86400")
+            .takes_value(false))
         .subcommand(SubCommand::with_name("category")
             .about("actions on the category into VTEX")
             .version(crate_version!())
@@ -256,6 +335,24 @@ impl Command {
                 .value_name("FILE")
                 .help("Sets the input or output file to read or write to.")
                 .takes_value(true))
+            .arg(Arg::with_name("PRODUCT_SPEC_ASSIGNMENTS_FILE")
+                .required(false)
+                .long("prod_spec_assigns_file")
+                .value_name("PRODUCT_SPEC_ASSIGNMENTS_FILE")
+                .help("Sets the Product Specification Assignments file")
+                .takes_value(true))
+            .arg(Arg::with_name("SKU_SPEC_ALLOWED_VALUES_FILE")
+                .required(false)
+                .long("sku_spec_allowed_values_file")
+                .value_name("SKU_SPEC_ALLOWED_VALUES_FILE")
+                .help("Sets the Sku Specification Allowed Values file")
+                .takes_value(true))
+            .arg(Arg::with_name("PRODUCT_FILE")
+                .required(false)
+                .long("product_file")
+                .value_name("PRODUCT_FILE")
+                .help("Sets the Product file")
+                .takes_value(true))
             .arg(Arg::with_name("CONCURRENCY")
                 .short("c")
                 .long("concurrency")
@@ -324,6 +421,12 @@ impl Command {
                 .value_name("FILE")
                 .help("Sets the input or output file to read or write to.")
                 .takes_value(true))
+            .arg(Arg::with_name("PRODUCT_SPEC_ASSIGNMENTS_FILE")
+                .required(false)
+                .long("prod_spec_assigns_file")
+                .value_name("PRODUCT_SPEC_ASSIGNMENTS_FILE")
+                .help("Sets the Product Specification Assignments file")
+                .takes_value(true))
             .arg(Arg::with_name("SKU_SPEC_ALLOWED_VALUES_FILE")
                 .required(false)
                 .long("sku_spec_allowed_values_file")
@@ -385,6 +488,15 @@ impl Command {
                 .value_name("SKIPCATLOOKUP")
                 .help("If you pass in the category_id it will skip building the category id lookups")
                 .takes_value(true))
+            .arg(Arg::with_name("FEED")
+                .long("feed")
+                .help("deactivate: the file is the full feed - items in VTEX but not in the file are deactivated")
+                .takes_value(false))
+            .arg(Arg::with_name("MAXPERCENT")
+                .long("max_percent")
+                .value_name("MAXPERCENT")
+                .help("deactivate: aborts when more than this percent of the active items would be deactivated - default is 10")
+                .takes_value(true))
         )
         .subcommand(SubCommand::with_name("sku")
             .about("actions on the sku into VTEX")
@@ -416,6 +528,15 @@ impl Command {
                 .value_name("RATELIMIT")
                 .help("Sets the rate limit value (how many calls per second) - default is 40")
                 .takes_value(true))
+            .arg(Arg::with_name("FEED")
+                .long("feed")
+                .help("deactivate: the file is the full feed - items in VTEX but not in the file are deactivated")
+                .takes_value(false))
+            .arg(Arg::with_name("MAXPERCENT")
+                .long("max_percent")
+                .value_name("MAXPERCENT")
+                .help("deactivate: aborts when more than this percent of the active items would be deactivated - default is 10")
+                .takes_value(true))
         )
         .subcommand(SubCommand::with_name("productspecassociation")
             .about("actions on product specification associations into VTEX")
@@ -583,6 +704,68 @@ impl Command {
                 .help("Sets the rate limit value (how many calls per second) - default is 40")
                 .takes_value(true))
         )
+        .subcommand(SubCommand::with_name("skukit")
+            .about("actions on sku kit components into VTEX")
+            .version(crate_version!())
+            .arg(Arg::with_name("ACTION")
+                .required(true)
+                .possible_values(&SkuKitActions::variants())
+                .short("a")
+                .long("action")
+                .value_name("ACTION")
+                .help("The action to perform on the VTEX Object - import, export")
+                .takes_value(true))
+            .arg(Arg::with_name("FILE")
+                .required(true)
+                .short("f")
+                .long("file")
+                .value_name("FILE")
+                .help("Sets the input or output file to read or write to.")
+                .takes_value(true))
+            .arg(Arg::with_name("CONCURRENCY")
+                .short("c")
+                .long("concurrency")
+                .value_name("CONCURRENCY")
+                .help("Sets the concurrency value - default is 1")
+                .takes_value(true))
+            .arg(Arg::with_name("RATELIMIT")
+                .short("r")
+                .long("rate_limit")
+                .value_name("RATELIMIT")
+                .help("Sets the rate limit value (how many calls per second) - default is 40")
+                .takes_value(true))
+        )
+        .subcommand(SubCommand::with_name("collection")
+            .about("actions on collections and their skus into VTEX")
+            .version(crate_version!())
+            .arg(Arg::with_name("ACTION")
+                .required(true)
+                .possible_values(&CollectionActions::variants())
+                .short("a")
+                .long("action")
+                .value_name("ACTION")
+                .help("The action to perform on the VTEX Object - import, addskus, removeskus, replaceskus")
+                .takes_value(true))
+            .arg(Arg::with_name("FILE")
+                .required(true)
+                .short("f")
+                .long("file")
+                .value_name("FILE")
+                .help("Sets the input or output file to read or write to.")
+                .takes_value(true))
+            .arg(Arg::with_name("CONCURRENCY")
+                .short("c")
+                .long("concurrency")
+                .value_name("CONCURRENCY")
+                .help("Sets the concurrency value - default is 1")
+                .takes_value(true))
+            .arg(Arg::with_name("RATELIMIT")
+                .short("r")
+                .long("rate_limit")
+                .value_name("RATELIMIT")
+                .help("Sets the rate limit value (how many calls per second) - default is 40")
+                .takes_value(true))
+        )
         .subcommand(SubCommand::with_name("similarcategory")
             .about("actions on similarcategory into VTEX")
             .version(crate_version!())
@@ -670,6 +853,172 @@ impl Command {
                 .help("Sets the rate limit value (how many calls per second) - default is 40")
                 .takes_value(true))
         )
+        .subcommand(SubCommand::with_name("attributes")
+            .about("generates and loads specification groups, specifications, values and associations from attribute sheets")
+            .version(crate_version!())
+            .arg(Arg::with_name("ACTION")
+                .required(true)
+                .possible_values(&AttributeActions::variants())
+                .short("a")
+                .long("action")
+                .value_name("ACTION")
+                .help("The action to perform on the VTEX Object - import")
+                .takes_value(true))
+            .arg(Arg::with_name("FILE")
+                .required(true)
+                .short("f")
+                .long("file")
+                .value_name("FILE")
+                .help("Sets the directory the generated files are written to.")
+                .takes_value(true))
+            .arg(Arg::with_name("PRODUCT_SPEC_ASSIGNMENTS_FILE")
+                .required(false)
+                .long("prod_spec_assigns_file")
+                .value_name("PRODUCT_SPEC_ASSIGNMENTS_FILE")
+                .help("Sets the Product Specification Assignments file (product attribute sheet)")
+                .takes_value(true))
+            .arg(Arg::with_name("SKU_SPEC_ASSIGNMENTS_FILE")
+                .required(false)
+                .long("sku_spec_assigns_file")
+                .value_name("SKU_SPEC_ASSIGNMENTS_FILE")
+                .help("Sets the Sku Specification Assignments file (sku attribute sheet)")
+                .takes_value(true))
+            .arg(Arg::with_name("PRODUCT_FILE")
+                .required(true)
+                .long("product_file")
+                .value_name("PRODUCT_FILE")
+                .help("Sets the Product file")
+                .takes_value(true))
+            .arg(Arg::with_name("SKU_FILE")
+                .required(false)
+                .long("sku_file")
+                .value_name("SKU_FILE")
+                .help("Sets the Sku file (required with --sku_spec_assigns_file)")
+                .takes_value(true))
+            .arg(Arg::with_name("CONCURRENCY")
+                .short("c")
+                .long("concurrency")
+                .value_name("CONCURRENCY")
+                .help("Sets the concurrency value - default is 1")
+                .takes_value(true))
+            .arg(Arg::with_name("RATELIMIT")
+                .short("r")
+                .long("rate_limit")
+                .value_name("RATELIMIT")
+                .help("Sets the rate limit value (how many calls per second) - default is 30")
+                .takes_value(true))
+        )
+        .subcommand(SubCommand::with_name("diff")
+            .about("reports the differences between the source CSV files and VTEX")
+            .version(crate_version!())
+            .arg(Arg::with_name("ACTION")
+                .required(true)
+                .possible_values(&DiffActions::variants())
+                .short("a")
+                .long("action")
+                .value_name("ACTION")
+                .help("The action to perform - report")
+                .takes_value(true))
+            .arg(Arg::with_name("FILE")
+                .required(true)
+                .short("f")
+                .long("file")
+                .value_name("FILE")
+                .help("Sets the report file to write to.")
+                .takes_value(true))
+            .arg(Arg::with_name("PRODUCT_FILE")
+                .required(false)
+                .long("product_file")
+                .value_name("PRODUCT_FILE")
+                .help("Sets the Product file to compare")
+                .takes_value(true))
+            .arg(Arg::with_name("SKU_FILE")
+                .required(false)
+                .long("sku_file")
+                .value_name("SKU_FILE")
+                .help("Sets the Sku file to compare")
+                .takes_value(true))
+            .arg(Arg::with_name("PRICE_FILE")
+                .required(false)
+                .long("price_file")
+                .value_name("PRICE_FILE")
+                .help("Sets the Price file to compare")
+                .takes_value(true))
+            .arg(Arg::with_name("INVENTORY_FILE")
+                .required(false)
+                .long("inventory_file")
+                .value_name("INVENTORY_FILE")
+                .help("Sets the Inventory file to compare")
+                .takes_value(true))
+            .arg(Arg::with_name("SNAPSHOT_FILE")
+                .required(false)
+                .long("from_snapshot")
+                .value_name("SNAPSHOT_FILE")
+                .help("Reads the account from a snapshot (vtex_impex snapshot) instead of the API")
+                .takes_value(true))
+            .arg(Arg::with_name("CONCURRENCY")
+                .short("c")
+                .long("concurrency")
+                .value_name("CONCURRENCY")
+                .help("Sets the concurrency value - default is 1")
+                .takes_value(true))
+        )
+        .subcommand(SubCommand::with_name("audit")
+            .about("reports the data quality issues of the SKUs in VTEX")
+            .version(crate_version!())
+            .arg(Arg::with_name("ACTION")
+                .required(false)
+                .possible_values(&AuditActions::variants())
+                .short("a")
+                .long("action")
+                .value_name("ACTION")
+                .help("The action to perform - report (default)")
+                .takes_value(true))
+            .arg(Arg::with_name("FILE")
+                .required(true)
+                .short("f")
+                .long("file")
+                .value_name("FILE")
+                .help("Sets the issues file to write to - JSON when the file ends in .json, otherwise CSV")
+                .takes_value(true))
+            .arg(Arg::with_name("SNAPSHOT_FILE")
+                .required(false)
+                .long("from_snapshot")
+                .value_name("SNAPSHOT_FILE")
+                .help("Reads the account from a snapshot (vtex_impex snapshot) instead of the API")
+                .takes_value(true))
+            .arg(Arg::with_name("CONCURRENCY")
+                .short("c")
+                .long("concurrency")
+                .value_name("CONCURRENCY")
+                .help("Sets the concurrency value - default is 1")
+                .takes_value(true))
+        )
+        .subcommand(SubCommand::with_name("snapshot")
+            .about("downloads the catalog, prices and inventory of the account into a SQLite file")
+            .version(crate_version!())
+            .arg(Arg::with_name("ACTION")
+                .required(false)
+                .possible_values(&SnapshotActions::variants())
+                .short("a")
+                .long("action")
+                .value_name("ACTION")
+                .help("The action to perform - create (default)")
+                .takes_value(true))
+            .arg(Arg::with_name("FILE")
+                .required(true)
+                .short("f")
+                .long("file")
+                .value_name("FILE")
+                .help("Sets the SQLite file to write to - an existing snapshot is replaced")
+                .takes_value(true))
+            .arg(Arg::with_name("CONCURRENCY")
+                .short("c")
+                .long("concurrency")
+                .value_name("CONCURRENCY")
+                .help("Sets the concurrency value - default is 1")
+                .takes_value(true))
+        )
         .get_matches();
 
         let mut command = Command {
@@ -682,8 +1031,14 @@ impl Command {
             product_file: "".to_string(),
             sku_file: "".to_string(),
             concurrency: 1,
-            rate_limit: NonZeroU32::new(1).unwrap(),
+            rate_limit: NonZeroU32::new(utils::DEFAULT_RATE_LIMIT).unwrap(),
             skip_cat_lookup: 0,
+            full_feed: false,
+            max_percent: 10.0,
+            price_file: "".to_string(),
+            inventory_file: "".to_string(),
+            snapshot_file: "".to_string(),
+            refresh_cache: matches.is_present("REFRESH_CACHE"),
         };
 
         match matches.subcommand() {
@@ -713,8 +1068,18 @@ impl Command {
             }
             ("specificationgroup", Some(m)) => {
                 command.object = "specificationgroup".to_string();
+                command.action = m.value_of("ACTION").unwrap().to_string();
                 command.input_file = m.value_of("FILE").expect("-f <FILE> must be set to the input file (example: data/specificationgrouops.csv").to_string();
                 debug!("input_file: {}", command.input_file);
+                command.prod_spec_assign_file = m
+                    .value_of("PRODUCT_SPEC_ASSIGNMENTS_FILE")
+                    .unwrap_or("")
+                    .to_string();
+                command.sku_spec_allowed_values_file = m
+                    .value_of("SKU_SPEC_ALLOWED_VALUES_FILE")
+                    .unwrap_or("")
+                    .to_string();
+                command.product_file = m.value_of("PRODUCT_FILE").unwrap_or("").to_string();
                 command.concurrency = m
                     .value_of("CONCURRENCY")
                     .unwrap_or("1")
@@ -751,6 +1116,10 @@ impl Command {
                 command.action = m.value_of("ACTION").unwrap().to_string();
                 command.input_file = m.value_of("FILE").expect("-f <FILE> must be set to the input file (example: data/specificationvalues.csv").to_string();
                 debug!("input_file: {}", command.input_file);
+                command.prod_spec_assign_file = m
+                    .value_of("PRODUCT_SPEC_ASSIGNMENTS_FILE")
+                    .unwrap_or("")
+                    .to_string();
                 command.sku_spec_allowed_values_file = m
                     .value_of("SKU_SPEC_ALLOWED_VALUES_FILE")
                     .unwrap_or("")
@@ -761,7 +1130,7 @@ impl Command {
                     .unwrap_or("1")
                     .parse::<usize>()
                     .expect("CONCURRENCY must be a positive integer between 1 and 24");
-                command.rate_limit = m.value_of("RATE_LIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
+                command.rate_limit = m.value_of("RATELIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
             }
             ("product", Some(m)) => {
                 command.object = "product".to_string();
@@ -772,8 +1141,14 @@ impl Command {
                     .to_string();
                 debug!("input_file: {}", command.input_file);
                 command.concurrency = m.value_of("CONCURRENCY").unwrap_or("1").parse::<usize>().expect("CONCURRENCY must be a positive integer between 1 and 24. Default is 1 - Recommended");
-                command.rate_limit = m.value_of("RATE_LIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
+                command.rate_limit = m.value_of("RATELIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
                 command.skip_cat_lookup = m.value_of("SKIPCATLOOKUP").unwrap_or("0").parse::<usize>().expect("SKIPCATLOOKUP must be a 0 or 1. Default is 0 - perform category lookup, 1 will skip the category lookup");
+                command.full_feed = m.is_present("FEED");
+                command.max_percent = m
+                    .value_of("MAXPERCENT")
+                    .unwrap_or("10")
+                    .parse::<f64>()
+                    .expect("MAXPERCENT must be a number between 0 and 100. Default is 10");
             }
             ("sku", Some(m)) => {
                 command.object = "sku".to_string();
@@ -784,7 +1159,13 @@ impl Command {
                     .to_string();
                 debug!("input_file: {}", command.input_file);
                 command.concurrency = m.value_of("CONCURRENCY").unwrap_or("1").parse::<usize>().expect("CONCURRENCY must be a positive integer between 1 and 24. Default is 1 - Recommended");
-                command.rate_limit = m.value_of("RATE_LIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
+                command.rate_limit = m.value_of("RATELIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
+                command.full_feed = m.is_present("FEED");
+                command.max_percent = m
+                    .value_of("MAXPERCENT")
+                    .unwrap_or("10")
+                    .parse::<f64>()
+                    .expect("MAXPERCENT must be a number between 0 and 100. Default is 10");
             }
             ("productspecassociation", Some(m)) => {
                 command.object = "productspecassociation".to_string();
@@ -797,7 +1178,7 @@ impl Command {
                     .to_string();
                 command.product_file = m.value_of("PRODUCT_FILE").unwrap_or("").to_string();
                 command.concurrency = m.value_of("CONCURRENCY").unwrap_or("1").parse::<usize>().expect("CONCURRENCY must be a positive integer between 1 and 24. Default is 1 - Recommended");
-                command.rate_limit = m.value_of("RATE_LIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
+                command.rate_limit = m.value_of("RATELIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
             }
             ("skuspecassociation", Some(m)) => {
                 command.object = "skuspecassociation".to_string();
@@ -811,7 +1192,7 @@ impl Command {
                 command.product_file = m.value_of("PRODUCT_FILE").unwrap_or("").to_string();
                 command.sku_file = m.value_of("SKU_FILE").unwrap_or("").to_string();
                 command.concurrency = m.value_of("CONCURRENCY").unwrap_or("1").parse::<usize>().expect("CONCURRENCY must be a positive integer between 1 and 24. Default is 1 - Recommended");
-                command.rate_limit = m.value_of("RATE_LIMIT").unwrap_or("30").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 30 - Recommended");
+                command.rate_limit = m.value_of("RATELIMIT").unwrap_or("30").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 30 - Recommended");
             }
             ("skufile", Some(m)) => {
                 command.object = "skufile".to_string();
@@ -823,7 +1204,7 @@ impl Command {
                 debug!("input_file: {}", command.input_file);
                 command.sku_file = m.value_of("SKU_FILE").unwrap_or("").to_string();
                 command.concurrency = m.value_of("CONCURRENCY").unwrap_or("1").parse::<usize>().expect("CONCURRENCY must be a positive integer between 1 and 24. Default is 1 - Recommended");
-                command.rate_limit = m.value_of("RATE_LIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
+                command.rate_limit = m.value_of("RATELIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
             }
             ("skuean", Some(m)) => {
                 command.object = "skuean".to_string();
@@ -835,7 +1216,31 @@ impl Command {
                 debug!("input_file: {}", command.input_file);
                 command.sku_file = m.value_of("SKU_FILE").unwrap_or("").to_string();
                 command.concurrency = m.value_of("CONCURRENCY").unwrap_or("1").parse::<usize>().expect("CONCURRENCY must be a positive integer between 1 and 24. Default is 1 - Recommended");
-                command.rate_limit = m.value_of("RATE_LIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
+                command.rate_limit = m.value_of("RATELIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
+            }
+            ("skukit", Some(m)) => {
+                command.object = "skukit".to_string();
+                command.action = m.value_of("ACTION").unwrap().to_string();
+                command.input_file = m
+                    .value_of("FILE")
+                    .expect("-f <FILE> must be set to the input file (example: data/SkuKits.csv")
+                    .to_string();
+                debug!("input_file: {}", command.input_file);
+                command.concurrency = m.value_of("CONCURRENCY").unwrap_or("1").parse::<usize>().expect("CONCURRENCY must be a positive integer between 1 and 24. Default is 1 - Recommended");
+                command.rate_limit = m.value_of("RATELIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
+            }
+            ("collection", Some(m)) => {
+                command.object = "collection".to_string();
+                command.action = m.value_of("ACTION").unwrap().to_string();
+                command.input_file = m
+                    .value_of("FILE")
+                    .expect(
+                        "-f <FILE> must be set to the input file (example: data/Collections.csv",
+                    )
+                    .to_string();
+                debug!("input_file: {}", command.input_file);
+                command.concurrency = m.value_of("CONCURRENCY").unwrap_or("1").parse::<usize>().expect("CONCURRENCY must be a positive integer between 1 and 24. Default is 1 - Recommended");
+                command.rate_limit = m.value_of("RATELIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
             }
             ("similarcategory", Some(m)) => {
                 command.object = "similarcategory".to_string();
@@ -856,7 +1261,7 @@ impl Command {
                     .to_string();
                 debug!("input_file: {}", command.input_file);
                 command.concurrency = m.value_of("CONCURRENCY").unwrap_or("2").parse::<usize>().expect("CONCURRENCY must be a positive integer between 1 and 24. Default is 2 - Recommended");
-                command.rate_limit = m.value_of("RATE_LIMIT").unwrap_or("30").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 30 - Recommended");
+                command.rate_limit = m.value_of("RATELIMIT").unwrap_or("30").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 30 - Recommended");
             }
             ("inventory", Some(m)) => {
                 command.object = "inventory".to_string();
@@ -867,7 +1272,68 @@ impl Command {
                     .to_string();
                 debug!("input_file: {}", command.input_file);
                 command.concurrency = m.value_of("CONCURRENCY").unwrap_or("1").parse::<usize>().expect("CONCURRENCY must be a positive integer between 1 and 24. Default is 1 - Recommended");
-                command.rate_limit = m.value_of("RATE_LIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
+                command.rate_limit = m.value_of("RATELIMIT").unwrap_or("40").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 40 - Recommended");
+            }
+            ("attributes", Some(m)) => {
+                command.object = "attributes".to_string();
+                command.action = m.value_of("ACTION").unwrap().to_string();
+                command.input_file = m
+                    .value_of("FILE")
+                    .expect(
+                        "-f <FILE> must be set to the output directory (example: data/attributes",
+                    )
+                    .to_string();
+                debug!("input_file: {}", command.input_file);
+                command.prod_spec_assign_file = m
+                    .value_of("PRODUCT_SPEC_ASSIGNMENTS_FILE")
+                    .unwrap_or("")
+                    .to_string();
+                command.sku_spec_assign_file = m
+                    .value_of("SKU_SPEC_ASSIGNMENTS_FILE")
+                    .unwrap_or("")
+                    .to_string();
+                command.product_file = m.value_of("PRODUCT_FILE").unwrap_or("").to_string();
+                command.sku_file = m.value_of("SKU_FILE").unwrap_or("").to_string();
+                command.concurrency = m.value_of("CONCURRENCY").unwrap_or("1").parse::<usize>().expect("CONCURRENCY must be a positive integer between 1 and 24. Default is 1 - Recommended");
+                command.rate_limit = m.value_of("RATELIMIT").unwrap_or("30").parse::<NonZeroU32>().expect("RATE_LIMIT must be a positive integer between 1 and 200. Default is 30 - Recommended");
+            }
+            ("diff", Some(m)) => {
+                command.object = "diff".to_string();
+                command.action = m.value_of("ACTION").unwrap().to_string();
+                command.input_file = m
+                    .value_of("FILE")
+                    .expect(
+                        "-f <FILE> must be set to the report file (example: data/Differences.csv",
+                    )
+                    .to_string();
+                debug!("input_file: {}", command.input_file);
+                command.product_file = m.value_of("PRODUCT_FILE").unwrap_or("").to_string();
+                command.sku_file = m.value_of("SKU_FILE").unwrap_or("").to_string();
+                command.price_file = m.value_of("PRICE_FILE").unwrap_or("").to_string();
+                command.inventory_file = m.value_of("INVENTORY_FILE").unwrap_or("").to_string();
+                command.snapshot_file = m.value_of("SNAPSHOT_FILE").unwrap_or("").to_string();
+                command.concurrency = m.value_of("CONCURRENCY").unwrap_or("1").parse::<usize>().expect("CONCURRENCY must be a positive integer between 1 and 24. Default is 1 - Recommended");
+            }
+            ("audit", Some(m)) => {
+                command.object = "audit".to_string();
+                command.action = m.value_of("ACTION").unwrap_or("report").to_string();
+                command.input_file = m
+                    .value_of("FILE")
+                    .expect("-f <FILE> must be set to the issues file (example: data/Issues.csv")
+                    .to_string();
+                debug!("input_file: {}", command.input_file);
+                command.snapshot_file = m.value_of("SNAPSHOT_FILE").unwrap_or("").to_string();
+                command.concurrency = m.value_of("CONCURRENCY").unwrap_or("1").parse::<usize>().expect("CONCURRENCY must be a positive integer between 1 and 24. Default is 1 - Recommended");
+            }
+            ("snapshot", Some(m)) => {
+                command.object = "snapshot".to_string();
+                command.action = m.value_of("ACTION").unwrap_or("create").to_string();
+                command.input_file = m
+                    .value_of("FILE")
+                    .expect("-f <FILE> must be set to the snapshot file (example: data/catalog.db")
+                    .to_string();
+                debug!("input_file: {}", command.input_file);
+                command.concurrency = m.value_of("CONCURRENCY").unwrap_or("1").parse::<usize>().expect("CONCURRENCY must be a positive integer between 1 and 24. Default is 1 - Recommended");
             }
             _ => error!("no match"),
         }
@@ -882,6 +1348,9 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
     dotenv::dotenv().expect("Failed to read .env file");
     let account_name = env::var("ACCOUNT_NAME").expect("Failed to parse ACCOUNT_NAME");
     let environment = env::var("ENVIRONMENT").expect("Failed to parse ENVIRONMENT");
+    if cmd.refresh_cache {
+        LookupCache::new(&account_name, &environment).clear();
+    }
     let vtex_api_key =
         env::var("VTEX_API_APPKEY").expect("Failed to parse VTEX_API_APPKEY in .env");
     let vtex_api_apptoken =
@@ -901,6 +1370,22 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
         .timeout(Duration::from_secs(12))
         .default_headers(headers)
         .build()?;
+    // Bounds the calls that build the lookups
+    let limiter = RequestLimiter::new(cmd.concurrency, cmd.rate_limit);
+    // diff and audit can read the account from a snapshot instead of the API
+    let snapshot = if cmd.snapshot_file.is_empty() {
+        None
+    } else {
+        Some(Snapshot::open(&cmd.snapshot_file)?)
+    };
+    let catalog = match &snapshot {
+        Some(snapshot) => CatalogSource::Snapshot(snapshot),
+        None => CatalogSource::Api {
+            client: &client,
+            account_name: &account_name,
+            environment: &environment,
+        },
+    };
 
     if cmd.object.eq("category") {
         if cmd.action.eq("import") {
@@ -945,15 +1430,30 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
             brands::gen_brand_file(cmd.input_file.to_string(), cmd.product_file)?;
         }
     } else if cmd.object.eq("specificationgroup") {
-        // Load specification groups
-        specificationgroups::load_specification_groups(
-            cmd.input_file.to_string(),
-            &client,
-            account_name,
-            environment,
-            cmd.concurrency,
-        )
-        .await?;
+        if cmd.action.eq("import") {
+            // Load specification groups
+            specificationgroups::load_specification_groups(
+                cmd.input_file.to_string(),
+                &client,
+                account_name,
+                environment,
+                cmd.concurrency,
+            )
+            .await?;
+        } else if cmd.action.eq("genspecgroupsfile") {
+            // Generate the specification groups file
+            specificationgroups::gen_specification_groups_file(
+                cmd.input_file.to_string(),
+                &client,
+                account_name,
+                environment,
+                cmd.prod_spec_assign_file.to_string(),
+                cmd.sku_spec_allowed_values_file.to_string(),
+                cmd.product_file.to_string(),
+                &limiter,
+            )
+            .await?;
+        }
     } else if cmd.object.eq("specification") {
         // Load specifications
         if cmd.action.eq("import") {
@@ -973,6 +1473,7 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
                 environment,
                 cmd.prod_spec_assign_file,
                 cmd.product_file,
+                &limiter,
             )
             .await?;
         } else if cmd.action.eq("genskuspecsfile") {
@@ -983,6 +1484,7 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
                 environment,
                 cmd.sku_spec_allowed_values_file,
                 cmd.product_file,
+                &limiter,
             )
             .await?;
         } else if cmd.action.eq("genskuspecsfilealternate") {
@@ -993,6 +1495,7 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
                 environment,
                 cmd.sku_spec_allowed_values_file,
                 cmd.product_file,
+                &limiter,
             )
             .await?;
         }
@@ -1014,8 +1517,10 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
                 &client,
                 account_name,
                 environment,
+                cmd.prod_spec_assign_file,
                 cmd.sku_spec_allowed_values_file,
                 cmd.product_file,
+                &limiter,
             )
             .await?;
         } else if cmd.action.eq("genspecvaluesfilealternate") {
@@ -1025,8 +1530,19 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
                 account_name,
                 environment,
                 cmd.sku_spec_allowed_values_file,
+                &limiter,
             )
             .await?
+        } else if cmd.action.eq("convertallowedvaluestolong") {
+            specificationvalues::convert_allowed_values_to_long(
+                cmd.input_file.to_string(),
+                cmd.sku_spec_allowed_values_file,
+            )?;
+        } else if cmd.action.eq("convertallowedvaluestowide") {
+            specificationvalues::convert_allowed_values_to_wide(
+                cmd.input_file.to_string(),
+                cmd.sku_spec_allowed_values_file,
+            )?;
         }
     } else if cmd.object.eq("product") {
         // Load products
@@ -1052,6 +1568,18 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
                 cmd.skip_cat_lookup,
             )
             .await?;
+        } else if cmd.action.eq("deactivate") {
+            deactivate::deactivate_products(
+                cmd.input_file.to_string(),
+                &client,
+                account_name,
+                environment,
+                cmd.full_feed,
+                cmd.max_percent,
+                cmd.concurrency,
+                cmd.rate_limit,
+            )
+            .await?;
         }
     } else if cmd.object.eq("sku") {
         // Load skus
@@ -1077,6 +1605,18 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
             .await?;
         } else if cmd.action.eq("count") {
             skus::count_skus(&client, account_name, environment).await?;
+        } else if cmd.action.eq("deactivate") {
+            deactivate::deactivate_skus(
+                cmd.input_file.to_string(),
+                &client,
+                account_name,
+                environment,
+                cmd.full_feed,
+                cmd.max_percent,
+                cmd.concurrency,
+                cmd.rate_limit,
+            )
+            .await?;
         }
     } else if cmd.object.eq("productspecassociation") {
         // Load product specs
@@ -1098,6 +1638,7 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
                 environment,
                 cmd.prod_spec_assign_file,
                 cmd.product_file,
+                &limiter,
             )
             .await?;
         } else if cmd.action.eq("genproductspecassocfilerootcategory") {
@@ -1107,6 +1648,7 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
                 account_name,
                 environment,
                 cmd.prod_spec_assign_file,
+                &limiter,
             )
             .await?;
         }
@@ -1131,6 +1673,7 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
                 cmd.sku_spec_assign_file,
                 cmd.product_file,
                 cmd.sku_file,
+                &limiter,
             )
             .await?;
         } else if cmd.action.eq("genskuspecassignfilealternate") {
@@ -1153,6 +1696,7 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
                 cmd.sku_spec_assign_file,
                 cmd.product_file,
                 cmd.sku_file,
+                &limiter,
             )
             .await?
         }
@@ -1200,6 +1744,58 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
             )
             .await?;
         }
+    } else if cmd.object.eq("skukit") {
+        if cmd.action.eq("import") {
+            // Load sku kit components
+            skukits::load_sku_kits(
+                cmd.input_file.to_string(),
+                &client,
+                account_name,
+                environment,
+                cmd.concurrency,
+                cmd.rate_limit,
+            )
+            .await?;
+        } else if cmd.action.eq("export") {
+            // Export sku kit components
+            skukits::export_sku_kits(
+                cmd.input_file.to_string(),
+                &client,
+                account_name,
+                environment,
+                cmd.concurrency,
+            )
+            .await?;
+        }
+    } else if cmd.object.eq("collection") {
+        if cmd.action.eq("import") {
+            // Create collections
+            collections::load_collections(
+                cmd.input_file.to_string(),
+                &client,
+                account_name,
+                environment,
+                cmd.concurrency,
+            )
+            .await?;
+        } else {
+            // Add, remove or replace the skus of collections
+            let mode = match cmd.action.as_str() {
+                "addskus" => collections::MembershipMode::Add,
+                "removeskus" => collections::MembershipMode::Remove,
+                _ => collections::MembershipMode::Replace,
+            };
+            collections::update_collection_skus(
+                cmd.input_file.to_string(),
+                &client,
+                account_name,
+                environment,
+                cmd.concurrency,
+                cmd.rate_limit,
+                mode,
+            )
+            .await?;
+        }
     } else if cmd.object.eq("similarcategory") {
         // Load similar categories
         if cmd.action.eq("import") {
@@ -1238,6 +1834,54 @@ pub async fn run() -> Result<(), Box<dyn Error>> {
             )
             .await?;
         }
+    } else if cmd.object.eq("attributes") {
+        // Generate and load the whole specification pipeline
+        if cmd.action.eq("import") {
+            attributes::load_attributes(
+                cmd.input_file.to_string(),
+                &client,
+                account_name,
+                environment,
+                cmd.prod_spec_assign_file.to_string(),
+                cmd.sku_spec_assign_file.to_string(),
+                cmd.product_file.to_string(),
+                cmd.sku_file.to_string(),
+                cmd.concurrency,
+                cmd.rate_limit,
+            )
+            .await?;
+        }
+    } else if cmd.object.eq("diff") {
+        // Compare the source files with the account
+        if cmd.action.eq("report") {
+            diff::report_differences(
+                cmd.input_file.to_string(),
+                &catalog,
+                cmd.product_file.to_string(),
+                cmd.sku_file.to_string(),
+                cmd.price_file.to_string(),
+                cmd.inventory_file.to_string(),
+                cmd.concurrency,
+            )
+            .await?;
+        }
+    } else if cmd.object.eq("audit") {
+        // Check the data quality of the SKUs in the account
+        if cmd.action.eq("report") {
+            audit::audit_catalog(cmd.input_file.to_string(), &catalog, cmd.concurrency).await?;
+        }
+    } else if cmd.object.eq("snapshot") {
+        // Download the account into a SQLite file
+        if cmd.action.eq("create") {
+            snapshot::create_snapshot(
+                cmd.input_file.to_string(),
+                &client,
+                account_name.to_string(),
+                environment.to_string(),
+                cmd.concurrency,
+            )
+            .await?;
+        }
     } else {
         info!("Did not enter a valid object");
     }
@@ -33,8 +33,10 @@ pub async fn load_products(
         debug!("category_lookup: {:?}", category_lookup.len());
 
         // Get a lookup for the cateogory name of a category by GroupIdentifier
+        let limiter = utils::RequestLimiter::new(concurrent_requests, rate_limit);
         category_identifier_name_lookup =
-            utils::create_category_name_lookup(client, &account_name, &environment).await;
+            utils::create_category_name_lookup(client, &account_name, &environment, &limiter)
+                .await?;
         debug!(
             "category_identifier_name_lookup: {:?}",
             category_identifier_name_lookup.len()
@@ -143,8 +145,10 @@ pub async fn update_products(
         debug!("category_lookup: {:?}", category_lookup.len());
 
         // Get a lookup for the cateogory name of a category by GroupIdentifier
+        let limiter = utils::RequestLimiter::new(concurrent_requests, rate_limit);
         category_identifier_name_lookup =
-            utils::create_category_name_lookup(client, &account_name, &environment).await;
+            utils::create_category_name_lookup(client, &account_name, &environment, &limiter)
+                .await?;
         debug!(
             "category_identifier_name_lookup: {:?}",
             category_identifier_name_lookup.len()
@@ -10,7 +10,7 @@ use std::time::Duration;
 use std::{error::Error, fs::File};
 use vtex::csvrecords::ProductSpecificationAssignmentAlternate;
 use vtex::model::{ProductSpecificationAssignment, ProductSpecificationAssocation};
-use vtex::utils;
+use vtex::utils::{self, RequestLimiter};
 
 pub async fn gen_product_spec_association_file_root_category(
     file_path: String,
@@ -18,6 +18,7 @@ pub async fn gen_product_spec_association_file_root_category(
     account_name: String,
     environment: String,
     prod_specs_assignment_file: String,
+    limiter: &RequestLimiter,
 ) -> Result<(), Box<dyn Error>> {
     info!("Begin gen_product_spec_association_file_root_category()");
     let mut category_lookup: HashMap<String, i32> = HashMap::new();
@@ -54,8 +55,14 @@ pub async fn gen_product_spec_association_file_root_category(
     );
 
     // Need HashMap to get Field Id
-    let field_id_lookup =
-        utils::create_field_id_lookup(&category_lookup, client, &account_name, &environment).await;
+    let field_id_lookup = utils::create_field_id_lookup(
+        &category_lookup,
+        client,
+        &account_name,
+        &environment,
+        limiter,
+    )
+    .await?;
     debug!("field_id_lookup: {:?}", field_id_lookup);
 
     for line in prod_specs {
@@ -198,10 +205,11 @@ pub async fn gen_product_spec_association_file(
     environment: String,
     prod_specs_assignment_file: String,
     product_file: String,
+    limiter: &RequestLimiter,
 ) -> Result<(), Box<dyn Error>> {
     info!("Starting generate product spec assoocation file");
     // Read in the Specificaiton Groups and store in a HashMap for lookup
-    let groups = utils::get_vtex_field_groups(client, &account_name, &environment).await;
+    let groups = utils::get_vtex_field_groups(client, &account_name, &environment, limiter).await?;
     let group_lookup = utils::parse_spec_groups(groups);
     debug!("group_lookup: {:?}", group_lookup.len());
 
@@ -211,9 +219,44 @@ pub async fn gen_product_spec_association_file(
     debug!("category_lookup: {:?}", category_lookup.len());
 
     // Need HashMap to get Field Id
-    let field_id_lookup =
-        utils::create_field_id_lookup(&category_lookup, client, &account_name, &environment).await;
+    let field_id_lookup = utils::create_field_id_lookup(
+        &category_lookup,
+        client,
+        &account_name,
+        &environment,
+        limiter,
+    )
+    .await?;
     debug!("field_id_lookup: {:?}", field_id_lookup.len());
+    // Need the field type to validate the values
+    let field_type_lookup = utils::create_field_type_lookup(
+        &field_id_lookup,
+        client,
+        &account_name,
+        &environment,
+        limiter,
+    )
+    .await?;
+    debug!("field_type_lookup: {:?}", field_type_lookup.len());
+    // Only fields with a value list need a field value id lookup
+    let value_list_field_id_lookup: HashMap<String, i32> = field_id_lookup
+        .iter()
+        .filter(|(_, field_id)| {
+            field_type_lookup
+                .get(field_id)
+                .is_some_and(|field_type| field_type.has_value_list())
+        })
+        .map(|(key, field_id)| (key.clone(), *field_id))
+        .collect();
+    let field_value_id_lookup = utils::create_field_value_id_lookup(
+        &value_list_field_id_lookup,
+        client,
+        &account_name,
+        &environment,
+        limiter,
+    )
+    .await?;
+    debug!("field_value_id_lookup: {:?}", field_value_id_lookup.len());
 
     // Get a lookup HashMap for the parent category of a product
     let product_parent_category_lookup =
@@ -224,7 +267,7 @@ pub async fn gen_product_spec_association_file(
     );
     // Get a lookup for the cateogory name of a category by GroupIdentifier
     let category_identifier_name_lookup =
-        utils::create_category_name_lookup(client, &account_name, &environment).await;
+        utils::create_category_name_lookup(client, &account_name, &environment, limiter).await?;
     debug!(
         "category_identifier_name_lookup: {:?}",
         category_identifier_name_lookup.len()
@@ -263,6 +306,25 @@ pub async fn gen_product_spec_association_file(
         let field_id = field_id_lookup
             .get(&key)
             .expect("failed to find field_id for category in field_id_lookup");
+        // Validate the value against the field type
+        let field_type = *field_type_lookup
+            .get(field_id)
+            .expect("failed to find field_type in field_type_lookup");
+        let (field_value_id, text) = match utils::resolve_spec_value(
+            field_type,
+            *field_id,
+            &record.value,
+            &field_value_id_lookup,
+        ) {
+            Ok(resolved) => resolved,
+            Err(err) => {
+                error!(
+                    "product_ref_id: {} specification: {}: {}. Skipping record.",
+                    record.product_ref_id, name, err
+                );
+                continue;
+            }
+        };
 
         let mut product_id: i32 = 0;
         if !product_lookup.contains_key(&record.product_ref_id) {
@@ -296,8 +358,8 @@ pub async fn gen_product_spec_association_file(
                 id: Some(0),
                 product_id,
                 field_id: *field_id,
-                field_value_id: None,
-                text: Some(record.value),
+                field_value_id,
+                text,
             };
             writer.serialize(prod_spec)?;
         }
@@ -0,0 +1,216 @@
+use futures::{executor::block_on, stream, StreamExt};
+use governor::{Jitter, Quota, RateLimiter};
+use log::*;
+use reqwest::{Client, StatusCode};
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+use std::{error::Error, fs::File};
+use vtex::model::{SkuKit, SkuKitComponent};
+use vtex::utils::{self, RequestLimiter};
+
+pub async fn load_sku_kits(
+    file_path: String,
+    client: &Client,
+    account_name: String,
+    environment: String,
+    concurrent_requests: usize,
+    rate_limit: NonZeroU32,
+) -> Result<(), Box<dyn Error>> {
+    info!("Starting load of SKU Kit file");
+    let url = "https://{accountName}.{environment}.com.br/api/catalog/pvt/stockkeepingunitkit"
+        .replace("{accountName}", &account_name)
+        .replace("{environment}", &environment);
+
+    let input = File::open(file_path)?;
+    let mut rdr = csv::Reader::from_reader(input);
+
+    let mut kit_component_recs: Vec<SkuKitComponent> = Vec::new();
+    let mut e = 0;
+    for line in rdr.deserialize() {
+        match line {
+            Ok(record) => {
+                let kit_component: SkuKitComponent = record;
+                kit_component_recs.push(kit_component);
+            }
+            Err(err) => {
+                error!("Error parsing row: {:?}", err);
+                e += 1;
+            }
+        }
+    }
+    info!(
+        "Records successfully read: {}. Records not read (errors): {}",
+        kit_component_recs.len(),
+        e
+    );
+
+    // Look up the sku_id of the kits and their components
+    let mut ref_ids: HashSet<String> = HashSet::new();
+    for record in &kit_component_recs {
+        ref_ids.insert(record.kit_sku_ref_id.clone());
+        ref_ids.insert(record.component_sku_ref_id.clone());
+    }
+    let sku_id_lookup = utils::get_sku_ids_by_ref_ids(
+        ref_ids.into_iter().collect(),
+        client,
+        &account_name,
+        &environment,
+    )
+    .await;
+    debug!("sku_id_lookup: {}", sku_id_lookup.len());
+
+    // The kit SKUs must have IsKit=true in VTEX
+    let kit_sku_ids: HashSet<i32> = kit_component_recs
+        .iter()
+        .filter_map(|record| sku_id_lookup.get(&record.kit_sku_ref_id))
+        .copied()
+        .collect();
+    let kit_sku_ids: Vec<i32> = kit_sku_ids.into_iter().collect();
+    let kit_records =
+        utils::get_item_records(&kit_sku_ids, client, &account_name, &environment).await?;
+
+    let mut sku_kit_recs: Vec<SkuKit> = Vec::new();
+    for record in kit_component_recs {
+        let kit_sku_id = match sku_id_lookup.get(&record.kit_sku_ref_id) {
+            Some(kit_sku_id) => *kit_sku_id,
+            None => {
+                error!(
+                    "kit sku_ref_id: {} not found. Skipping component: {}",
+                    record.kit_sku_ref_id, record.component_sku_ref_id
+                );
+                continue;
+            }
+        };
+        let component_sku_id = match sku_id_lookup.get(&record.component_sku_ref_id) {
+            Some(component_sku_id) => *component_sku_id,
+            None => {
+                error!(
+                    "component sku_ref_id: {} not found. Skipping component of kit: {}",
+                    record.component_sku_ref_id, record.kit_sku_ref_id
+                );
+                continue;
+            }
+        };
+        match kit_records.get(&kit_sku_id) {
+            Some(kit_record) if kit_record.is_kit => (),
+            Some(_) => {
+                error!(
+                    "kit sku_ref_id: {} does not have IsKit=true. Skipping component: {}",
+                    record.kit_sku_ref_id, record.component_sku_ref_id
+                );
+                continue;
+            }
+            None => {
+                error!(
+                    "kit sku_ref_id: {} could not be retrieved. Skipping component: {}",
+                    record.kit_sku_ref_id, record.component_sku_ref_id
+                );
+                continue;
+            }
+        }
+        sku_kit_recs.push(SkuKit {
+            id: None,
+            stock_keeping_unit_parent: kit_sku_id,
+            stock_keeping_unit_id: component_sku_id,
+            quantity: record.quantity,
+            unit_price: record.unit_price,
+        });
+    }
+    info!("sku kit components to load: {}", sku_kit_recs.len());
+
+    let lim = Arc::new(RateLimiter::direct(Quota::per_second(rate_limit)));
+
+    let bodies = stream::iter(sku_kit_recs)
+        .map(|record| {
+            let client = &client;
+            let url = &url;
+            let lim = Arc::clone(&lim);
+            async move {
+                block_on(lim.until_ready_with_jitter(Jitter::up_to(Duration::from_millis(100))));
+
+                let response = client.post(url).json(&record).send().await?;
+
+                let status = response.status();
+                info!(
+                    "kit sku_id: {:?}  component sku_id: {:?}:  response: {:?}",
+                    record.stock_keeping_unit_parent, record.stock_keeping_unit_id, status
+                );
+                let text = response.text().await;
+                if status != StatusCode::OK {
+                    error!("text: {:?}", text);
+                }
+                text
+            }
+        })
+        .buffer_unordered(concurrent_requests);
+    bodies
+        .for_each(|b| async {
+            match b {
+                Ok(b) => info!("output: {:?}", b),
+                Err(e) => error!("error: {:?}", e),
+            }
+        })
+        .await;
+
+    info!("finished loading SKU Kit file");
+
+    Ok(())
+}
+
+pub async fn export_sku_kits(
+    file_path: String,
+    client: &Client,
+    account_name: String,
+    environment: String,
+    concurrent_requests: usize,
+) -> Result<(), Box<dyn Error>> {
+    info!("Starting export of SKU Kits");
+    // Get all the skus to find the kits and map the sku_id back to the ref_id
+    let sku_ids = utils::get_all_sku_ids(client, &account_name, &environment).await;
+    let item_records =
+        utils::get_item_records(&sku_ids, client, &account_name, &environment).await?;
+    let ref_id_lookup: HashMap<i32, String> = item_records
+        .iter()
+        .map(|(sku_id, item)| (*sku_id, item.alternate_ids.ref_id.clone()))
+        .collect();
+    let kit_sku_ids: Vec<i32> = item_records
+        .values()
+        .filter(|item| item.is_kit)
+        .map(|item| item.id)
+        .collect();
+    info!("kit skus: {}", kit_sku_ids.len());
+
+    // A failed read fails the export - the kit would be missing from the file
+    let limiter = RequestLimiter::with_concurrency(concurrent_requests);
+    let kit_records =
+        utils::get_sku_kit_records(&kit_sku_ids, client, &account_name, &environment, &limiter)
+            .await?;
+    let mut kit_recs: Vec<SkuKit> = kit_records.into_values().flatten().collect();
+    kit_recs.sort_by_key(|kit| (kit.stock_keeping_unit_parent, kit.stock_keeping_unit_id));
+    let mut writer = csv::Writer::from_path(file_path)?;
+    let mut x = 0;
+    for kit in kit_recs {
+        let kit_sku_ref_id = ref_id_lookup.get(&kit.stock_keeping_unit_parent);
+        let component_sku_ref_id = ref_id_lookup.get(&kit.stock_keeping_unit_id);
+        match (kit_sku_ref_id, component_sku_ref_id) {
+            (Some(kit_sku_ref_id), Some(component_sku_ref_id)) => {
+                writer.serialize(SkuKitComponent {
+                    kit_sku_ref_id: kit_sku_ref_id.clone(),
+                    component_sku_ref_id: component_sku_ref_id.clone(),
+                    quantity: kit.quantity,
+                    unit_price: kit.unit_price,
+                })?;
+                x += 1;
+            }
+            _ => error!("ref_id not found for kit component: {:?}", kit),
+        }
+    }
+    // Flush the records
+    writer.flush()?;
+    info!("records written: {}", x);
+    info!("finished export of SKU Kits");
+
+    Ok(())
+}
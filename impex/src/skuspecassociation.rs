@@ -2,7 +2,6 @@ use futures::{executor::block_on, stream, StreamExt};
 use governor::{Jitter, Quota, RateLimiter};
 use log::*;
 use reqwest::{Client, StatusCode};
-use serde::Serialize;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
@@ -10,8 +9,10 @@ use std::num::NonZeroU32;
 use std::sync::Arc;
 use std::time::Duration;
 use vtex::csvrecords::SkuSpecificationAssignmentAlternate;
-use vtex::model::{SkuSpecificationAssociation, SkuSpecificationValueAssignment};
-use vtex::utils;
+use vtex::model::{
+    SkuSpecAllowedValue, SkuSpecificationAssociation, SkuSpecificationValueAssignment,
+};
+use vtex::utils::{self, RequestLimiter};
 
 pub async fn gen_sku_spec_assign_file_alternate(
     file_path: String,
@@ -81,73 +82,6 @@ pub async fn gen_sku_spec_assign_file_alternate(
         utils::get_sku_ids_by_ref_ids(ref_ids, client, &account_name, &environment).await;
     debug!("sku_id_lookup: {}", sku_id_lookup.len());
 
-    // Write header record
-    writer.write_record(&[
-        "ProductRefId",
-        "Name",
-        "Position",
-        "AllowedValue1",
-        "AllowedValue2",
-        "AllowedValue3",
-        "AllowedValue4",
-        "AllowedValue5",
-        "AllowedValue6",
-        "AllowedValue7",
-        "AllowedValue8",
-        "AllowedValue9",
-        "AllowedValue10",
-        "AllowedValue11",
-        "AllowedValue12",
-        "AllowedValue13",
-        "AllowedValue14",
-        "AllowedValue15",
-        "AllowedValue16",
-        "AllowedValue17",
-        "AllowedValue18",
-        "AllowedValue19",
-        "AllowedValue20",
-        "AllowedValue21",
-        "AllowedValue22",
-        "AllowedValue23",
-        "AllowedValue24",
-        "AllowedValue25",
-        "AllowedValue26",
-        "AllowedValue27",
-        "AllowedValue28",
-        "AllowedValue29",
-        "AllowedValue30",
-        "AllowedValue31",
-        "AllowedValue32",
-        "AllowedValue33",
-        "AllowedValue34",
-        "AllowedValue35",
-        "AllowedValue36",
-        "AllowedValue37",
-        "AllowedValue38",
-        "AllowedValue39",
-        "AllowedValue40",
-        "AllowedValue41",
-        "AllowedValue42",
-        "AllowedValue43",
-        "AllowedValue44",
-        "AllowedValue45",
-        "AllowedValue46",
-        "AllowedValue47",
-        "AllowedValue48",
-        "AllowedValue49",
-        "AllowedValue50",
-        "AllowedValue51",
-        "AllowedValue52",
-        "AllowedValue53",
-        "AllowedValue54",
-        "AllowedValue55",
-        "AllowedValue56",
-        "AllowedValue57",
-        "AllowedValue58",
-        "AllowedValue59",
-        "AllowedValue60",
-    ])?;
-
     let mut product_allowed_values_map_color: HashMap<i32, Vec<String>> = HashMap::new();
     let mut product_allowed_values_map_size: HashMap<i32, Vec<String>> = HashMap::new();
     for line in sku_specs {
@@ -214,58 +148,41 @@ pub async fn gen_sku_spec_assign_file_alternate(
         "product_allowed_values_map_size.len(): {}",
         product_allowed_values_map_size.len()
     );
-    // Build the Color record to write
-    #[derive(Debug, Serialize)]
-    enum Value {
-        StringValue(String),
-        NumberValue(i32),
-        NoneValue(Option<String>),
-    }
+    // Build the allowed value records to write - one row per value
+    let mut allowed_values: Vec<SkuSpecAllowedValue> = Vec::new();
     for (k, v) in product_allowed_values_map_color {
-        let num_colors = v.len();
-        debug!("num_colors: {}", num_colors);
-        let mut record: Vec<Value> = Vec::new();
-        record.push(Value::NumberValue(k));
-        record.push(Value::StringValue("Color".to_string()));
-        record.push(Value::NumberValue(1));
+        debug!("num_colors: {}", v.len());
         for s in v {
-            record.push(Value::StringValue(s));
-        }
-        debug!("color record before write: {:?}", record);
-        let mut i = 0;
-        while i < (60 - (num_colors)) {
-            record.push(Value::NoneValue(None));
-            i += 1;
+            allowed_values.push(SkuSpecAllowedValue {
+                product_ref_id: k.to_string(),
+                name: "Color".to_string(),
+                position: 1,
+                value: s,
+                field_type: None,
+                group_name: None,
+            });
         }
-        debug!("color record after adding commas: {:?}", record);
-        writer.serialize(record)?;
     }
     for (k, v) in product_allowed_values_map_size {
-        let num_sizes = v.len();
-        debug!("num_colors: {}", num_sizes);
-        let mut record: Vec<Value> = Vec::new();
-        record.push(Value::NumberValue(k));
-        record.push(Value::StringValue("Size".to_string()));
-        record.push(Value::NumberValue(1));
+        debug!("num_sizes: {}", v.len());
         for s in v {
-            record.push(Value::StringValue(s));
+            allowed_values.push(SkuSpecAllowedValue {
+                product_ref_id: k.to_string(),
+                name: "Size".to_string(),
+                position: 1,
+                value: s,
+                field_type: None,
+                group_name: None,
+            });
         }
-        debug!("size record before write: {:?}", record);
-        let mut i = 0;
-        while i < (60 - (num_sizes)) {
-            record.push(Value::NoneValue(None));
-            i += 1;
-        }
-        debug!("size record after adding commas: {:?}", record);
-        writer.serialize(record)?;
     }
-    // Flush the records
-    writer.flush()?;
+    utils::write_sku_spec_allowed_values_long(&allowed_values, &mut writer)?;
     info!("Finished generating SKU Spec Assigns file");
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn gen_sku_spec_association_file_alternate(
     file_path: String,
     client: &Client,
@@ -274,6 +191,7 @@ pub async fn gen_sku_spec_association_file_alternate(
     sku_spec_assignment_file: String,
     product_file: String,
     sku_file: String,
+    limiter: &RequestLimiter,
 ) -> Result<(), Box<dyn Error>> {
     // Setup the input and output files
     let in_file = File::open(sku_spec_assignment_file).unwrap();
@@ -323,7 +241,7 @@ pub async fn gen_sku_spec_association_file_alternate(
 
     // Build a category name lookup
     // let category_name_lookup =
-    //     utils::create_category_name_lookup(client, &account_name, &environment).await;
+    //     utils::create_category_name_lookup(client, &account_name, &environment, limiter).await?;
     // debug!("category_name_lookup: {}", category_name_lookup.len());
 
     // Build category id lookup
@@ -333,14 +251,24 @@ pub async fn gen_sku_spec_association_file_alternate(
     let category_id_lookup = utils::create_category_id_lookup_alternate(&product_file).await;
     debug!("category_id_lookup: {}", category_id_lookup.len());
     // Build a field id lookup fn get the fields for a category
-    let field_id_lookup =
-        utils::create_field_id_lookup(&category_id_lookup, client, &account_name, &environment)
-            .await;
+    let field_id_lookup = utils::create_field_id_lookup(
+        &category_id_lookup,
+        client,
+        &account_name,
+        &environment,
+        limiter,
+    )
+    .await?;
     debug!("field_id_lookup: {:?}", field_id_lookup.len());
     // Build a field value id lookup table
-    let field_value_id_lookup =
-        utils::create_field_value_id_lookup(&field_id_lookup, client, &account_name, &environment)
-            .await;
+    let field_value_id_lookup = utils::create_field_value_id_lookup(
+        &field_id_lookup,
+        client,
+        &account_name,
+        &environment,
+        limiter,
+    )
+    .await?;
     debug!("field_value_id_lookup: {:?}", field_value_id_lookup.len());
 
     // Build a Sku_id lookup fn
@@ -411,6 +339,7 @@ pub async fn gen_sku_spec_association_file_alternate(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn gen_sku_spec_association_file(
     file_path: String,
     client: &Client,
@@ -419,6 +348,7 @@ pub async fn gen_sku_spec_association_file(
     sku_spec_assignment_file: String,
     product_file: String,
     sku_file: String,
+    limiter: &RequestLimiter,
 ) -> Result<(), Box<dyn Error>> {
     // Setup the input and output files
     let in_file = File::open(sku_spec_assignment_file).unwrap();
@@ -454,7 +384,7 @@ pub async fn gen_sku_spec_association_file(
     info!("Staring generation of SKU Spec Association file");
     // Build a category name lookup
     let category_name_lookup =
-        utils::create_category_name_lookup(client, &account_name, &environment).await;
+        utils::create_category_name_lookup(client, &account_name, &environment, limiter).await?;
     debug!("category_name_lookup: {}", category_name_lookup.len());
 
     // Build a Sku_id lookup fn
@@ -480,15 +410,35 @@ pub async fn gen_sku_spec_association_file(
         utils::create_category_id_lookup(client, &account_name, &environment).await;
     debug!("category_id_lookup: {}", category_id_lookup.len());
     // Build a field id lookup fn get the fields for a category
-    let field_id_lookup =
-        utils::create_field_id_lookup(&category_id_lookup, client, &account_name, &environment)
-            .await;
+    let field_id_lookup = utils::create_field_id_lookup(
+        &category_id_lookup,
+        client,
+        &account_name,
+        &environment,
+        limiter,
+    )
+    .await?;
     debug!("field_id_lookup: {:?}", field_id_lookup.len());
     // Build a field value id lookup table
-    let field_value_id_lookup =
-        utils::create_field_value_id_lookup(&field_id_lookup, client, &account_name, &environment)
-            .await;
+    let field_value_id_lookup = utils::create_field_value_id_lookup(
+        &field_id_lookup,
+        client,
+        &account_name,
+        &environment,
+        limiter,
+    )
+    .await?;
     debug!("field_value_id_lookup: {:?}", field_value_id_lookup.len());
+    // Need the field type to validate the values
+    let field_type_lookup = utils::create_field_type_lookup(
+        &field_id_lookup,
+        client,
+        &account_name,
+        &environment,
+        limiter,
+    )
+    .await?;
+    debug!("field_type_lookup: {:?}", field_type_lookup.len());
 
     //    let mut sku_id_lookup: HashMap<String, i32> = HashMap::new();
 
@@ -524,20 +474,33 @@ pub async fn gen_sku_spec_association_file(
         let field_id = field_id_lookup
             .get(&key)
             .expect("failed to find field_id in field_id_lookup");
-        // Build the key to use with the field_value_id_lookup
-        let field_value_key =
-            field_id.to_string().as_str().to_owned() + "|" + record.value.as_str().trim();
-        let field_value_id = field_value_id_lookup
-            .get(&field_value_key)
-            .expect("failed to find field_value_id in field_value_id_lookup");
+        // Validate the value against the field type
+        let field_type = *field_type_lookup
+            .get(field_id)
+            .expect("failed to find field_type in field_type_lookup");
+        let (field_value_id, text) = match utils::resolve_spec_value(
+            field_type,
+            *field_id,
+            &record.value,
+            &field_value_id_lookup,
+        ) {
+            Ok(resolved) => resolved,
+            Err(err) => {
+                error!(
+                    "sku_ref_id: {} specification: {}: {}. Skipping record.",
+                    record.sku_ref_id, record.name, err
+                );
+                continue;
+            }
+        };
         debug!("record.sku_ref_id {}", &record.sku_ref_id);
         // if sku_id_lookup.contains_key(&record.sku_ref_id) {
         let sku_spec_assign = SkuSpecificationAssociation {
             id: Some(0), // Hardcode to 0, API does not work with None (null)
             sku_id: *sku_id_lookup.get(&record.sku_ref_id).unwrap(),
             field_id: *field_id,
-            field_value_id: Some(*field_value_id),
-            text: None,
+            field_value_id,
+            text,
         };
         writer.serialize(sku_spec_assign)?;
         x += 1;
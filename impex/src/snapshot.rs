@@ -0,0 +1,172 @@
+use futures::{stream, StreamExt};
+use log::*;
+use reqwest::Client;
+use std::collections::HashSet;
+use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+use vtex::model::{FieldValueList, Product, SpecificationField};
+use vtex::snapshot::Snapshot;
+use vtex::utils::{self, RequestLimiter};
+
+// Download the catalog, prices and inventory of the account into a SQLite snapshot
+pub async fn create_snapshot(
+    file_path: String,
+    client: &Client,
+    account_name: String,
+    environment: String,
+    concurrent_requests: usize,
+) -> Result<(), Box<dyn Error>> {
+    info!("Starting catalog snapshot: {}", file_path);
+    let concurrent_requests = concurrent_requests.max(1);
+    let limiter = &RequestLimiter::with_concurrency(concurrent_requests);
+    let mut snapshot = Snapshot::create(&file_path)?;
+    let created_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    snapshot.set_meta("account_name", &account_name)?;
+    snapshot.set_meta("environment", &environment)?;
+
+    let category_tree = utils::get_vtex_category_tree(client, &account_name, &environment).await;
+    info!(
+        "categories written: {}",
+        snapshot.insert_categories(&category_tree)?
+    );
+    let brands = utils::get_brands(client, &account_name, &environment).await;
+    info!("brands written: {}", snapshot.insert_brands(&brands)?);
+
+    // The fields of every category - fields can be shared so each field is read once
+    let category_ids = snapshot.category_ids()?;
+    let results: Vec<Result<(i32, Vec<i32>), String>> = stream::iter(category_ids)
+        .map(|category_id| {
+            let (account_name, environment) = (&account_name, &environment);
+            async move {
+                let specs = utils::get_spec_fields_for_category(
+                    client,
+                    account_name,
+                    environment,
+                    &category_id.to_string(),
+                    limiter,
+                )
+                .await?;
+                Ok((
+                    category_id,
+                    specs.iter().map(|spec| spec.field_id).collect(),
+                ))
+            }
+        })
+        .buffer_unordered(limiter.concurrency)
+        .collect()
+        .await;
+    let category_fields: Vec<(i32, Vec<i32>)> = results.into_iter().collect::<Result<_, _>>()?;
+    let field_ids: HashSet<i32> = category_fields
+        .iter()
+        .flat_map(|(_, field_ids)| field_ids.iter().copied())
+        .collect();
+    let results: Vec<Result<SpecificationField, String>> = stream::iter(field_ids)
+        .map(|field_id| {
+            let (account_name, environment) = (&account_name, &environment);
+            async move {
+                utils::get_specification_field(
+                    client,
+                    account_name,
+                    environment,
+                    &field_id.to_string(),
+                    limiter,
+                )
+                .await
+            }
+        })
+        .buffer_unordered(limiter.concurrency)
+        .collect()
+        .await;
+    let fields: Vec<SpecificationField> = results.into_iter().collect::<Result<_, _>>()?;
+    info!(
+        "specification fields written: {}",
+        snapshot.insert_specification_fields(&fields, &category_fields)?
+    );
+    let results: Vec<Result<(i32, Vec<FieldValueList>), String>> = stream::iter(
+        fields
+            .iter()
+            .filter(|field| field.field_type_id.has_value_list()),
+    )
+    .map(|field| {
+        let (account_name, environment) = (&account_name, &environment);
+        async move {
+            let values = utils::get_field_values_for_field_id(
+                client,
+                account_name,
+                environment,
+                &field.field_id.to_string(),
+                limiter,
+            )
+            .await?;
+            Ok((field.field_id, values))
+        }
+    })
+    .buffer_unordered(limiter.concurrency)
+    .collect()
+    .await;
+    let field_values: Vec<(i32, Vec<FieldValueList>)> =
+        results.into_iter().collect::<Result<_, _>>()?;
+    info!(
+        "specification values written: {}",
+        snapshot.insert_specification_values(&field_values)?
+    );
+
+    let sku_ids = utils::get_all_sku_ids(client, &account_name, &environment).await;
+    let item_records =
+        utils::get_item_records(&sku_ids, client, &account_name, &environment).await?;
+    info!("skus written: {}", snapshot.insert_skus(&item_records)?);
+
+    // Products are found through their skus
+    let ref_ids: HashSet<String> = item_records
+        .values()
+        .map(|item| item.product_ref_id.clone())
+        .collect();
+    let results: Vec<Result<Product, String>> = stream::iter(ref_ids)
+        .map(|ref_id| {
+            let (account_name, environment) = (&account_name, &environment);
+            async move {
+                utils::get_product_record_by_ref_id(&ref_id, client, account_name, environment)
+                    .await
+            }
+        })
+        .buffer_unordered(concurrent_requests)
+        .collect()
+        .await;
+    let mut products: Vec<Product> = Vec::with_capacity(results.len());
+    let mut failed = 0;
+    for result in results {
+        match result {
+            Ok(product) => products.push(product),
+            Err(err) => {
+                error!("product not written: {}", err);
+                failed += 1;
+            }
+        }
+    }
+    info!("products written: {}", snapshot.insert_products(&products)?);
+
+    let price_records = utils::get_price_records(&sku_ids, client, &account_name).await?;
+    info!(
+        "prices written: {}",
+        snapshot.insert_prices(&price_records)?
+    );
+    let inventory_records =
+        utils::get_inventory_records(&sku_ids, client, &account_name, &environment).await?;
+    info!(
+        "inventory balances written: {}",
+        snapshot.insert_inventory(&inventory_records)?
+    );
+
+    // Written last so an interrupted (or incomplete) snapshot has no created_at
+    if failed > 0 {
+        return Err(format!(
+            "{} products could not be read - the snapshot: {} is incomplete",
+            failed, file_path
+        )
+        .into());
+    }
+    snapshot.set_meta("created_at", &created_at.to_string())?;
+    info!("finished catalog snapshot");
+
+    Ok(())
+}
@@ -1,9 +1,112 @@
 use futures::{stream, StreamExt};
 use log::*;
 use reqwest::Client;
+use std::collections::HashSet;
 use std::error::Error;
 use std::fs::File;
-use vtex::model::SpecificationGroup;
+use vtex::model::{ProductSpecificationAssignment, SpecificationGroup};
+use vtex::utils::{self, RequestLimiter};
+
+// Generate the specification groups that do not exist yet from the GroupName of the specification input files
+// Named groups are created on the product's category, the default group is not tied to a category
+#[allow(clippy::too_many_arguments)]
+pub async fn gen_specification_groups_file(
+    file_path: String,
+    client: &Client,
+    account_name: String,
+    environment: String,
+    prod_specs_assignment_file: String,
+    sku_spec_allowed_values_file: String,
+    product_file: String,
+    limiter: &RequestLimiter,
+) -> Result<(), Box<dyn Error>> {
+    info!("Starting specification group file generation");
+    // Read in the category tree and store in a HashMap for lookup
+    let categories = utils::get_vtex_category_tree(client, &account_name, &environment).await;
+    let category_lookup = utils::parse_category_tree(categories);
+    debug!("category_lookup: {:?}", category_lookup.len());
+    // Read in the Specification Groups of every category and store in a HashMap for lookup
+    let group_lookup = utils::create_spec_group_lookup(
+        &category_lookup,
+        client,
+        &account_name,
+        &environment,
+        limiter,
+    )
+    .await?;
+    debug!("group_lookup: {:?}", group_lookup.len());
+    // Get a lookup HashMap for the parent category of a product
+    let product_parent_category_lookup =
+        utils::create_product_parent_category_lookup(&product_file);
+    debug!(
+        "product_parent_category_lookkup: {:?}",
+        product_parent_category_lookup.len()
+    );
+    // Get a lookup for the cateogory name of a category by GroupIdentifier
+    let category_identifier_name_lookup =
+        utils::create_category_name_lookup(client, &account_name, &environment, limiter).await?;
+    debug!(
+        "category_identifier_name_lookup: {:?}",
+        category_identifier_name_lookup.len()
+    );
+
+    // Collect the group name of every product specification
+    let mut product_groups: Vec<(String, Option<String>)> = Vec::new();
+    if !prod_specs_assignment_file.is_empty() {
+        let in_file = File::open(prod_specs_assignment_file)?;
+        let mut reader = csv::Reader::from_reader(in_file);
+        for line in reader.deserialize() {
+            let record: ProductSpecificationAssignment = line?;
+            product_groups.push((record.product_ref_id, record.group_name));
+        }
+    }
+    if !sku_spec_allowed_values_file.is_empty() {
+        for record in utils::read_sku_spec_allowed_values(&sku_spec_allowed_values_file)? {
+            product_groups.push((record.product_ref_id, record.group_name));
+        }
+    }
+    debug!("product_groups: {}", product_groups.len());
+
+    let mut writer = csv::Writer::from_path(file_path)?;
+    let mut unique_groups: HashSet<String> = HashSet::new();
+    let mut x = 0;
+    for (product_ref_id, group_name) in product_groups {
+        let (category_id, group_name) = match group_name {
+            Some(group_name) => {
+                // Look up the VTEX Category Id of the product
+                let vtex_cat_id = product_parent_category_lookup
+                    .get(&product_ref_id)
+                    .and_then(|identifier| category_identifier_name_lookup.get(identifier))
+                    .and_then(|cat_name| category_lookup.get(cat_name));
+                match vtex_cat_id {
+                    Some(vtex_cat_id) => (Some(*vtex_cat_id), group_name),
+                    None => {
+                        error!(
+                            "category not found for product_ref_id: {}. Skipping group: {}",
+                            product_ref_id, group_name
+                        );
+                        continue;
+                    }
+                }
+            }
+            None => (None, utils::DEFAULT_SPECIFICATION_GROUP.to_string()),
+        };
+        let key = category_id.unwrap_or(0).to_string() + "|" + group_name.as_str();
+        // Only write the groups that do not exist in VTEX and have not been written
+        if utils::get_spec_group_id(&group_lookup, category_id.unwrap_or(0), &group_name).is_none()
+            && unique_groups.insert(key)
+        {
+            writer.serialize(SpecificationGroup::new(None, group_name, category_id, None))?;
+            x += 1;
+        }
+    }
+    // Flush the records
+    writer.flush()?;
+    info!("records written: {}", x);
+    info!("Finished generating specification group file");
+
+    Ok(())
+}
 
 pub async fn load_specification_groups(
     file_path: String,
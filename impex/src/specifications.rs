@@ -6,8 +6,9 @@ use std::error::Error;
 use std::fs::File;
 
 use reqwest::Client;
-use vtex::model::{ProductSpecificationAssignment, SkuSpecAllowedValues, Specification};
-use vtex::utils;
+use vtex::cache::{self, LookupCache};
+use vtex::model::{FieldType, ProductSpecificationAssignment, SkuSpecAllowedValue, Specification};
+use vtex::utils::{self, RequestLimiter};
 
 pub async fn gen_product_specifications_file(
     file_path: String,
@@ -16,19 +17,23 @@ pub async fn gen_product_specifications_file(
     environment: String,
     prod_specs_assignment_file: String,
     product_file: String,
+    limiter: &RequestLimiter,
 ) -> Result<(), Box<dyn Error>> {
     info!("Starting product specification file generation");
-    // Read in the Specificaiton Groups and store in a HashMap for lookup
-    let groups = utils::get_vtex_field_groups(client, &account_name, &environment).await;
-    let group_lookup = utils::parse_spec_groups(groups);
-    debug!("group_lookup: {:?}", group_lookup.len());
-    // TODO: Need to figure out this hard-coded value
-    let prod_spec_id = group_lookup.get("Default Specification Group").expect("Expected [Default Specification Group]. Ensure you create a specification group with this value.");
-
     // Read in the category tree and store in a HashMap for lookup
     let categories = utils::get_vtex_category_tree(client, &account_name, &environment).await;
     let category_lookup = utils::parse_category_tree(categories);
     debug!("category_lookup: {:?}", category_lookup.len());
+    // Read in the Specification Groups of every category and store in a HashMap for lookup
+    let group_lookup = utils::create_spec_group_lookup(
+        &category_lookup,
+        client,
+        &account_name,
+        &environment,
+        limiter,
+    )
+    .await?;
+    debug!("group_lookup: {:?}", group_lookup.len());
 
     // Get a lookup HashMap for the parent category of a product
     let product_parent_category_lookup =
@@ -39,7 +44,7 @@ pub async fn gen_product_specifications_file(
     );
     // Get a lookup for the cateogory name of a category by GroupIdentifier
     let category_identifier_name_lookup =
-        utils::create_category_name_lookup(client, &account_name, &environment).await;
+        utils::create_category_name_lookup(client, &account_name, &environment, limiter).await?;
     debug!(
         "category_identifier_name_lookup: {:?}",
         category_identifier_name_lookup.len()
@@ -77,11 +82,32 @@ pub async fn gen_product_specifications_file(
         if !unique_spec_cat.contains(&unique_spec_cat_id) {
             debug!("unique_spec_cat_id: {}", unique_spec_cat_id);
             if !record.name.eq("Brand") {
+                // Look up the Specification Group for the category
+                let group_name = record
+                    .group_name
+                    .as_deref()
+                    .unwrap_or(utils::DEFAULT_SPECIFICATION_GROUP);
+                let field_group_id = match utils::get_spec_group_id(
+                    &group_lookup,
+                    *vtex_cat_id,
+                    group_name,
+                ) {
+                    Some(field_group_id) => field_group_id,
+                    None => {
+                        error!(
+                            "Specification group: [{}] not found for category: {}. Create it with the specificationgroup genspecgroupsfile action. Skipping specification: {}",
+                            group_name, vtex_cat_id, record.name
+                        );
+                        unique_spec_cat.insert(unique_spec_cat_id);
+                        continue;
+                    }
+                };
                 let spec = Specification {
                     id: None,
-                    field_type_id: 1, // 1 = Text
+                    // Default to Text when the input does not set a FieldType
+                    field_type_id: record.field_type.unwrap_or(FieldType::Text),
                     category_id: Some(*vtex_cat_id),
-                    field_group_id: *prod_spec_id,
+                    field_group_id,
                     name: record.name.clone(),
                     description: Some(record.name.clone()),
                     position: Some(record.position),
@@ -116,48 +142,31 @@ pub async fn gen_sku_specifications_file_alternate(
     environment: String,
     sku_specs_assignment_file: String,
     product_file: String,
+    limiter: &RequestLimiter,
 ) -> Result<(), Box<dyn Error>> {
     info!("Starting SKU specification file generation");
 
     // Setup the input and output files
     debug!("current_directory: {:?}", env::current_dir());
-    let in_file = File::open(sku_specs_assignment_file).unwrap();
-    let mut reader = csv::Reader::from_reader(in_file);
+    let sku_specs_allowed_values = utils::read_sku_spec_allowed_values(&sku_specs_assignment_file)?;
     let out_path = file_path;
     let mut writer = csv::Writer::from_path(out_path)?;
-
-    let mut sku_specs_allowed_values: Vec<SkuSpecAllowedValues> = Vec::new();
-    let mut e = 0;
-    for line in reader.deserialize() {
-        match line {
-            Ok(record) => {
-                let sku_spec_allowed_value: SkuSpecAllowedValues = record;
-                sku_specs_allowed_values.push(sku_spec_allowed_value);
-            }
-            Err(err) => {
-                error!("Error parsing row: {:?}", err);
-                e += 1;
-            }
-        }
-    }
     info!("Finished: Reading input file");
-    info!(
-        "Records successfully read: {}. Records not read (errors): {}",
-        sku_specs_allowed_values.len(),
-        e
-    );
-
-    // Read in the Specificaiton Groups and store in a HashMap for lookup
-    let groups = utils::get_vtex_field_groups(client, &account_name, &environment).await;
-    let group_lookup = utils::parse_spec_groups(groups);
-    debug!("group_lookup: {:?}", group_lookup.len());
-    // TODO: Need to figure out this hard-coded value
-    let prod_spec_id = group_lookup.get("Default Specification Group").expect("Expected [Default Specification Group]. Ensure you create a specification group with this value.");
 
     // Read in the category tree and store in a HashMap for lookup
     let categories = utils::get_vtex_category_tree(client, &account_name, &environment).await;
     let category_lookup = utils::parse_category_tree(categories);
     debug!("category_lookup: {:?}", category_lookup.len());
+    // Read in the Specification Groups of every category and store in a HashMap for lookup
+    let group_lookup = utils::create_spec_group_lookup(
+        &category_lookup,
+        client,
+        &account_name,
+        &environment,
+        limiter,
+    )
+    .await?;
+    debug!("group_lookup: {:?}", group_lookup.len());
 
     // Get a lookup HashMap for the parent category of a product
     let product_parent_category_lookup =
@@ -168,7 +177,7 @@ pub async fn gen_sku_specifications_file_alternate(
     );
     // Get a lookup for the cateogory name of a category by GroupIdentifier
     // let category_identifier_name_lookup =
-    //     utils::create_category_name_lookup(client, &account_name, &environment).await;
+    //     utils::create_category_name_lookup(client, &account_name, &environment, limiter).await?;
     // debug!(
     //     "category_identifier_name_lookup: {:?}",
     //     category_identifier_name_lookup.len()
@@ -179,7 +188,7 @@ pub async fn gen_sku_specifications_file_alternate(
 
     // Process the input file
     for line in sku_specs_allowed_values {
-        let record: SkuSpecAllowedValues = line;
+        let record: SkuSpecAllowedValue = line;
         // look up the part number
         let parent_cat_identifier = product_parent_category_lookup
             .get(&record.product_ref_id)
@@ -202,11 +211,41 @@ pub async fn gen_sku_specifications_file_alternate(
             record.name.clone() + String::as_str(&vtex_cat_id.to_string());
         if !unique_spec_cat.contains(&unique_spec_cat_id) {
             debug!("unique_spec_cat_id: {}", unique_spec_cat_id);
+            // SKU specifications must use a value list - default to Radio
+            let field_type = record.field_type.unwrap_or(FieldType::Radio);
+            if !field_type.has_value_list() {
+                error!(
+                    "SKU specification: {} has FieldType: {}. Expected Combo, Radio or Checkbox. Skipping record.",
+                    record.name, field_type
+                );
+                unique_spec_cat.insert(unique_spec_cat_id);
+                continue;
+            }
+            // Look up the Specification Group for the category
+            let group_name = record
+                .group_name
+                .as_deref()
+                .unwrap_or(utils::DEFAULT_SPECIFICATION_GROUP);
+            let field_group_id = match utils::get_spec_group_id(
+                &group_lookup,
+                vtex_cat_id,
+                group_name,
+            ) {
+                Some(field_group_id) => field_group_id,
+                None => {
+                    error!(
+                        "Specification group: [{}] not found for category: {}. Create it with the specificationgroup genspecgroupsfile action. Skipping specification: {}",
+                        group_name, vtex_cat_id, record.name
+                    );
+                    unique_spec_cat.insert(unique_spec_cat_id);
+                    continue;
+                }
+            };
             let spec = Specification {
                 id: None,
-                field_type_id: 6, // 6 = Radio
+                field_type_id: field_type,
                 category_id: Some(vtex_cat_id),
-                field_group_id: *prod_spec_id,
+                field_group_id,
                 name: record.name.clone(),
                 description: Some(record.name.clone()),
                 position: Some(record.position),
@@ -240,48 +279,31 @@ pub async fn gen_sku_specifications_file(
     environment: String,
     sku_specs_assignment_file: String,
     product_file: String,
+    limiter: &RequestLimiter,
 ) -> Result<(), Box<dyn Error>> {
     info!("Starting SKU specification file generation");
 
     // Setup the input and output files
     debug!("current_directory: {:?}", env::current_dir());
-    let in_file = File::open(sku_specs_assignment_file).unwrap();
-    let mut reader = csv::Reader::from_reader(in_file);
+    let sku_specs_allowed_values = utils::read_sku_spec_allowed_values(&sku_specs_assignment_file)?;
     let out_path = file_path;
     let mut writer = csv::Writer::from_path(out_path)?;
-
-    let mut sku_specs_allowed_values: Vec<SkuSpecAllowedValues> = Vec::new();
-    let mut e = 0;
-    for line in reader.deserialize() {
-        match line {
-            Ok(record) => {
-                let sku_spec_allowed_value: SkuSpecAllowedValues = record;
-                sku_specs_allowed_values.push(sku_spec_allowed_value);
-            }
-            Err(err) => {
-                error!("Error parsing row: {:?}", err);
-                e += 1;
-            }
-        }
-    }
     info!("Finished: Reading input file");
-    info!(
-        "Records successfully read: {}. Records not read (errors): {}",
-        sku_specs_allowed_values.len(),
-        e
-    );
-
-    // Read in the Specificaiton Groups and store in a HashMap for lookup
-    let groups = utils::get_vtex_field_groups(client, &account_name, &environment).await;
-    let group_lookup = utils::parse_spec_groups(groups);
-    debug!("group_lookup: {:?}", group_lookup.len());
-    // TODO: Need to figure out this hard-coded value
-    let prod_spec_id = group_lookup.get("Default Specification Group").expect("Expected [Default Specification Group]. Ensure you create a specification group with this value.");
 
     // Read in the category tree and store in a HashMap for lookup
     let categories = utils::get_vtex_category_tree(client, &account_name, &environment).await;
     let category_lookup = utils::parse_category_tree(categories);
     debug!("category_lookup: {:?}", category_lookup.len());
+    // Read in the Specification Groups of every category and store in a HashMap for lookup
+    let group_lookup = utils::create_spec_group_lookup(
+        &category_lookup,
+        client,
+        &account_name,
+        &environment,
+        limiter,
+    )
+    .await?;
+    debug!("group_lookup: {:?}", group_lookup.len());
 
     // Get a lookup HashMap for the parent category of a product
     let product_parent_category_lookup =
@@ -292,7 +314,7 @@ pub async fn gen_sku_specifications_file(
     );
     // Get a lookup for the cateogory name of a category by GroupIdentifier
     let category_identifier_name_lookup =
-        utils::create_category_name_lookup(client, &account_name, &environment).await;
+        utils::create_category_name_lookup(client, &account_name, &environment, limiter).await?;
     debug!(
         "category_identifier_name_lookup: {:?}",
         category_identifier_name_lookup.len()
@@ -303,7 +325,7 @@ pub async fn gen_sku_specifications_file(
 
     // Process the input file
     for line in sku_specs_allowed_values {
-        let record: SkuSpecAllowedValues = line;
+        let record: SkuSpecAllowedValue = line;
         // look up the part number
         let parent_cat_identifier = product_parent_category_lookup
             .get(&record.product_ref_id)
@@ -326,11 +348,41 @@ pub async fn gen_sku_specifications_file(
             record.name.clone() + String::as_str(&vtex_cat_id.to_string());
         if !unique_spec_cat.contains(&unique_spec_cat_id) {
             debug!("unique_spec_cat_id: {}", unique_spec_cat_id);
+            // SKU specifications must use a value list - default to Radio
+            let field_type = record.field_type.unwrap_or(FieldType::Radio);
+            if !field_type.has_value_list() {
+                error!(
+                    "SKU specification: {} has FieldType: {}. Expected Combo, Radio or Checkbox. Skipping record.",
+                    record.name, field_type
+                );
+                unique_spec_cat.insert(unique_spec_cat_id);
+                continue;
+            }
+            // Look up the Specification Group for the category
+            let group_name = record
+                .group_name
+                .as_deref()
+                .unwrap_or(utils::DEFAULT_SPECIFICATION_GROUP);
+            let field_group_id = match utils::get_spec_group_id(
+                &group_lookup,
+                *vtex_cat_id,
+                group_name,
+            ) {
+                Some(field_group_id) => field_group_id,
+                None => {
+                    error!(
+                        "Specification group: [{}] not found for category: {}. Create it with the specificationgroup genspecgroupsfile action. Skipping specification: {}",
+                        group_name, vtex_cat_id, record.name
+                    );
+                    unique_spec_cat.insert(unique_spec_cat_id);
+                    continue;
+                }
+            };
             let spec = Specification {
                 id: None,
-                field_type_id: 6, // 6 = Radio
+                field_type_id: field_type,
                 category_id: Some(*vtex_cat_id),
-                field_group_id: *prod_spec_id,
+                field_group_id,
                 name: record.name.clone(),
                 description: Some(record.name.clone()),
                 position: Some(record.position),
@@ -381,6 +433,9 @@ pub async fn load_specifications(
 
     info!("specification records: {:?}", spec_recs.len());
 
+    // Invalidated before the first create so the lookup is rebuilt even when the load fails
+    LookupCache::new(&account_name, &environment).invalidate(&[cache::FIELD_ID_LOOKUP]);
+
     let bodies = stream::iter(spec_recs)
         .map(|record| {
             let client = &client;
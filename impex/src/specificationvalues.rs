@@ -1,4 +1,3 @@
-use csv::StringRecord;
 use futures::{executor::block_on, stream, StreamExt};
 use governor::{Jitter, Quota, RateLimiter};
 use log::*;
@@ -9,9 +8,10 @@ use std::fs::File;
 use std::num::NonZeroU32;
 use std::sync::Arc;
 use std::time::Duration;
+use vtex::cache::{self, LookupCache};
 use vtex::csvrecords::SkuSpecificationAssignmentAlternate;
-use vtex::model::SpecificationValue;
-use vtex::utils;
+use vtex::model::{ProductSpecificationAssignment, SpecificationValue};
+use vtex::utils::{self, RequestLimiter};
 
 pub async fn gen_specification_values_file_alternate(
     file_path: String,
@@ -19,6 +19,7 @@ pub async fn gen_specification_values_file_alternate(
     account_name: String,
     environment: String,
     sku_spec_allowed_values_file: String,
+    limiter: &RequestLimiter,
 ) -> Result<(), Box<dyn Error>> {
     info!("Start: generation of specification values file");
 
@@ -54,8 +55,14 @@ pub async fn gen_specification_values_file_alternate(
     debug!("category_lookup: {:?}", category_lookup.len());
 
     // Need HashMap to get Field Id
-    let field_id_lookup =
-        utils::create_field_id_lookup(&category_lookup, client, &account_name, &environment).await;
+    let field_id_lookup = utils::create_field_id_lookup(
+        &category_lookup,
+        client,
+        &account_name,
+        &environment,
+        limiter,
+    )
+    .await?;
     debug!("field_id_lookup: {:?}", field_id_lookup);
 
     // Create set to store values - you can't have duplicates of Size or Color
@@ -143,7 +150,7 @@ pub async fn gen_specification_values_file_alternate(
 
 // //     // Need HashMap to get Field Id
 // //     let field_id_lookup =
-// //         utils::create_field_id_lookup(&category_lookup, client, &account_name, &environment).await;
+// //         utils::create_field_id_lookup(&category_lookup, client, &account_name, &environment, limiter).await?;
 // //     debug!("field_id_lookup: {:?}", field_id_lookup);
 
 // //     // Create set to store values - you can't have duplicates of Size or Color
@@ -189,13 +196,18 @@ pub async fn gen_specification_values_file_alternate(
 //     Ok(())
 // }
 
+// Generate the values of the specifications with a value list from the SKU allowed values and
+// the product specifications (Combo, Radio, Checkbox...). Either input file can be omitted
+#[allow(clippy::too_many_arguments)]
 pub async fn gen_specification_values_file(
     file_path: String,
     client: &Client,
     account_name: String,
     environment: String,
+    prod_specs_assignment_file: String,
     sku_spec_allowed_values_file: String,
     product_file: String,
+    limiter: &RequestLimiter,
 ) -> Result<(), Box<dyn Error>> {
     info!("Starting generation of specification values file");
     // Read in the category tree and store in a HashMap for lookup
@@ -204,8 +216,14 @@ pub async fn gen_specification_values_file(
     debug!("category_lookup: {:?}", category_lookup.len());
 
     // Need HashMap to get Field Id
-    let field_id_lookup =
-        utils::create_field_id_lookup(&category_lookup, client, &account_name, &environment).await;
+    let field_id_lookup = utils::create_field_id_lookup(
+        &category_lookup,
+        client,
+        &account_name,
+        &environment,
+        limiter,
+    )
+    .await?;
     debug!("field_id_lookup: {:?}", field_id_lookup.len());
     // Get a lookup HashMap for the parent category of a product
     let product_parent_category_lookup =
@@ -216,25 +234,17 @@ pub async fn gen_specification_values_file(
     );
     // Get a lookup for the cateogory name of a category by GroupIdentifier
     let category_identifier_name_lookup =
-        utils::create_category_name_lookup(client, &account_name, &environment).await;
+        utils::create_category_name_lookup(client, &account_name, &environment, limiter).await?;
     debug!(
         "category_identifier_name_lookup: {:?}",
         category_identifier_name_lookup.len()
     );
 
-    let in_file = File::open(&sku_spec_allowed_values_file).unwrap();
-    let mut reader = csv::Reader::from_reader(in_file);
-    let out_path = file_path;
-    let mut writer = csv::Writer::from_path(out_path)?;
-
-    let mut record = StringRecord::new();
-    let mut specvalue_set: HashSet<SpecificationValue> = HashSet::new();
-    while reader.read_record(&mut record)? {
-        // product_ref_id is in the Column 1 - index starts at 0 so position 0
-        let product_ref_id = record.get(0).unwrap().to_string();
+    // The field id of a specification of the product's category
+    let get_field_id = |product_ref_id: &str, name: &str| -> i32 {
         debug!("product_ref_id: {}", product_ref_id);
         // look up the part number
-        let parent_cat_identifier = product_parent_category_lookup.get(&product_ref_id).unwrap();
+        let parent_cat_identifier = product_parent_category_lookup.get(product_ref_id).unwrap();
         // look up the category name
         let parent_cat_name = category_identifier_name_lookup
             .get(&parent_cat_identifier.to_string())
@@ -242,37 +252,112 @@ pub async fn gen_specification_values_file(
         // Look up the VTEX Category Id
         let vtex_cat_id = category_lookup.get(&parent_cat_name.to_string()).unwrap();
         debug!("vtex_cat_id: {}", vtex_cat_id);
-        // Name starts in the Column 2 - index starts at 0 so position 1
-        let name = record.get(1).unwrap().to_string();
-        let key = vtex_cat_id.to_string().to_owned() + "|" + name.as_str();
-        let field_id = field_id_lookup
+        let key = vtex_cat_id.to_string() + "|" + name;
+        *field_id_lookup
             .get(&key)
-            .expect("failed to find field_id for category in field_id_lookup");
-
-        // The AllowedValues fields start in the 4th postion of the file - range begins at 3 in for loop
-        for number in 3..record.len() {
-            let value = record.get(number).unwrap().trim();
-            if !value.is_empty() {
-                debug!("name: [{}] value: [{}]", name, value);
-                let field_value = SpecificationValue {
-                    field_value_id: None,
-                    field_id: *field_id,
-                    is_active: Some(true),
-                    name: value.to_string(),
-                    text: None,
-                    position: None,
-                };
-                // Don't insert duplicate records
-                if !specvalue_set.contains(&field_value) {
-                    writer.serialize(field_value.clone())?;
-                    specvalue_set.insert(field_value);
-                }
+            .expect("failed to find field_id for category in field_id_lookup")
+    };
+
+    // (field_id, value) of every value to generate
+    let mut values: Vec<(i32, String)> = Vec::new();
+    if !sku_spec_allowed_values_file.is_empty() {
+        for record in utils::read_sku_spec_allowed_values(&sku_spec_allowed_values_file)? {
+            values.push((
+                get_field_id(&record.product_ref_id, &record.name),
+                record.value,
+            ));
+        }
+    }
+    if !prod_specs_assignment_file.is_empty() {
+        let in_file = File::open(&prod_specs_assignment_file)?;
+        let mut reader = csv::Reader::from_reader(in_file);
+        let mut product_values: Vec<(i32, String)> = Vec::new();
+        for line in reader.deserialize() {
+            let record: ProductSpecificationAssignment = line?;
+            // Brand is not created as a specification
+            if record.name.eq("Brand") || record.value.trim().is_empty() {
+                continue;
             }
+            product_values.push((
+                get_field_id(&record.product_ref_id, &record.name),
+                record.value,
+            ));
+        }
+        // Only the product specifications with a value list - the field type is read from VTEX
+        // as the specifications are created before their values
+        let product_field_ids: HashMap<String, i32> = product_values
+            .iter()
+            .map(|(field_id, _)| (field_id.to_string(), *field_id))
+            .collect();
+        let field_type_lookup = utils::create_field_type_lookup(
+            &product_field_ids,
+            client,
+            &account_name,
+            &environment,
+            limiter,
+        )
+        .await?;
+        values.extend(product_values.into_iter().filter(|(field_id, _)| {
+            field_type_lookup
+                .get(field_id)
+                .is_some_and(|field_type| field_type.has_value_list())
+        }));
+    }
+
+    let out_path = file_path;
+    let mut writer = csv::Writer::from_path(out_path)?;
+    let mut specvalue_set: HashSet<SpecificationValue> = HashSet::new();
+    for (field_id, value) in values {
+        debug!("field_id: [{}] value: [{}]", field_id, value);
+        let field_value = SpecificationValue {
+            field_value_id: None,
+            field_id,
+            is_active: Some(true),
+            // Values are matched trimmed when the associations are generated
+            name: value.trim().to_string(),
+            text: None,
+            position: None,
+        };
+        // Don't insert duplicate records
+        if !specvalue_set.contains(&field_value) {
+            writer.serialize(field_value.clone())?;
+            specvalue_set.insert(field_value);
         }
     }
     // Flush the records
     writer.flush()?;
-    info!("Finished specification values file generation");
+    info!(
+        "Finished specification values file generation: {}",
+        specvalue_set.len()
+    );
+
+    Ok(())
+}
+
+// Convert a SKU Specification Allowed Values file (wide or long) to the long format
+pub fn convert_allowed_values_to_long(
+    file_path: String,
+    sku_spec_allowed_values_file: String,
+) -> Result<(), Box<dyn Error>> {
+    info!("Start: conversion of allowed values file to long format");
+    let allowed_values = utils::read_sku_spec_allowed_values(&sku_spec_allowed_values_file)?;
+    let mut writer = csv::Writer::from_path(file_path)?;
+    utils::write_sku_spec_allowed_values_long(&allowed_values, &mut writer)?;
+    info!("Finish: conversion of allowed values file to long format");
+
+    Ok(())
+}
+
+// Convert a SKU Specification Allowed Values file (wide or long) to the wide format
+pub fn convert_allowed_values_to_wide(
+    file_path: String,
+    sku_spec_allowed_values_file: String,
+) -> Result<(), Box<dyn Error>> {
+    info!("Start: conversion of allowed values file to wide format");
+    let allowed_values = utils::read_sku_spec_allowed_values(&sku_spec_allowed_values_file)?;
+    let mut writer = csv::Writer::from_path(file_path)?;
+    utils::write_sku_spec_allowed_values_wide(&allowed_values, &mut writer)?;
+    info!("Finish: conversion of allowed values file to wide format");
 
     Ok(())
 }
@@ -300,6 +385,9 @@ pub async fn load_specification_values(
         specvalues_rec.push(record);
     }
 
+    // Invalidated before the first create so the lookup is rebuilt even when the load fails
+    LookupCache::new(&account_name, &environment).invalidate(&[cache::FIELD_VALUE_ID_LOOKUP]);
+
     let lim = Arc::new(RateLimiter::direct(Quota::per_second(rate_limit)));
 
     let bodies = stream::iter(specvalues_rec)
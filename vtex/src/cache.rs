@@ -0,0 +1,173 @@
+// On-disk cache of the lookups that take one call per category or field to build.
+// The lookups are stored per account in data/cache/{accountName}-{environment}/{name}.json and
+// expire after LOOKUP_CACHE_TTL seconds (.env - default 24 hours, 0 disables the cache)
+use log::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const CATEGORY_NAME_LOOKUP: &str = "category_name_lookup";
+pub const BRAND_LOOKUP: &str = "brand_lookup";
+pub const FIELD_ID_LOOKUP: &str = "field_id_lookup";
+pub const FIELD_VALUE_ID_LOOKUP: &str = "field_value_id_lookup";
+
+const DEFAULT_TTL_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheEntry<T> {
+    // Seconds since the epoch
+    pub created_at: u64,
+    pub lookup: T,
+}
+
+impl<T> CacheEntry<T> {
+    pub fn new(lookup: T) -> CacheEntry<T> {
+        CacheEntry {
+            created_at: now(),
+            lookup,
+        }
+    }
+}
+
+pub struct LookupCache {
+    dir: PathBuf,
+    ttl_secs: u64,
+}
+
+impl LookupCache {
+    pub fn new(account_name: &str, environment: &str) -> LookupCache {
+        let ttl_secs = env::var("LOOKUP_CACHE_TTL")
+            .ok()
+            .and_then(|ttl| ttl.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+        LookupCache::with_ttl(account_name, environment, ttl_secs)
+    }
+
+    pub fn with_ttl(account_name: &str, environment: &str, ttl_secs: u64) -> LookupCache {
+        LookupCache::in_dir(
+            Path::new("data/cache").join(format!("{}-{}", account_name, environment)),
+            ttl_secs,
+        )
+    }
+
+    // A cache stored in the directory
+    pub fn in_dir<P: AsRef<Path>>(dir: P, ttl_secs: u64) -> LookupCache {
+        LookupCache {
+            dir: dir.as_ref().to_path_buf(),
+            ttl_secs,
+        }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+
+    // The cached lookup - None when it is not cached, has expired or can not be read
+    pub fn get<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        self.get_entry(name).map(|entry| entry.lookup)
+    }
+
+    pub fn get_entry<T: DeserializeOwned>(&self, name: &str) -> Option<CacheEntry<T>> {
+        if self.ttl_secs == 0 {
+            return None;
+        }
+        let file = File::open(self.path(name)).ok()?;
+        let entry: CacheEntry<T> = match serde_json::from_reader(BufReader::new(file)) {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("cached {} could not be read: {}", name, e);
+                return None;
+            }
+        };
+        if now().saturating_sub(entry.created_at) >= self.ttl_secs {
+            debug!("cached {} has expired", name);
+            return None;
+        }
+        debug!("using cached {}", name);
+        Some(entry)
+    }
+
+    pub fn put<T: Serialize>(&self, name: &str, lookup: &T) {
+        self.put_entry(name, &CacheEntry::new(lookup))
+    }
+
+    // Failing to write the cache is not an error - the lookup is built again on the next run
+    pub fn put_entry<T: Serialize>(&self, name: &str, entry: &CacheEntry<T>) {
+        if self.ttl_secs == 0 {
+            return;
+        }
+        let result = fs::create_dir_all(&self.dir)
+            .map_err(|e| e.to_string())
+            .and_then(|_| File::create(self.path(name)).map_err(|e| e.to_string()))
+            .and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                serde_json::to_writer(&mut writer, entry).map_err(|e| e.to_string())?;
+                writer.flush().map_err(|e| e.to_string())
+            });
+        if result.is_err() {
+            // Do not leave a truncated file behind
+            let _ = fs::remove_file(self.path(name));
+        }
+        if let Err(e) = result {
+            warn!("{} could not be cached: {}", name, e);
+        }
+    }
+
+    // Remove the lookups - after they are changed in VTEX
+    pub fn invalidate(&self, names: &[&str]) {
+        for name in names {
+            if fs::remove_file(self.path(name)).is_ok() {
+                info!("cached {} invalidated", name);
+            }
+        }
+    }
+
+    // Remove all the lookups of the account
+    pub fn clear(&self) {
+        if fs::remove_dir_all(&self.dir).is_ok() {
+            info!("lookup cache cleared: {}", self.dir.display());
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn put_get_expire_and_invalidate() {
+        let dir = env::temp_dir().join(format!("vtex-lookup-cache-{}", std::process::id()));
+        let cache = LookupCache::in_dir(&dir, 60);
+        let lookup: HashMap<String, i32> = [("Columbia".to_string(), 2000001)].into();
+        cache.put(BRAND_LOOKUP, &lookup);
+        assert_eq!(
+            cache.get::<HashMap<String, i32>>(BRAND_LOOKUP),
+            Some(lookup.clone())
+        );
+
+        let expired = CacheEntry {
+            created_at: now() - 61,
+            lookup: &lookup,
+        };
+        cache.put_entry(BRAND_LOOKUP, &expired);
+        assert_eq!(cache.get::<HashMap<String, i32>>(BRAND_LOOKUP), None);
+
+        cache.put(BRAND_LOOKUP, &lookup);
+        cache.invalidate(&[BRAND_LOOKUP]);
+        assert_eq!(cache.get::<HashMap<String, i32>>(BRAND_LOOKUP), None);
+        cache.clear();
+        assert!(!dir.exists());
+    }
+}
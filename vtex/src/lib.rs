@@ -1,8 +1,14 @@
+pub mod cache;
 pub mod csvrecords;
+pub mod snapshot;
 pub mod utils;
 
 pub mod model {
-    use serde::{Deserialize, Serialize};
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::{Deserialize, Serialize, Serializer};
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::str::FromStr;
 
     #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
     pub struct Category {
@@ -135,6 +141,148 @@ pub mod model {
         pub is_stock_keeping_unit: bool,
     }
 
+    // VTEX specification field types. Serialized as the VTEX FieldTypeId
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+    pub enum FieldType {
+        Text,
+        MultiLineText,
+        Number,
+        Combo,
+        Radio,
+        Checkbox,
+        IndexedText,
+        IndexedMultiLineText,
+    }
+
+    impl FieldType {
+        pub fn id(&self) -> i32 {
+            match self {
+                FieldType::Text => 1,
+                FieldType::MultiLineText => 2,
+                FieldType::Number => 4,
+                FieldType::Combo => 5,
+                FieldType::Radio => 6,
+                FieldType::Checkbox => 7,
+                FieldType::IndexedText => 8,
+                FieldType::IndexedMultiLineText => 9,
+            }
+        }
+
+        pub fn from_id(id: i64) -> Option<FieldType> {
+            match id {
+                1 => Some(FieldType::Text),
+                2 => Some(FieldType::MultiLineText),
+                4 => Some(FieldType::Number),
+                5 => Some(FieldType::Combo),
+                6 => Some(FieldType::Radio),
+                7 => Some(FieldType::Checkbox),
+                8 => Some(FieldType::IndexedText),
+                9 => Some(FieldType::IndexedMultiLineText),
+                _ => None,
+            }
+        }
+
+        // Combo, Radio and Checkbox fields take a FieldValueId from the field's value list
+        pub fn has_value_list(&self) -> bool {
+            matches!(
+                self,
+                FieldType::Combo | FieldType::Radio | FieldType::Checkbox
+            )
+        }
+    }
+
+    impl fmt::Display for FieldType {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let name = match self {
+                FieldType::Text => "Text",
+                FieldType::MultiLineText => "Multi-Line Text",
+                FieldType::Number => "Number",
+                FieldType::Combo => "Combo",
+                FieldType::Radio => "Radio",
+                FieldType::Checkbox => "Checkbox",
+                FieldType::IndexedText => "Indexed Text",
+                FieldType::IndexedMultiLineText => "Indexed Multi-Line Text",
+            };
+            write!(f, "{}", name)
+        }
+    }
+
+    // Accepts the FieldTypeId ("6") or the name ("Radio", "Multi-Line Text", "multilinetext")
+    impl FromStr for FieldType {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let s = s.trim();
+            if let Ok(id) = s.parse::<i64>() {
+                return FieldType::from_id(id).ok_or(format!("unknown FieldTypeId: {}", id));
+            }
+            let normalized: String = s
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase();
+            match normalized.as_str() {
+                "text" => Ok(FieldType::Text),
+                "multilinetext" => Ok(FieldType::MultiLineText),
+                "number" => Ok(FieldType::Number),
+                "combo" => Ok(FieldType::Combo),
+                "radio" => Ok(FieldType::Radio),
+                "checkbox" => Ok(FieldType::Checkbox),
+                "indexedtext" => Ok(FieldType::IndexedText),
+                "indexedmultilinetext" => Ok(FieldType::IndexedMultiLineText),
+                _ => Err(format!("unknown field type: {}", s)),
+            }
+        }
+    }
+
+    impl Serialize for FieldType {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_i32(self.id())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for FieldType {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct FieldTypeVisitor;
+
+            impl<'de> Visitor<'de> for FieldTypeVisitor {
+                type Value = FieldType;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str("a VTEX FieldTypeId or field type name")
+                }
+
+                fn visit_i64<E: de::Error>(self, v: i64) -> Result<FieldType, E> {
+                    FieldType::from_id(v)
+                        .ok_or_else(|| E::custom(format!("unknown FieldTypeId: {}", v)))
+                }
+
+                fn visit_u64<E: de::Error>(self, v: u64) -> Result<FieldType, E> {
+                    self.visit_i64(v as i64)
+                }
+
+                fn visit_str<E: de::Error>(self, v: &str) -> Result<FieldType, E> {
+                    v.parse::<FieldType>().map_err(E::custom)
+                }
+            }
+
+            deserializer.deserialize_any(FieldTypeVisitor)
+        }
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct SpecificationField {
+        pub name: String,
+        pub category_id: Option<i32>,
+        pub field_id: i32,
+        pub field_type_id: FieldType,
+        pub is_active: bool,
+        pub is_stock_keeping_unit: bool,
+        #[serde(default)]
+        pub is_required: bool,
+    }
+
     #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
     #[serde(rename_all = "PascalCase")]
     pub struct ProductSpecificationAssignment {
@@ -151,74 +299,23 @@ pub mod model {
         pub is_active: bool,
         pub is_top_menu_link_active: bool,
         pub default_value: Option<String>,
+        #[serde(default)]
+        pub field_type: Option<FieldType>,
+        #[serde(default)]
+        pub group_name: Option<String>,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
     #[serde(rename_all = "PascalCase")]
-    pub struct SkuSpecAllowedValues {
+    pub struct SkuSpecAllowedValue {
         pub product_ref_id: String,
         pub name: String,
         pub position: i32,
-        pub allowed_value1: String,
-        pub allowed_value2: String,
-        pub allowed_value3: String,
-        pub allowed_value4: String,
-        pub allowed_value5: String,
-        pub allowed_value6: String,
-        pub allowed_value7: String,
-        pub allowed_value8: String,
-        pub allowed_value9: String,
-        pub allowed_value10: String,
-        pub allowed_value11: String,
-        pub allowed_value12: String,
-        pub allowed_value13: String,
-        pub allowed_value14: String,
-        pub allowed_value15: String,
-        pub allowed_value16: String,
-        pub allowed_value17: String,
-        pub allowed_value18: String,
-        pub allowed_value19: String,
-        pub allowed_value20: String,
-        pub allowed_value21: String,
-        pub allowed_value22: String,
-        pub allowed_value23: String,
-        pub allowed_value24: String,
-        pub allowed_value25: String,
-        pub allowed_value26: String,
-        pub allowed_value27: String,
-        pub allowed_value28: String,
-        pub allowed_value29: String,
-        pub allowed_value30: String,
-        pub allowed_value31: String,
-        pub allowed_value32: String,
-        pub allowed_value33: String,
-        pub allowed_value34: String,
-        pub allowed_value35: String,
-        pub allowed_value36: String,
-        pub allowed_value37: String,
-        pub allowed_value38: String,
-        pub allowed_value39: String,
-        pub allowed_value40: String,
-        pub allowed_value41: String,
-        pub allowed_value42: String,
-        pub allowed_value43: String,
-        pub allowed_value44: String,
-        pub allowed_value45: String,
-        pub allowed_value46: String,
-        pub allowed_value47: String,
-        pub allowed_value48: String,
-        pub allowed_value49: String,
-        pub allowed_value50: String,
-        pub allowed_value51: String,
-        pub allowed_value52: String,
-        pub allowed_value53: String,
-        pub allowed_value54: String,
-        pub allowed_value55: String,
-        pub allowed_value56: String,
-        pub allowed_value57: String,
-        pub allowed_value58: String,
-        pub allowed_value59: String,
-        pub allowed_value60: String,
+        pub value: String,
+        #[serde(default)]
+        pub field_type: Option<FieldType>,
+        #[serde(default)]
+        pub group_name: Option<String>,
     }
 
     #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -226,7 +323,7 @@ pub mod model {
         #[serde(rename = "Id")]
         pub id: Option<i32>,
         #[serde(rename = "FieldTypeId")]
-        pub field_type_id: i32,
+        pub field_type_id: FieldType,
         #[serde(rename = "CategoryId")]
         pub category_id: Option<i32>,
         #[serde(rename = "FieldGroupId")]
@@ -390,6 +487,152 @@ pub mod model {
         pub ean: String,
     }
 
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct Collection {
+        pub id: Option<i32>,
+        pub name: String,
+        pub searchable: bool,
+        pub highlight: bool,
+        pub date_from: String,
+        pub date_to: String,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct CollectionSku {
+        pub collection_name: String,
+        pub sku_ref_id: String,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct SubCollection {
+        pub id: Option<i32>,
+        pub collection_id: i32,
+        pub name: String,
+        #[serde(rename = "Type")]
+        pub sub_collection_type: String,
+        pub pre_sale: bool,
+        pub release: bool,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct SubCollectionSku {
+        pub sku_id: i32,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    #[serde(rename_all = "camelCase")]
+    pub struct CollectionSearch {
+        pub paging: CollectionPaging,
+        pub items: Vec<CollectionSearchItem>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    #[serde(rename_all = "camelCase")]
+    pub struct CollectionPaging {
+        pub page: i32,
+        pub per_page: i32,
+        pub total: i32,
+        pub pages: i32,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    #[serde(rename_all = "camelCase")]
+    pub struct CollectionSearchItem {
+        pub id: i32,
+        pub name: String,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct CollectionProducts {
+        pub page: i32,
+        pub size: i32,
+        pub total_rows: i32,
+        pub total_page: i32,
+        pub data: Vec<CollectionProduct>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct CollectionProduct {
+        pub product_id: i32,
+        pub sku_id: i32,
+        pub sub_collection_id: i32,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+    pub enum DifferenceType {
+        MissingInVtex,
+        ExtraInVtex,
+        Changed,
+    }
+
+    // A row of the reconciliation report between the source CSV files and VTEX
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct CatalogDifference {
+        pub object: String,
+        pub ref_id: String,
+        pub difference: DifferenceType,
+        pub field: Option<String>,
+        pub source_value: Option<String>,
+        pub vtex_value: Option<String>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+    pub enum IssueSeverity {
+        Error,
+        Warning,
+        Info,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+    pub enum IssueType {
+        NoImages,
+        NoPrice,
+        NoStock,
+        InactiveProduct,
+        MissingEan,
+        MissingDimensions,
+        EmptyDescription,
+        MissingRequiredSpecification,
+    }
+
+    // A row of the data quality audit of the SKUs in VTEX
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct CatalogIssue {
+        pub severity: IssueSeverity,
+        pub issue: IssueType,
+        pub sku_id: i32,
+        pub sku_ref_id: String,
+        pub product_ref_id: String,
+        pub details: Option<String>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct SkuKitComponent {
+        pub kit_sku_ref_id: String,
+        pub component_sku_ref_id: String,
+        pub quantity: i32,
+        pub unit_price: f64,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct SkuKit {
+        pub id: Option<i32>,
+        pub stock_keeping_unit_parent: i32,
+        pub stock_keeping_unit_id: i32,
+        pub quantity: i32,
+        pub unit_price: f64,
+    }
+
     #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
     #[serde(rename_all = "PascalCase")]
     pub struct SimilarCategory {
@@ -409,6 +652,21 @@ pub mod model {
         pub error: Option<String>,
     }
 
+    // A page of product ids with the sku ids of each product
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    pub struct ProductAndSkuIds {
+        // sku ids keyed by product id
+        pub data: HashMap<String, Vec<i32>>,
+        pub range: ProductAndSkuIdsRange,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    pub struct ProductAndSkuIdsRange {
+        pub total: i32,
+        pub from: i32,
+        pub to: i32,
+    }
+
     #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
     #[serde(rename_all = "camelCase")]
     pub struct PriceGet {
@@ -419,6 +677,17 @@ pub mod model {
         pub cost_price: Option<f32>,
     }
 
+    // Price of a sku for a trade policy (price table) after the pricing rules are applied
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ComputedPrice {
+        pub trade_policy_id: String,
+        pub list_price: Option<f32>,
+        pub cost_price: Option<f32>,
+        pub selling_price: f32,
+        pub price_valid_until: Option<String>,
+    }
+
     #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
     #[serde(rename_all = "camelCase")]
     pub struct Inventory {
@@ -487,7 +756,8 @@ pub mod model {
         pub images: Option<Vec<Image>>,
         // pub videos: Option<_>,
         pub sku_specifications: Option<Vec<SkuSpecification>>,
-        // pub product_specifications: Option<_>,
+        // Same shape as the sku specifications
+        pub product_specifications: Option<Vec<SkuSpecification>>,
         // pub product_clusters_ids: Option<String>,
         // pub positions_in_clusters: Option<_>,
         // pub product_cluster_names: Option<_>,
@@ -495,7 +765,7 @@ pub mod model {
         pub product_category_ids: String,
         pub is_direct_category_active: bool,
         // pub product_global_category_id: Option<_>,
-        pub product_categories: serde_json::Value,
+        pub product_categories: ProductCategories,
         pub commercial_condition_id: i32,
         pub reward_value: f32,
         pub alternate_ids: AlternateIds,
@@ -563,19 +833,27 @@ pub mod model {
         pub field_group_name: String,
     }
 
-    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
-    pub struct ProductCategories {
-        #[serde(rename = "3")]
-        pub three: String,
-        #[serde(rename = "2")]
-        pub two: String,
-        #[serde(rename = "1")]
-        pub one: String,
+    // Category names keyed by category id - for every level of the product category
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+    #[serde(transparent)]
+    pub struct ProductCategories(pub HashMap<String, String>);
+
+    impl ProductCategories {
+        // The category names from the department down to the product category.
+        // product_category_ids is the path of ids (ex: /1/12/123/)
+        pub fn path(&self, product_category_ids: &str) -> Vec<String> {
+            product_category_ids
+                .split('/')
+                .filter(|id| !id.is_empty())
+                .filter_map(|id| self.0.get(id).cloned())
+                .collect()
+        }
     }
 
     #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
     #[serde(rename_all = "PascalCase")]
     pub struct AlternateIds {
         pub ref_id: String,
+        pub ean: Option<String>,
     }
 }
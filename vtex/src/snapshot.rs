@@ -0,0 +1,694 @@
+// A local SQLite copy of the catalog of an account - written by `vtex_impex snapshot` and read by
+// the commands that run against the snapshot instead of the live API.
+// Each table has the columns to query plus a data column with the record from the API (JSON).
+// The schema is documented in impex/README.md
+use crate::model::{
+    Balance, BrandList, CategoryTree, FieldValueList, InventoryList, PriceGet, Product,
+    ProductAndSkuIds, ProductAndSkuIdsRange, SkuAndContext, SpecificationField,
+};
+use crate::utils::{self, RequestLimiter};
+use log::*;
+use reqwest::Client;
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+
+const DROP_TABLES: &str = "
+    DROP TABLE IF EXISTS meta;
+    DROP TABLE IF EXISTS categories;
+    DROP TABLE IF EXISTS brands;
+    DROP TABLE IF EXISTS specification_fields;
+    DROP TABLE IF EXISTS category_specification_fields;
+    DROP TABLE IF EXISTS specification_values;
+    DROP TABLE IF EXISTS products;
+    DROP TABLE IF EXISTS skus;
+    DROP TABLE IF EXISTS prices;
+    DROP TABLE IF EXISTS inventory;
+";
+
+const SCHEMA: &str = "
+    CREATE TABLE meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE categories (
+        id INTEGER PRIMARY KEY,
+        parent_id INTEGER,
+        level INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE brands (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        is_active INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE specification_fields (
+        field_id INTEGER PRIMARY KEY,
+        category_id INTEGER,
+        name TEXT NOT NULL,
+        field_type_id INTEGER NOT NULL,
+        is_active INTEGER NOT NULL,
+        is_required INTEGER NOT NULL,
+        is_stock_keeping_unit INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE category_specification_fields (
+        category_id INTEGER NOT NULL,
+        field_id INTEGER NOT NULL,
+        PRIMARY KEY (category_id, field_id)
+    );
+    CREATE TABLE specification_values (
+        field_value_id INTEGER PRIMARY KEY,
+        field_id INTEGER NOT NULL,
+        value TEXT NOT NULL,
+        is_active INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE products (
+        id INTEGER PRIMARY KEY,
+        ref_id TEXT,
+        name TEXT NOT NULL,
+        category_id INTEGER,
+        brand_id INTEGER,
+        is_active INTEGER,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX products_ref_id ON products (ref_id);
+    CREATE TABLE skus (
+        id INTEGER PRIMARY KEY,
+        product_id INTEGER NOT NULL,
+        ref_id TEXT NOT NULL,
+        product_ref_id TEXT NOT NULL,
+        is_active INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX skus_product_id ON skus (product_id);
+    CREATE TABLE prices (
+        sku_id INTEGER PRIMARY KEY,
+        list_price REAL,
+        base_price REAL,
+        cost_price REAL,
+        markup REAL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE inventory (
+        sku_id INTEGER NOT NULL,
+        warehouse_id TEXT NOT NULL,
+        warehouse_name TEXT NOT NULL,
+        total_quantity INTEGER NOT NULL,
+        reserved_quantity INTEGER NOT NULL,
+        has_unlimited_quantity INTEGER NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (sku_id, warehouse_id)
+    );
+";
+
+pub struct Snapshot {
+    conn: Connection,
+}
+
+impl Snapshot {
+    // Create an empty snapshot - an existing snapshot in the file is replaced
+    pub fn create(path: &str) -> rusqlite::Result<Snapshot> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(DROP_TABLES)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Snapshot { conn })
+    }
+
+    // Open an existing snapshot to read. The created_at is written once every read succeeded:
+    // a snapshot without it is incomplete and is refused
+    pub fn open(path: &str) -> Result<Snapshot, Box<dyn Error>> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let snapshot = Snapshot { conn };
+        let created_at = match snapshot.meta("created_at")? {
+            Some(created_at) => created_at,
+            None => {
+                return Err(format!(
+                    "snapshot: {} is incomplete (no created_at) - create it again",
+                    path
+                )
+                .into())
+            }
+        };
+        info!(
+            "reading snapshot: {} of account: {} created_at: {}",
+            path,
+            snapshot.meta("account_name")?.unwrap_or_default(),
+            created_at
+        );
+        Ok(snapshot)
+    }
+
+    pub fn set_meta(&self, key: &str, value: &str) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    pub fn meta(&self, key: &str) -> rusqlite::Result<Option<String>> {
+        self.conn
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()
+    }
+
+    // The categories of the tree, every level with its parent
+    pub fn insert_categories(&mut self, category_tree: &[CategoryTree]) -> rusqlite::Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO categories (id, parent_id, level, name, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            let mut stack: Vec<(&CategoryTree, Option<i32>, i32)> = category_tree
+                .iter()
+                .map(|category| (category, None, 1))
+                .collect();
+            while let Some((category, parent_id, level)) = stack.pop() {
+                let data = CategoryTree {
+                    children: None,
+                    ..category.clone()
+                };
+                stmt.execute(params![
+                    category.id,
+                    parent_id,
+                    level,
+                    category.name,
+                    to_json(&data)?
+                ])?;
+                inserted += 1;
+                for child in category.children.iter().flatten() {
+                    stack.push((child, Some(category.id), level + 1));
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    pub fn insert_brands(&mut self, brands: &[BrandList]) -> rusqlite::Result<usize> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO brands (id, name, is_active, data) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for brand in brands {
+                stmt.execute(params![
+                    brand.id,
+                    brand.name,
+                    brand.is_active,
+                    to_json(brand)?
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(brands.len())
+    }
+
+    // The specification fields and the field ids of each category
+    pub fn insert_specification_fields(
+        &mut self,
+        fields: &[SpecificationField],
+        category_fields: &[(i32, Vec<i32>)],
+    ) -> rusqlite::Result<usize> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO specification_fields (field_id, category_id, name, field_type_id, is_active, is_required, is_stock_keeping_unit, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for field in fields {
+                stmt.execute(params![
+                    field.field_id,
+                    field.category_id,
+                    field.name,
+                    field.field_type_id.id(),
+                    field.is_active,
+                    field.is_required,
+                    field.is_stock_keeping_unit,
+                    to_json(field)?
+                ])?;
+            }
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO category_specification_fields (category_id, field_id) VALUES (?1, ?2)",
+            )?;
+            for (category_id, field_ids) in category_fields {
+                for field_id in field_ids {
+                    stmt.execute(params![category_id, field_id])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(fields.len())
+    }
+
+    // The values of the fields with a value list - keyed by field_id
+    pub fn insert_specification_values(
+        &mut self,
+        field_values: &[(i32, Vec<FieldValueList>)],
+    ) -> rusqlite::Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO specification_values (field_value_id, field_id, value, is_active, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for (field_id, values) in field_values {
+                for value in values {
+                    stmt.execute(params![
+                        value.field_value_id,
+                        field_id,
+                        value.value,
+                        value.is_active,
+                        to_json(value)?
+                    ])?;
+                    inserted += 1;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    pub fn insert_products(&mut self, products: &[Product]) -> rusqlite::Result<usize> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO products (id, ref_id, name, category_id, brand_id, is_active, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for product in products {
+                stmt.execute(params![
+                    product.id,
+                    product.ref_id,
+                    product.name,
+                    product.category_id,
+                    product.brand_id,
+                    product.is_active,
+                    to_json(product)?
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(products.len())
+    }
+
+    pub fn insert_skus(
+        &mut self,
+        item_records: &HashMap<i32, SkuAndContext>,
+    ) -> rusqlite::Result<usize> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO skus (id, product_id, ref_id, product_ref_id, is_active, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for sku_ctx in item_records.values() {
+                stmt.execute(params![
+                    sku_ctx.id,
+                    sku_ctx.product_id,
+                    sku_ctx.alternate_ids.ref_id,
+                    sku_ctx.product_ref_id,
+                    sku_ctx.is_active,
+                    to_json(sku_ctx)?
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(item_records.len())
+    }
+
+    pub fn insert_prices(
+        &mut self,
+        price_records: &HashMap<i32, PriceGet>,
+    ) -> rusqlite::Result<usize> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO prices (sku_id, list_price, base_price, cost_price, markup, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for (sku_id, price) in price_records {
+                stmt.execute(params![
+                    sku_id,
+                    price.list_price,
+                    price.base_price,
+                    price.cost_price,
+                    price.markup,
+                    to_json(price)?
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(price_records.len())
+    }
+
+    pub fn insert_inventory(
+        &mut self,
+        inventory_records: &HashMap<i32, InventoryList>,
+    ) -> rusqlite::Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO inventory (sku_id, warehouse_id, warehouse_name, total_quantity, reserved_quantity, has_unlimited_quantity, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for (sku_id, inventory) in inventory_records {
+                for balance in &inventory.balance {
+                    stmt.execute(params![
+                        sku_id,
+                        balance.warehouse_id,
+                        balance.warehouse_name,
+                        balance.total_quantity,
+                        balance.reserved_quantity,
+                        balance.has_unlimited_quantity,
+                        to_json(balance)?
+                    ])?;
+                    inserted += 1;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    pub fn category_ids(&self) -> rusqlite::Result<Vec<i32>> {
+        let mut stmt = self.conn.prepare("SELECT id FROM categories ORDER BY id")?;
+        let category_ids = stmt.query_map([], |row| row.get(0))?.collect();
+        category_ids
+    }
+
+    pub fn sku_ids(&self) -> rusqlite::Result<Vec<i32>> {
+        let mut stmt = self.conn.prepare("SELECT id FROM skus ORDER BY id")?;
+        let sku_ids = stmt.query_map([], |row| row.get(0))?.collect();
+        sku_ids
+    }
+
+    pub fn item_records(&self, sku_ids: &[i32]) -> rusqlite::Result<HashMap<i32, SkuAndContext>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT data FROM skus WHERE id = ?1")?;
+        let mut item_records: HashMap<i32, SkuAndContext> = HashMap::new();
+        for sku_id in sku_ids {
+            if let Some(data) = stmt
+                .query_row([sku_id], |row| row.get::<_, String>(0))
+                .optional()?
+            {
+                item_records.insert(*sku_id, from_json(&data)?);
+            }
+        }
+        Ok(item_records)
+    }
+
+    pub fn price_records(&self, sku_ids: &[i32]) -> rusqlite::Result<HashMap<i32, PriceGet>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT data FROM prices WHERE sku_id = ?1")?;
+        let mut price_records: HashMap<i32, PriceGet> = HashMap::new();
+        for sku_id in sku_ids {
+            if let Some(data) = stmt
+                .query_row([sku_id], |row| row.get::<_, String>(0))
+                .optional()?
+            {
+                price_records.insert(*sku_id, from_json(&data)?);
+            }
+        }
+        Ok(price_records)
+    }
+
+    pub fn inventory_records(
+        &self,
+        sku_ids: &[i32],
+    ) -> rusqlite::Result<HashMap<i32, InventoryList>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT data FROM inventory WHERE sku_id = ?1 ORDER BY warehouse_id")?;
+        let mut inventory_records: HashMap<i32, InventoryList> = HashMap::new();
+        for sku_id in sku_ids {
+            let balance = stmt
+                .query_map([sku_id], |row| row.get::<_, String>(0))?
+                .map(|data| from_json::<Balance>(&data?))
+                .collect::<rusqlite::Result<Vec<Balance>>>()?;
+            if !balance.is_empty() {
+                inventory_records.insert(
+                    *sku_id,
+                    InventoryList {
+                        sku_id: sku_id.to_string(),
+                        balance,
+                    },
+                );
+            }
+        }
+        Ok(inventory_records)
+    }
+
+    pub fn product_by_ref_id(&self, ref_id: &str) -> rusqlite::Result<Option<Product>> {
+        self.conn
+            .query_row(
+                "SELECT data FROM products WHERE ref_id = ?1",
+                [ref_id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?
+            .map(|data| from_json(&data))
+            .transpose()
+    }
+
+    // The specification fields assigned to the category
+    pub fn spec_fields_for_category(
+        &self,
+        category_id: &str,
+    ) -> rusqlite::Result<Vec<SpecificationField>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT f.data FROM category_specification_fields c
+             JOIN specification_fields f ON f.field_id = c.field_id
+             WHERE c.category_id = ?1 ORDER BY f.field_id",
+        )?;
+        let fields = stmt
+            .query_map([category_id], |row| row.get::<_, String>(0))?
+            .map(|data| from_json(&data?))
+            .collect();
+        fields
+    }
+
+    // The products from..to (1 based, ordered by product id) with their sku ids - like GetProductAndSkuIds
+    pub fn product_and_sku_ids(&self, from: i32, to: i32) -> rusqlite::Result<ProductAndSkuIds> {
+        let total: i32 =
+            self.conn
+                .query_row("SELECT COUNT(DISTINCT product_id) FROM skus", [], |row| {
+                    row.get(0)
+                })?;
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT s.product_id, s.id FROM skus s
+             JOIN (SELECT DISTINCT product_id FROM skus ORDER BY product_id LIMIT ?1 OFFSET ?2) p
+             ON p.product_id = s.product_id",
+        )?;
+        let mut data: HashMap<String, Vec<i32>> = HashMap::new();
+        let rows = stmt.query_map(params![(to - from + 1).max(0), (from - 1).max(0)], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?))
+        })?;
+        for row in rows {
+            let (product_id, sku_id) = row?;
+            data.entry(product_id.to_string()).or_default().push(sku_id);
+        }
+        Ok(ProductAndSkuIds {
+            data,
+            range: ProductAndSkuIdsRange { total, from, to },
+        })
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> rusqlite::Result<String> {
+    serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+fn from_json<T: DeserializeOwned>(data: &str) -> rusqlite::Result<T> {
+    serde_json::from_str(data)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))
+}
+
+// Where the catalog is read from - the live account or a snapshot
+pub enum CatalogSource<'a> {
+    Api {
+        client: &'a Client,
+        account_name: &'a str,
+        environment: &'a str,
+    },
+    Snapshot(&'a Snapshot),
+}
+
+impl CatalogSource<'_> {
+    pub async fn sku_ids(&self) -> Result<Vec<i32>, Box<dyn Error>> {
+        match self {
+            CatalogSource::Api {
+                client,
+                account_name,
+                environment,
+            } => Ok(utils::get_all_sku_ids(client, account_name, environment).await),
+            CatalogSource::Snapshot(snapshot) => Ok(snapshot.sku_ids()?),
+        }
+    }
+
+    pub async fn item_records(
+        &self,
+        sku_ids: &[i32],
+    ) -> Result<HashMap<i32, SkuAndContext>, Box<dyn Error>> {
+        match self {
+            CatalogSource::Api {
+                client,
+                account_name,
+                environment,
+            } => Ok(utils::get_item_records(sku_ids, client, account_name, environment).await?),
+            CatalogSource::Snapshot(snapshot) => Ok(snapshot.item_records(sku_ids)?),
+        }
+    }
+
+    pub async fn price_records(
+        &self,
+        sku_ids: &[i32],
+    ) -> Result<HashMap<i32, PriceGet>, Box<dyn Error>> {
+        match self {
+            CatalogSource::Api {
+                client,
+                account_name,
+                ..
+            } => Ok(utils::get_price_records(sku_ids, client, account_name).await?),
+            CatalogSource::Snapshot(snapshot) => Ok(snapshot.price_records(sku_ids)?),
+        }
+    }
+
+    pub async fn inventory_records(
+        &self,
+        sku_ids: &[i32],
+    ) -> Result<HashMap<i32, InventoryList>, Box<dyn Error>> {
+        match self {
+            CatalogSource::Api {
+                client,
+                account_name,
+                environment,
+            } => Ok(
+                utils::get_inventory_records(sku_ids, client, account_name, environment).await?,
+            ),
+            CatalogSource::Snapshot(snapshot) => Ok(snapshot.inventory_records(sku_ids)?),
+        }
+    }
+
+    // The product of the ref_id - None when it is not in the catalog
+    pub async fn product_by_ref_id(
+        &self,
+        ref_id: &str,
+        limiter: &RequestLimiter,
+    ) -> Result<Option<Product>, String> {
+        match self {
+            CatalogSource::Api {
+                client,
+                account_name,
+                environment,
+            } => {
+                utils::get_product_record_by_ref_id_with_retry(
+                    ref_id,
+                    client,
+                    account_name,
+                    environment,
+                    limiter,
+                )
+                .await
+            }
+            CatalogSource::Snapshot(snapshot) => snapshot
+                .product_by_ref_id(ref_id)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    pub async fn product_and_sku_ids(
+        &self,
+        from: i32,
+        to: i32,
+    ) -> Result<ProductAndSkuIds, Box<dyn Error>> {
+        match self {
+            CatalogSource::Api {
+                client,
+                account_name,
+                environment,
+            } => Ok(
+                utils::get_product_and_sku_ids(client, account_name, environment, from, to).await?,
+            ),
+            CatalogSource::Snapshot(snapshot) => Ok(snapshot.product_and_sku_ids(from, to)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prices_inventory_and_pages() {
+        let mut snapshot = Snapshot::create(":memory:").unwrap();
+        let price = PriceGet {
+            item_id: "1".to_string(),
+            markup: Some(20.0),
+            list_price: None,
+            base_price: Some(12.5),
+            cost_price: Some(10.0),
+        };
+        snapshot
+            .insert_prices(&[(1, price.clone())].into_iter().collect())
+            .unwrap();
+        let balance = Balance {
+            warehouse_id: "1".to_string(),
+            warehouse_name: "warehouse1".to_string(),
+            total_quantity: 3,
+            reserved_quantity: 1,
+            has_unlimited_quantity: false,
+            time_to_refill: None,
+            date_of_supply_utc: None,
+        };
+        let inventory = InventoryList {
+            sku_id: "1".to_string(),
+            balance: vec![balance],
+        };
+        snapshot
+            .insert_inventory(&[(1, inventory.clone())].into_iter().collect())
+            .unwrap();
+        assert_eq!(
+            snapshot.price_records(&[1, 2]).unwrap().get(&1),
+            Some(&price)
+        );
+        let inventory_records = snapshot.inventory_records(&[1, 2]).unwrap();
+        assert_eq!(inventory_records.len(), 1);
+        assert_eq!(inventory_records.get(&1), Some(&inventory));
+
+        for (sku_id, product_id) in [(1, 10), (2, 10), (3, 20), (4, 30)] {
+            snapshot
+                .conn
+                .execute(
+                    "INSERT INTO skus (id, product_id, ref_id, product_ref_id, is_active, data) VALUES (?1, ?2, '', '', 1, '{}')",
+                    params![sku_id, product_id],
+                )
+                .unwrap();
+        }
+        let page = snapshot.product_and_sku_ids(1, 2).unwrap();
+        assert_eq!(page.range.total, 3);
+        assert_eq!(page.data.len(), 2);
+        assert_eq!(page.data.get("10").map(|ids| ids.len()), Some(2));
+        let page = snapshot.product_and_sku_ids(3, 4).unwrap();
+        assert_eq!(page.data.keys().collect::<Vec<_>>(), vec!["30"]);
+    }
+
+    #[test]
+    fn open_refuses_incomplete_snapshot() {
+        let path = std::env::temp_dir().join(format!("vtex-snapshot-{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+        let snapshot = Snapshot::create(path).unwrap();
+        assert!(Snapshot::open(path).is_err());
+
+        snapshot.set_meta("created_at", "1700000000").unwrap();
+        assert!(Snapshot::open(path).is_ok());
+        std::fs::remove_file(path).unwrap();
+    }
+}
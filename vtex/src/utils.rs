@@ -1,31 +1,168 @@
+use crate::cache::{self, CacheEntry, LookupCache};
 use crate::model::{
-    BrandList, Category, CategoryTree, FieldValueList, Product, Sku, SkuAndContext,
-    SpecificationGroup, SpecificationList,
+    BrandList, Category, CategoryTree, ComputedPrice, FieldType, FieldValueList, InventoryList,
+    PriceGet, Product, ProductAndSkuIds, Sku, SkuAndContext, SkuKit, SkuSpecAllowedValue,
+    SpecificationField, SpecificationGroup, SpecificationList,
 };
 // use futures::task::Spawn;
 use futures::{stream, StreamExt};
+use governor::clock::DefaultClock;
+use governor::state::{InMemoryState, NotKeyed};
+use governor::{Quota, RateLimiter};
 use log::*;
 use reqwest::{Client, StatusCode};
-use std::collections::HashMap;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
 use std::fs::File;
+use std::io::{Read, Write};
+use std::num::NonZeroU32;
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
+
+// Specification group used when the input does not name one
+pub const DEFAULT_SPECIFICATION_GROUP: &str = "Default Specification Group";
 // use crate::csvrecords::{CatRecord, ProdHeaderRecord, ProductLookup, SkuLookup};
 
 const CONCURRENT_REQUESTS: usize = 12;
+// Requests per second when the command does not set a rate limit
+pub const DEFAULT_RATE_LIMIT: u32 = 40;
+// Attempts per request and the delay before the first retry - doubled on each retry
+const MAX_ATTEMPTS: u32 = 4;
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+// Bounds the calls that read the catalog to build the lookups: the concurrent requests
+// (--concurrency) and the requests per second (--rate_limit) of the command
+pub struct RequestLimiter {
+    pub concurrency: usize,
+    limiter: RateLimiter<NotKeyed, InMemoryState, DefaultClock>,
+    retry_delay: Duration,
+}
+
+impl RequestLimiter {
+    pub fn new(concurrency: usize, rate_limit: NonZeroU32) -> RequestLimiter {
+        RequestLimiter {
+            concurrency: concurrency.max(1),
+            limiter: RateLimiter::direct(Quota::per_second(rate_limit)),
+            retry_delay: RETRY_DELAY,
+        }
+    }
+
+    // The default rate limit with the concurrency of the command
+    pub fn with_concurrency(concurrency: usize) -> RequestLimiter {
+        RequestLimiter::new(concurrency, NonZeroU32::new(DEFAULT_RATE_LIMIT).unwrap())
+    }
+}
+
+// The delay before the retry of the attempt (1 based): 500ms, 1s, 2s
+fn retry_delay(first_delay: Duration, attempt: u32) -> Duration {
+    first_delay * 2u32.pow(attempt - 1)
+}
+
+// GET the url as T. Connection errors, 429 Too Many Requests and 5xx responses are retried
+// with an exponential backoff. Other responses (ex: 404) fail without a retry
+async fn get_with_retry<T: DeserializeOwned>(
+    client: &Client,
+    url: &str,
+    limiter: &RequestLimiter,
+) -> Result<T, String> {
+    get_or_none_with_retry(client, url, limiter, false)
+        .await?
+        .ok_or_else(|| format!("url: {} response: {}", url, StatusCode::NOT_FOUND))
+}
+
+// GET the url as T - as get_with_retry, but a 404 Not Found is None when not_found_is_none
+async fn get_or_none_with_retry<T: DeserializeOwned>(
+    client: &Client,
+    url: &str,
+    limiter: &RequestLimiter,
+    not_found_is_none: bool,
+) -> Result<Option<T>, String> {
+    let mut attempt = 1;
+    loop {
+        limiter.limiter.until_ready().await;
+        let error = match client.get(url).send().await {
+            Ok(response) if response.status().is_success() => {
+                match response.json::<T>().await {
+                    Ok(result) => return Ok(Some(result)),
+                    // Usually an incomplete body - connection terminated
+                    Err(e) => format!("url: {} error: {}", url, e),
+                }
+            }
+            Ok(response) if not_found_is_none && response.status() == StatusCode::NOT_FOUND => {
+                return Ok(None)
+            }
+            Ok(response) => {
+                let status = response.status();
+                let message = response.text().await.unwrap_or_default();
+                let error = format!("url: {} response: {} message: {}", url, status, message);
+                if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() {
+                    return Err(error);
+                }
+                error
+            }
+            Err(e) => format!("url: {} error: {}", url, e),
+        };
+        if attempt >= MAX_ATTEMPTS {
+            return Err(error);
+        }
+        warn!("attempt {} of {} failed: {}", attempt, MAX_ATTEMPTS, error);
+        tokio::time::sleep(retry_delay(limiter.retry_delay, attempt)).await;
+        attempt += 1;
+    }
+}
+
+// GET the url of each sku_id concurrently - keyed by sku_id. A sku_id not found (404) is not
+// returned. Any other failure (after the retries) fails the whole read so that a SKU is never
+// silently missing from the result
+async fn get_sku_records<T: DeserializeOwned>(
+    urls: Vec<(i32, String)>,
+    client: &Client,
+    limiter: &RequestLimiter,
+) -> Result<HashMap<i32, T>, String> {
+    let records: Vec<Result<Option<(i32, T)>, String>> = stream::iter(urls)
+        .map(|(sku_id, url)| async move {
+            let record = get_or_none_with_retry::<T>(client, &url, limiter, true).await?;
+            if record.is_none() {
+                debug!("sku_id: {} not found url: {}", sku_id, url);
+            }
+            Ok(record.map(|record| (sku_id, record)))
+        })
+        .buffer_unordered(limiter.concurrency)
+        .collect()
+        .await;
+    let mut sku_records = HashMap::with_capacity(records.len());
+    for record in records {
+        if let Some((sku_id, record)) = record? {
+            sku_records.insert(sku_id, record);
+        }
+    }
+    Ok(sku_records)
+}
 
 // Get the in the Field Groups to store the Id and Name, store in a HashMap
 pub async fn get_vtex_field_groups(
     client: &Client,
     account_name: &str,
     environment: &str,
-) -> Vec<SpecificationGroup> {
-    let url = "https://{accountName}.{environment}.com.br/api/catalog_system/pvt/specification/groupbycategory/0"
+    limiter: &RequestLimiter,
+) -> Result<Vec<SpecificationGroup>, String> {
+    get_vtex_field_groups_for_category(client, account_name, environment, "0", limiter).await
+}
+
+// Get the specification groups of a category - category 0 returns the groups not tied to a category
+pub async fn get_vtex_field_groups_for_category(
+    client: &Client,
+    account_name: &str,
+    environment: &str,
+    category_id: &str,
+    limiter: &RequestLimiter,
+) -> Result<Vec<SpecificationGroup>, String> {
+    let url = "https://{accountName}.{environment}.com.br/api/catalog_system/pvt/specification/groupbycategory/"
             .replace("{accountName}", account_name)
-            .replace("{environment}", environment);
-    let groups: Vec<SpecificationGroup> =
-        client.get(url).send().await.unwrap().json().await.unwrap();
-    groups
+            .replace("{environment}", environment)
+            + category_id;
+    get_with_retry(client, &url, limiter).await
 }
 
 // Get the VTEX Category Tree - to store the Id and Name in a HashMap
@@ -107,75 +244,13 @@ pub async fn get_category_by_id(
     account_name: &str,
     environment: &str,
     id: &i32,
-) -> Category {
+    limiter: &RequestLimiter,
+) -> Result<Category, String> {
     let url = "https://{accountName}.{environment}.com.br/api/catalog/pvt/category/"
         .replace("{accountName}", account_name)
         .replace("{environment}", environment)
         + id.to_string().as_str();
-    // let category: Category =
-    //     client.get(url).send().await.unwrap().json().await.unwrap();
-    let get_category_result = client.get(&url).send().await;
-    let response = match get_category_result {
-        Ok(result) => {
-            if result.status() == StatusCode::OK {
-                let category: Category = result.json().await.unwrap();
-                Ok(category)
-            } else {
-                let status = result.status().clone();
-                let other_errors = result.text().await;
-                match other_errors {
-                    Ok(s) => {
-                        let error = format!("response: {}  message: {}", status, s);
-                        Err(error)
-                    }
-                    Err(e) => Err(e.to_string()),
-                }
-            }
-        }
-        Err(err) => {
-            // Because we sometimes get incomplete errors, connection terminated, try again
-            error!(
-                "Connection error on get_category_by_id() {:?}",
-                err.to_string()
-            );
-            Err(err.to_string())
-        }
-    };
-    if response.is_err() {
-        // Try again due to connection error
-        let get_category_result = client.get(&url).send().await;
-        let response = match get_category_result {
-            Ok(result) => {
-                if result.status() == StatusCode::OK {
-                    let category: Category = result.json().await.unwrap();
-                    category
-                } else {
-                    let status = result.status().clone();
-                    let other_errors = result.text().await;
-                    match other_errors {
-                        Ok(s) => {
-                            let error = format!("response: {}  message: {}", status, s);
-                            panic!("Failed second time get_category_by_id(): {:?}", error);
-                        }
-                        Err(e) => {
-                            panic!("Failed second time get_category_by_id(): {:?}", e);
-                        }
-                    }
-                }
-            }
-            Err(err) => {
-                // Because we sometimes get incomplete errors, connection terminated, try again
-                panic!(
-                    "Connection error on get_category_by_id() {:?}",
-                    err.to_string()
-                );
-            }
-        };
-        response
-    } else {
-        // Return the category
-        response.unwrap()
-    }
+    get_with_retry(client, &url, limiter).await
 }
 
 // Get the specs for a given category
@@ -184,13 +259,29 @@ pub async fn get_spec_fields_for_category(
     account_name: &str,
     environment: &str,
     category_id: &str,
-) -> Vec<SpecificationList> {
+    limiter: &RequestLimiter,
+) -> Result<Vec<SpecificationList>, String> {
     let url = "https://{accountName}.{environment}.com.br/api/catalog_system/pub/specification/field/listByCategoryId/"
             .replace("{accountName}", account_name)
             .replace("{environment}", environment)
             + category_id;
-    let specs: Vec<SpecificationList> = client.get(url).send().await.unwrap().json().await.unwrap();
-    specs
+    get_with_retry(client, &url, limiter).await
+}
+
+// Get a specification field, including its field type
+pub async fn get_specification_field(
+    client: &Client,
+    account_name: &str,
+    environment: &str,
+    field_id: &str,
+    limiter: &RequestLimiter,
+) -> Result<SpecificationField, String> {
+    let url =
+        "https://{accountName}.{environment}.com.br/api/catalog_system/pub/specification/fieldGet/"
+            .replace("{accountName}", account_name)
+            .replace("{environment}", environment)
+            + field_id;
+    get_with_retry(client, &url, limiter).await
 }
 
 // Get the field values for a given field
@@ -199,14 +290,13 @@ pub async fn get_field_values_for_field_id(
     account_name: &str,
     environment: &str,
     field_id: &str,
-) -> Vec<FieldValueList> {
+    limiter: &RequestLimiter,
+) -> Result<Vec<FieldValueList>, String> {
     let url = "https://{accountName}.{environment}.com.br/api/catalog_system/pub/specification/fieldvalue/"
             .replace("{accountName}", account_name)
             .replace("{environment}", environment)
             + field_id;
-    let fieldvalues: Vec<FieldValueList> =
-        client.get(url).send().await.unwrap().json().await.unwrap();
-    fieldvalues
+    get_with_retry(client, &url, limiter).await
 }
 
 // Get the brands
@@ -247,7 +337,13 @@ pub async fn create_brand_lookup(
     account_name: &str,
     environment: &str,
 ) -> HashMap<String, i32> {
-    parse_brands(get_brands(client, account_name, environment).await)
+    let cache = LookupCache::new(account_name, environment);
+    if let Some(brand_lookup) = cache.get(cache::BRAND_LOOKUP) {
+        return brand_lookup;
+    }
+    let brand_lookup = parse_brands(get_brands(client, account_name, environment).await);
+    cache.put(cache::BRAND_LOOKUP, &brand_lookup);
+    brand_lookup
 }
 
 // Parse the Specification Groups into a HashMap for Key Lookup
@@ -259,6 +355,61 @@ pub fn parse_spec_groups(groups: Vec<SpecificationGroup>) -> HashMap<String, i32
     group_ids
 }
 
+// Lookup of the specification group id by [category_id + group name] across all categories
+// Groups not tied to a category are stored under category 0
+pub async fn create_spec_group_lookup(
+    category_lookup: &HashMap<String, i32>,
+    client: &Client,
+    account_name: &str,
+    environment: &str,
+    limiter: &RequestLimiter,
+) -> Result<HashMap<String, i32>, String> {
+    let mut category_ids: HashSet<i32> = category_lookup.values().copied().collect();
+    category_ids.insert(0);
+    info!(
+        "Starting create_spec_group_lookup(): categories: {}",
+        category_ids.len()
+    );
+    let results: Vec<Result<(i32, Vec<SpecificationGroup>), String>> = stream::iter(category_ids)
+        .map(|category_id| async move {
+            let groups = get_vtex_field_groups_for_category(
+                client,
+                account_name,
+                environment,
+                category_id.to_string().as_str(),
+                limiter,
+            )
+            .await?;
+            Ok((category_id, groups))
+        })
+        .buffer_unordered(limiter.concurrency)
+        .collect()
+        .await;
+    let mut group_lookup: HashMap<String, i32> = HashMap::new();
+    for result in results {
+        let (category_id, groups) = result?;
+        for group in groups {
+            if let Some(group_id) = group.id {
+                let key = category_id.to_string() + "|" + group.name.as_str();
+                group_lookup.insert(key, group_id);
+            }
+        }
+    }
+    Ok(group_lookup)
+}
+
+// Get the specification group id for a category, falling back to a group not tied to a category
+pub fn get_spec_group_id(
+    group_lookup: &HashMap<String, i32>,
+    category_id: i32,
+    group_name: &str,
+) -> Option<i32> {
+    group_lookup
+        .get(&(category_id.to_string() + "|" + group_name))
+        .or_else(|| group_lookup.get(&("0|".to_string() + group_name)))
+        .copied()
+}
+
 // Read in the Category Id
 // Parse the Category Tree into a HashMap for Key Lookup
 pub fn parse_category_tree(cat_tree: Vec<CategoryTree>) -> HashMap<String, i32> {
@@ -324,74 +475,51 @@ pub async fn create_category_name_lookup(
     client: &Client,
     account_name: &str,
     environment: &str,
-) -> HashMap<String, String> {
+    limiter: &RequestLimiter,
+) -> Result<HashMap<String, String>, String> {
+    let cache = LookupCache::new(account_name, environment);
+    if let Some(cat_name_lookup) = cache.get(cache::CATEGORY_NAME_LOOKUP) {
+        return Ok(cat_name_lookup);
+    }
     let cat_tree = get_vtex_category_tree(client, account_name, environment).await;
-    let mut cat_name_lookup: HashMap<String, String> = HashMap::new();
+    // Every level of the tree
+    let mut categories: Vec<(i32, String)> = Vec::new();
+    let mut stack: Vec<CategoryTree> = cat_tree;
+    while let Some(category) = stack.pop() {
+        categories.push((category.id, category.name));
+        stack.extend(category.children.unwrap_or_default());
+    }
+    info!(
+        "Starting create_category_name_lookup(): categories: {}",
+        categories.len()
+    );
 
-    for category in cat_tree {
-        cat_name_lookup.insert(
-            get_category_by_id(client, account_name, environment, &category.id)
-                .await
-                .ad_words_remarketing_code
-                .unwrap(),
-            category.name.clone(),
-        );
-        if category.has_children {
-            for category2 in category.children.expect("missing category") {
-                cat_name_lookup.insert(
-                    get_category_by_id(client, account_name, environment, &category2.id)
-                        .await
-                        .ad_words_remarketing_code
-                        .unwrap(),
-                    category2.name.clone(),
-                );
-                if category2.has_children {
-                    for category3 in category2.children.expect("missing category") {
-                        cat_name_lookup.insert(
-                            get_category_by_id(client, account_name, environment, &category3.id)
-                                .await
-                                .ad_words_remarketing_code
-                                .unwrap(),
-                            category3.name.clone(),
-                        );
-                        if category3.has_children {
-                            for category4 in category3.children.expect("missing category") {
-                                cat_name_lookup.insert(
-                                    get_category_by_id(
-                                        client,
-                                        account_name,
-                                        environment,
-                                        &category4.id,
-                                    )
-                                    .await
-                                    .ad_words_remarketing_code
-                                    .unwrap(),
-                                    category4.name.clone(),
-                                );
-                                if category4.has_children {
-                                    for category5 in category4.children.expect("missing category") {
-                                        cat_name_lookup.insert(
-                                            get_category_by_id(
-                                                client,
-                                                account_name,
-                                                environment,
-                                                &category5.id,
-                                            )
-                                            .await
-                                            .ad_words_remarketing_code
-                                            .unwrap(),
-                                            category5.name.clone(),
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+    // The GroupIdentifier is stored in the ad_words_remarketing_code of the category
+    let results: Vec<(String, Result<Category, String>)> = stream::iter(categories)
+        .map(|(id, name)| async move {
+            let category =
+                get_category_by_id(client, account_name, environment, &id, limiter).await;
+            (name, category)
+        })
+        .buffer_unordered(limiter.concurrency)
+        .collect()
+        .await;
+
+    let mut cat_name_lookup: HashMap<String, String> = HashMap::new();
+    for (name, result) in results {
+        match result?.ad_words_remarketing_code {
+            Some(identifier) => {
+                cat_name_lookup.insert(identifier, name);
             }
+            None => warn!("category: {} has no GroupIdentifier. Skipping.", name),
         }
     }
-    cat_name_lookup
+    cache.put(cache::CATEGORY_NAME_LOOKUP, &cat_name_lookup);
+    info!(
+        "finished create_category_name_lookup(): {}",
+        cat_name_lookup.len()
+    );
+    Ok(cat_name_lookup)
 }
 
 // Create a lookup HashMap that allows lookup of the parent category_unique_identifier by the product ref_id
@@ -431,6 +559,188 @@ pub fn create_sku_product_ref_id_lookup(sku_file: String) -> HashMap<String, Str
     sku_product_ref_id_lookup
 }
 
+// GTIN-8, 12 (UPC), 13 (EAN) or 14
+pub fn is_gtin(value: &str) -> bool {
+    [8, 12, 13, 14].contains(&value.len()) && value.chars().all(|c| c.is_ascii_digit())
+}
+
+// Read the SKU Specification Allowed Values file and return one record per value.
+// Accepts the long format (ProductRefId,Name,Position,Value) as well as the
+// wide format (ProductRefId,Name,Position,AllowedValue1..AllowedValueN) with any number of columns
+pub fn read_sku_spec_allowed_values(
+    file_path: &str,
+) -> Result<Vec<SkuSpecAllowedValue>, Box<dyn Error>> {
+    let file = File::open(file_path)?;
+    let mut reader = csv::Reader::from_reader(file);
+    parse_sku_spec_allowed_values(&mut reader)
+}
+
+pub fn parse_sku_spec_allowed_values<R: Read>(
+    reader: &mut csv::Reader<R>,
+) -> Result<Vec<SkuSpecAllowedValue>, Box<dyn Error>> {
+    let headers = reader.headers()?.clone();
+    // Strip a UTF-8 BOM in case the file was saved from Excel
+    let position_of = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.trim_start_matches('\u{feff}').eq(name))
+    };
+    let product_ref_id_idx = position_of("ProductRefId").ok_or("missing column ProductRefId")?;
+    let name_idx = position_of("Name").ok_or("missing column Name")?;
+    let position_idx = position_of("Position").ok_or("missing column Position")?;
+    // FieldType is optional - when missing the generators choose the default
+    let field_type_idx = position_of("FieldType");
+    // GroupName is optional - when missing the default specification group is used
+    let group_name_idx = position_of("GroupName");
+    // Long format has a single Value column, wide format has AllowedValue1..N
+    let value_idxs: Vec<usize> = match position_of("Value") {
+        Some(idx) => vec![idx],
+        None => headers
+            .iter()
+            .enumerate()
+            .filter(|(_, h)| h.starts_with("AllowedValue"))
+            .map(|(idx, _)| idx)
+            .collect(),
+    };
+    if value_idxs.is_empty() {
+        return Err("missing column Value or AllowedValue1..N".into());
+    }
+    debug!("allowed value columns: {:?}", value_idxs);
+
+    let mut allowed_values: Vec<SkuSpecAllowedValue> = Vec::new();
+    let mut e = 0;
+    for line in reader.records() {
+        let record = line?;
+        let product_ref_id = record.get(product_ref_id_idx).unwrap_or("").trim();
+        let name = record.get(name_idx).unwrap_or("").trim();
+        let position = match record.get(position_idx).unwrap_or("").trim().parse::<i32>() {
+            Ok(position) => position,
+            Err(err) => {
+                error!(
+                    "Error parsing Position for product_ref_id: {} name: {}: {:?}",
+                    product_ref_id, name, err
+                );
+                e += 1;
+                continue;
+            }
+        };
+        let field_type = match field_type_idx
+            .and_then(|idx| record.get(idx))
+            .map(|field_type| field_type.trim())
+            .filter(|field_type| !field_type.is_empty())
+        {
+            Some(field_type) => match field_type.parse::<FieldType>() {
+                Ok(field_type) => Some(field_type),
+                Err(err) => {
+                    error!(
+                        "Error parsing FieldType for product_ref_id: {} name: {}: {}",
+                        product_ref_id, name, err
+                    );
+                    e += 1;
+                    continue;
+                }
+            },
+            None => None,
+        };
+        let group_name = group_name_idx
+            .and_then(|idx| record.get(idx))
+            .map(|group_name| group_name.trim())
+            .filter(|group_name| !group_name.is_empty())
+            .map(|group_name| group_name.to_string());
+        for idx in &value_idxs {
+            let value = record.get(*idx).unwrap_or("").trim();
+            if !value.is_empty() {
+                allowed_values.push(SkuSpecAllowedValue {
+                    product_ref_id: product_ref_id.to_string(),
+                    name: name.to_string(),
+                    position,
+                    value: value.to_string(),
+                    field_type,
+                    group_name: group_name.clone(),
+                });
+            }
+        }
+    }
+    info!(
+        "Allowed values successfully read: {}. Rows not read (errors): {}",
+        allowed_values.len(),
+        e
+    );
+    Ok(allowed_values)
+}
+
+// Write the allowed values in the long format - one row per value
+pub fn write_sku_spec_allowed_values_long<W: Write>(
+    allowed_values: &[SkuSpecAllowedValue],
+    writer: &mut csv::Writer<W>,
+) -> Result<(), Box<dyn Error>> {
+    for allowed_value in allowed_values {
+        writer.serialize(allowed_value)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// Write the allowed values in the wide format - one row per product and specification
+// The number of AllowedValueN columns grows to fit the specification with the most values
+pub fn write_sku_spec_allowed_values_wide<W: Write>(
+    allowed_values: &[SkuSpecAllowedValue],
+    writer: &mut csv::Writer<W>,
+) -> Result<(), Box<dyn Error>> {
+    // Group the values by product and specification, keeping the order of the input
+    let mut keys: Vec<(String, String)> = Vec::new();
+    // The first record of a group carries its Position, FieldType and GroupName
+    let mut rows: HashMap<(String, String), (&SkuSpecAllowedValue, Vec<String>)> = HashMap::new();
+    for allowed_value in allowed_values {
+        let key = (
+            allowed_value.product_ref_id.clone(),
+            allowed_value.name.clone(),
+        );
+        let row = rows.entry(key.clone()).or_insert_with(|| {
+            keys.push(key);
+            (allowed_value, Vec::new())
+        });
+        if !row.1.contains(&allowed_value.value) {
+            row.1.push(allowed_value.value.clone());
+        }
+    }
+    let num_values = rows.values().map(|row| row.1.len()).max().unwrap_or(1);
+    // Only write the FieldType column when the input has one
+    let has_field_type = rows.values().any(|row| row.0.field_type.is_some());
+    let has_group_name = rows.values().any(|row| row.0.group_name.is_some());
+
+    let mut header = vec![
+        "ProductRefId".to_string(),
+        "Name".to_string(),
+        "Position".to_string(),
+    ];
+    if has_field_type {
+        header.push("FieldType".to_string());
+    }
+    if has_group_name {
+        header.push("GroupName".to_string());
+    }
+    for number in 1..=num_values {
+        header.push(format!("AllowedValue{}", number));
+    }
+    writer.write_record(&header)?;
+    for key in keys {
+        let (first, values) = rows.get(&key).unwrap();
+        let mut record = vec![key.0.clone(), key.1.clone(), first.position.to_string()];
+        if has_field_type {
+            record.push(first.field_type.map(|t| t.to_string()).unwrap_or_default());
+        }
+        if has_group_name {
+            record.push(first.group_name.clone().unwrap_or_default());
+        }
+        record.extend(values.iter().cloned());
+        record.resize(header.len(), String::new());
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
 //     // Create a lookup HashMap that allows lookup of the parent category GroupoIdentifier by the PartNumber
 //     pub fn create_sku_parent_category_lookup() -> HashMap<String, String> {
 //         let file = File::open("transform/data/in/ProductHeaderItem-sorted-subset.csv").unwrap();
@@ -454,24 +764,60 @@ pub async fn create_field_id_lookup(
     client: &Client,
     account_name: &str,
     environment: &str,
-) -> HashMap<String, i32> {
+    limiter: &RequestLimiter,
+) -> Result<HashMap<String, i32>, String> {
+    // The field ids by field name are cached per category - only the categories not cached are read
+    let cache = LookupCache::new(account_name, environment);
+    let mut cached: CacheEntry<HashMap<i32, HashMap<String, i32>>> = cache
+        .get_entry(cache::FIELD_ID_LOOKUP)
+        .unwrap_or_else(|| CacheEntry::new(HashMap::new()));
+    let category_ids: HashSet<i32> = category_lookup
+        .values()
+        .filter(|category_id| !cached.lookup.contains_key(category_id))
+        .cloned()
+        .collect();
+    info!(
+        "Starting create_field_id_lookup(): categories to read: {}",
+        category_ids.len()
+    );
+    let results: Vec<(i32, Result<Vec<SpecificationList>, String>)> = stream::iter(category_ids)
+        .map(|category_id| async move {
+            // get the fields for the category
+            let category_fields = get_spec_fields_for_category(
+                client,
+                account_name,
+                environment,
+                category_id.to_string().as_str(),
+                limiter,
+            )
+            .await;
+            (category_id, category_fields)
+        })
+        .buffer_unordered(limiter.concurrency)
+        .collect()
+        .await;
+    let updated = !results.is_empty();
+    for (category_id, category_fields) in results {
+        let field_ids: HashMap<String, i32> = category_fields?
+            .into_iter()
+            .map(|cat_field| (cat_field.name, cat_field.field_id))
+            .collect();
+        cached.lookup.insert(category_id, field_ids);
+    }
+    // Added categories keep the created_at of the entry so they expire together
+    if updated {
+        cache.put_entry(cache::FIELD_ID_LOOKUP, &cached);
+    }
+
     // Lookup by [cat_id + field name, field-id]
     let mut field_id_lookup: HashMap<String, i32> = HashMap::new();
-    for category in category_lookup {
-        // get the fields for the category
-        let category_fields = get_spec_fields_for_category(
-            client,
-            account_name,
-            environment,
-            category.1.to_string().as_str(),
-        )
-        .await;
-        for cat_field in category_fields {
-            let key = category.1.to_string().as_str().to_owned() + "|" + cat_field.name.as_str();
-            field_id_lookup.insert(key, cat_field.field_id);
+    for category_id in category_lookup.values() {
+        for (name, field_id) in &cached.lookup[category_id] {
+            let key = category_id.to_string() + "|" + name.as_str();
+            field_id_lookup.insert(key, *field_id);
         }
     }
-    field_id_lookup
+    Ok(field_id_lookup)
 }
 
 // Get Product by RefId
@@ -481,6 +827,36 @@ pub async fn get_product_by_ref_id(
     account_name: &str,
     environment: &str,
 ) -> Result<i32, String> {
+    get_product_record_by_ref_id(ref_id, client, account_name, environment)
+        .await
+        .map(|product| product.id.unwrap())
+}
+
+// Get the full Product record by RefId - the reads are retried. None when the product is not
+// found (a 404 or a 200 with a "null" body), any other failed read is an error
+pub async fn get_product_record_by_ref_id_with_retry(
+    ref_id: &str,
+    client: &Client,
+    account_name: &str,
+    environment: &str,
+    limiter: &RequestLimiter,
+) -> Result<Option<Product>, String> {
+    let url = "https://{accountName}.{environment}.com.br/api/catalog_system/pvt/products/productgetbyrefid/"
+            .replace("{accountName}", account_name)
+            .replace("{environment}", environment)
+            + ref_id;
+    let product: Option<Option<Product>> =
+        get_or_none_with_retry(client, &url, limiter, true).await?;
+    Ok(product.flatten())
+}
+
+// Get the full Product record by RefId
+pub async fn get_product_record_by_ref_id(
+    ref_id: &str,
+    client: &Client,
+    account_name: &str,
+    environment: &str,
+) -> Result<Product, String> {
     let url = "https://{accountName}.{environment}.com.br/api/catalog_system/pvt/products/productgetbyrefid/"
             .replace("{accountName}", account_name)
             .replace("{environment}", environment)
@@ -493,7 +869,7 @@ pub async fn get_product_by_ref_id(
                 // This API returns a 200 even if not found. The body contains "null"
                 let result = result.json::<Product>().await;
                 match result {
-                    Ok(product) => Ok(product.id.unwrap()),
+                    Ok(product) => Ok(product),
                     Err(e) => {
                         let error =
                             format!("product with ref_id: {} not found. error: {}", ref_id, e);
@@ -677,28 +1053,171 @@ pub async fn get_sku_ids_by_ref_ids(
     ir
 }
 
+// Lookup of the sku_id by ref_id - the reads are retried. A ref_id not found (404) is not in the
+// lookup, any other failed read is an error
+pub async fn get_sku_ids_by_ref_ids_with_retry(
+    ref_ids: Vec<String>,
+    client: &Client,
+    account_name: &str,
+    environment: &str,
+    limiter: &RequestLimiter,
+) -> Result<HashMap<String, i32>, String> {
+    let url = "https://{accountName}.{environment}.com.br/api/catalog_system/pvt/sku/stockkeepingunitidbyrefid/{refId}"
+            .replace("{accountName}", account_name)
+            .replace("{environment}", environment);
+    let results: Vec<Result<Option<(String, i32)>, String>> = stream::iter(ref_ids)
+        .map(|ref_id| {
+            let url = url.replace("{refId}", &ref_id);
+            async move {
+                let sku_id: Option<String> =
+                    get_or_none_with_retry(client, &url, limiter, true).await?;
+                match sku_id {
+                    Some(sku_id) => {
+                        let sku_id = sku_id.parse::<i32>().map_err(|e| {
+                            format!("ref_id: {} sku_id: {} error: {}", ref_id, sku_id, e)
+                        })?;
+                        Ok(Some((ref_id, sku_id)))
+                    }
+                    None => {
+                        debug!("sku with ref_id: {} not found", ref_id);
+                        Ok(None)
+                    }
+                }
+            }
+        })
+        .buffer_unordered(limiter.concurrency)
+        .collect()
+        .await;
+    let mut sku_id_lookup = HashMap::with_capacity(results.len());
+    for result in results {
+        if let Some((ref_id, sku_id)) = result? {
+            sku_id_lookup.insert(ref_id, sku_id);
+        }
+    }
+    debug!("sku_id_lookup: {}", sku_id_lookup.len());
+    Ok(sku_id_lookup)
+}
+
+// Lookup of the field type by field_id
+pub async fn create_field_type_lookup(
+    field_id_lookup: &HashMap<String, i32>,
+    client: &Client,
+    account_name: &str,
+    environment: &str,
+    limiter: &RequestLimiter,
+) -> Result<HashMap<i32, FieldType>, String> {
+    // Fields are shared by the categories so each field is read once
+    let field_ids: HashSet<i32> = field_id_lookup.values().copied().collect();
+    info!(
+        "Starting create_field_type_lookup(): fields to read: {}",
+        field_ids.len()
+    );
+    let results: Vec<Result<(i32, FieldType), String>> = stream::iter(field_ids)
+        .map(|field_id| async move {
+            let field = get_specification_field(
+                client,
+                account_name,
+                environment,
+                field_id.to_string().as_str(),
+                limiter,
+            )
+            .await?;
+            Ok((field_id, field.field_type_id))
+        })
+        .buffer_unordered(limiter.concurrency)
+        .collect()
+        .await;
+    results.into_iter().collect()
+}
+
+// Validate a specification value against the field type and resolve what to send to VTEX
+// Returns (FieldValueId, Text): value list fields send the FieldValueId, the others send Text
+pub fn resolve_spec_value(
+    field_type: FieldType,
+    field_id: i32,
+    value: &str,
+    field_value_id_lookup: &HashMap<String, i32>,
+) -> Result<(Option<i32>, Option<String>), String> {
+    let value = value.trim();
+    if field_type.has_value_list() {
+        let key = field_id.to_string() + "|" + value;
+        match field_value_id_lookup.get(&key) {
+            Some(field_value_id) => Ok((Some(*field_value_id), None)),
+            None => Err(format!(
+                "value: [{}] is not in the value list of {} field_id: {}",
+                value, field_type, field_id
+            )),
+        }
+    } else if field_type == FieldType::Number {
+        match value.parse::<f64>() {
+            Ok(_) => Ok((None, Some(value.to_string()))),
+            Err(_) => Err(format!(
+                "value: [{}] is not a number for field_id: {}",
+                value, field_id
+            )),
+        }
+    } else {
+        Ok((None, Some(value.to_string())))
+    }
+}
+
 // Create field value id lookup. key = field_id + "|" + value, returns field_value_id
 pub async fn create_field_value_id_lookup(
     field_id_lookup: &HashMap<String, i32>,
     client: &Client,
     account_name: &str,
     environment: &str,
-) -> HashMap<String, i32> {
-    let mut field_value_id_lookup: HashMap<String, i32> = HashMap::new();
-    for field in field_id_lookup {
-        let field_values = get_field_values_for_field_id(
-            client,
-            account_name,
-            environment,
-            field.1.to_string().as_str(),
-        )
+    limiter: &RequestLimiter,
+) -> Result<HashMap<String, i32>, String> {
+    // The field value ids by value are cached per field - only the fields not cached are read
+    let cache = LookupCache::new(account_name, environment);
+    let mut cached: CacheEntry<HashMap<i32, HashMap<String, i32>>> = cache
+        .get_entry(cache::FIELD_VALUE_ID_LOOKUP)
+        .unwrap_or_else(|| CacheEntry::new(HashMap::new()));
+    let field_ids: HashSet<i32> = field_id_lookup
+        .values()
+        .filter(|field_id| !cached.lookup.contains_key(field_id))
+        .cloned()
+        .collect();
+    info!(
+        "Starting create_field_value_id_lookup(): fields to read: {}",
+        field_ids.len()
+    );
+    let results: Vec<(i32, Result<Vec<FieldValueList>, String>)> = stream::iter(field_ids)
+        .map(|field_id| async move {
+            let field_values = get_field_values_for_field_id(
+                client,
+                account_name,
+                environment,
+                field_id.to_string().as_str(),
+                limiter,
+            )
+            .await;
+            (field_id, field_values)
+        })
+        .buffer_unordered(limiter.concurrency)
+        .collect()
         .await;
-        for field_value in field_values {
-            let key = field.1.to_string().as_str().to_owned() + "|" + field_value.value.as_str();
-            field_value_id_lookup.insert(key, field_value.field_value_id);
+    let updated = !results.is_empty();
+    for (field_id, field_values) in results {
+        let value_ids: HashMap<String, i32> = field_values?
+            .into_iter()
+            .map(|field_value| (field_value.value, field_value.field_value_id))
+            .collect();
+        cached.lookup.insert(field_id, value_ids);
+    }
+    if updated {
+        cache.put_entry(cache::FIELD_VALUE_ID_LOOKUP, &cached);
+    }
+
+    let mut field_value_id_lookup: HashMap<String, i32> = HashMap::new();
+    for field_id in field_id_lookup.values() {
+        for (value, field_value_id) in &cached.lookup[field_id] {
+            let key = field_id.to_string() + "|" + value.as_str();
+            field_value_id_lookup.insert(key, *field_value_id);
         }
     }
-    field_value_id_lookup
+    Ok(field_value_id_lookup)
 }
 
 pub async fn get_all_sku_ids(client: &Client, account_name: &str, environment: &str) -> Vec<i32> {
@@ -762,65 +1281,155 @@ pub async fn get_all_sku_ids_by_page(
     }
 }
 
-fn build_get_sku_urls(sku_ids: &[i32], account_name: &str, environment: &str) -> Vec<String> {
+fn build_get_sku_urls(
+    sku_ids: &[i32],
+    account_name: &str,
+    environment: &str,
+) -> Vec<(i32, String)> {
     let url = "https://{accountName}.{environment}.com.br/api/catalog_system/pvt/sku/stockkeepingunitbyid/{skuId}?sc=1"
             .replace("{accountName}", account_name)
             .replace("{environment}", environment);
-    let mut urls: Vec<String> = Vec::with_capacity(sku_ids.len());
+    let mut urls: Vec<(i32, String)> = Vec::with_capacity(sku_ids.len());
     for sku_id in sku_ids {
         let url = url.replace("{skuId}", sku_id.to_string().as_str());
-        urls.push(url);
+        urls.push((*sku_id, url));
     }
     debug!("sku urls.len(): {}", urls.len());
     urls
 }
 
-async fn get_item_records(
+// Get the SKU and context records for the sku_ids - keyed by sku_id. SKUs not found are not
+// returned, any other failed read is an error
+pub async fn get_item_records(
     sku_ids: &[i32],
     client: &Client,
     account_name: &str,
     environment: &str,
-) -> HashMap<i32, SkuAndContext> {
+) -> Result<HashMap<i32, SkuAndContext>, String> {
     info!("Starting get_item_records()");
-    // Build the urls
     let urls = build_get_sku_urls(sku_ids, account_name, environment);
-    debug!("after call to build_get_sku_urls");
-    let item_recs: Arc<Mutex<HashMap<i32, SkuAndContext>>> = Arc::new(Mutex::new(HashMap::new()));
-    let bodies = stream::iter(urls)
-        .map(|url| {
-            let client = &client;
-            async move {
-                let resp = client.get(url.clone()).send().await?;
-
-                // let sctx: SkuAndContext = resp.json().await?;
-                debug!("end of async move - url: {}", url);
-                // resp.text().await
-                resp.json::<SkuAndContext>().await
-            }
-        })
-        .buffer_unordered(CONCURRENT_REQUESTS);
-    bodies
-        .for_each(|b| async {
-            let item_recs = item_recs.clone();
-            match b {
-                Ok(b) => {
-                    // let result: Result<SkuAndContext, serde_json::Error> = serde_json::from_str(b).unwrap();
-                    let sku_ctx: SkuAndContext = b;
-                    let mut item_recs = item_recs.lock().unwrap();
-                    item_recs.insert(sku_ctx.id, sku_ctx.clone());
-                    debug!("Got: {:?}", sku_ctx)
-                }
-                Err(e) => error!("Got an error: {}", e),
-            }
-        })
-        .await;
-
-    let ir = item_recs.lock().unwrap().clone();
+    let limiter = RequestLimiter::with_concurrency(CONCURRENT_REQUESTS);
+    let ir = get_sku_records::<SkuAndContext>(urls, client, &limiter).await?;
     info!(
         "finished get_item_records(): item_recs.len(): {:?}",
         ir.len()
     );
-    ir
+    Ok(ir)
+}
+
+// Get the products from..to (1 based, at most 250 per call) with the sku ids of each product
+pub async fn get_product_and_sku_ids(
+    client: &Client,
+    account_name: &str,
+    environment: &str,
+    from: i32,
+    to: i32,
+) -> Result<ProductAndSkuIds, reqwest::Error> {
+    let url = "https://{accountName}.{environment}.com.br/api/catalog_system/pvt/products/GetProductAndSkuIds?_from={from}&_to={to}"
+        .replace("{accountName}", account_name)
+        .replace("{environment}", environment)
+        .replace("{from}", from.to_string().as_str())
+        .replace("{to}", to.to_string().as_str());
+    let product_and_sku_ids = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<ProductAndSkuIds>()
+        .await?;
+    debug!(
+        "products: {} of {}",
+        product_and_sku_ids.data.len(),
+        product_and_sku_ids.range.total
+    );
+    Ok(product_and_sku_ids)
+}
+
+// The url of each sku_id - {skuId} replaced
+fn sku_urls(url: &str, sku_ids: &[i32]) -> Vec<(i32, String)> {
+    sku_ids
+        .iter()
+        .map(|sku_id| (*sku_id, url.replace("{skuId}", sku_id.to_string().as_str())))
+        .collect()
+}
+
+// Get the price records for the sku_ids - keyed by sku_id. SKUs without a price are not returned
+pub async fn get_price_records(
+    sku_ids: &[i32],
+    client: &Client,
+    account_name: &str,
+) -> Result<HashMap<i32, PriceGet>, String> {
+    info!("Starting get_price_records()");
+    let url = "https://api.vtex.com/{accountName}/pricing/prices/{skuId}"
+        .replace("{accountName}", account_name);
+    let limiter = RequestLimiter::with_concurrency(CONCURRENT_REQUESTS);
+    let pr = get_sku_records::<PriceGet>(sku_urls(&url, sku_ids), client, &limiter).await?;
+    info!(
+        "finished get_price_records(): price_recs.len(): {:?}",
+        pr.len()
+    );
+    Ok(pr)
+}
+
+// Get the computed price of the sku_ids for a trade policy - keyed by sku_id
+pub async fn get_computed_price_records(
+    sku_ids: &[i32],
+    client: &Client,
+    account_name: &str,
+    trade_policy_id: &str,
+) -> Result<HashMap<i32, ComputedPrice>, String> {
+    info!("Starting get_computed_price_records()");
+    let url = "https://api.vtex.com/{accountName}/pricing/prices/{skuId}/computed/{priceTableId}?quantity=1"
+        .replace("{accountName}", account_name)
+        .replace("{priceTableId}", trade_policy_id);
+    let limiter = RequestLimiter::with_concurrency(CONCURRENT_REQUESTS);
+    let pr = get_sku_records::<ComputedPrice>(sku_urls(&url, sku_ids), client, &limiter).await?;
+    info!(
+        "finished get_computed_price_records(): price_recs.len(): {:?}",
+        pr.len()
+    );
+    Ok(pr)
+}
+
+// Get the inventory (balance by warehouse) for the sku_ids - keyed by sku_id
+pub async fn get_inventory_records(
+    sku_ids: &[i32],
+    client: &Client,
+    account_name: &str,
+    environment: &str,
+) -> Result<HashMap<i32, InventoryList>, String> {
+    info!("Starting get_inventory_records()");
+    let url = "https://{accountName}.{environment}.com.br/api/logistics/pvt/inventory/skus/{skuId}"
+        .replace("{accountName}", account_name)
+        .replace("{environment}", environment);
+    let limiter = RequestLimiter::with_concurrency(CONCURRENT_REQUESTS);
+    let invr = get_sku_records::<InventoryList>(sku_urls(&url, sku_ids), client, &limiter).await?;
+    info!(
+        "finished get_inventory_records(): inventory_recs.len(): {:?}",
+        invr.len()
+    );
+    Ok(invr)
+}
+
+// Get the components of the kit sku_ids - keyed by the kit sku_id
+pub async fn get_sku_kit_records(
+    kit_sku_ids: &[i32],
+    client: &Client,
+    account_name: &str,
+    environment: &str,
+    limiter: &RequestLimiter,
+) -> Result<HashMap<i32, Vec<SkuKit>>, String> {
+    info!("Starting get_sku_kit_records()");
+    let url = "https://{accountName}.{environment}.com.br/api/catalog/pvt/stockkeepingunitkit?parentSkuId={skuId}"
+        .replace("{accountName}", account_name)
+        .replace("{environment}", environment);
+    let kit_recs =
+        get_sku_records::<Vec<SkuKit>>(sku_urls(&url, kit_sku_ids), client, limiter).await?;
+    info!(
+        "finished get_sku_kit_records(): kit_recs.len(): {:?}",
+        kit_recs.len()
+    );
+    Ok(kit_recs)
 }
 
 //     pub fn create_product_id_lookup() -> HashMap<String, i32> {
@@ -841,11 +1450,11 @@ pub async fn create_sku_id_lookup(
     client: &Client,
     account_name: &str,
     environment: &str,
-) -> HashMap<String, i32> {
+) -> Result<HashMap<String, i32>, String> {
     info!("Start creating sku_id_lookup");
     let mut sku_lookup = HashMap::new();
     let sku_ids = get_all_sku_ids(client, account_name, environment).await;
-    let item_records = get_item_records(&sku_ids, client, account_name, environment).await;
+    let item_records = get_item_records(&sku_ids, client, account_name, environment).await?;
     for ir in item_records {
         let sku_id = ir.0;
         let sku_context = ir.1;
@@ -855,12 +1464,16 @@ pub async fn create_sku_id_lookup(
         "Finish creating sku_id_lookup length: {:?}",
         sku_lookup.len()
     );
-    sku_lookup
+    Ok(sku_lookup)
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
 
     //  #[test]
     // fn get_product_id_by_ref_id() {
@@ -913,6 +1526,211 @@ mod tests {
         let result = utf8_percent_encode(ref_id, FRAGMENT);
         println!("result: {:?}: ", result.to_string());
     }
+
+    // Serves the responses in order, one per connection, and counts the requests
+    async fn serve(responses: Vec<(u16, &'static str)>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                counter.fetch_add(1, Ordering::SeqCst);
+                let response = format!(
+                    "HTTP/1.1 {} STATUS\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        (url, requests)
+    }
+
+    fn test_limiter() -> RequestLimiter {
+        RequestLimiter {
+            retry_delay: Duration::from_millis(20),
+            ..RequestLimiter::with_concurrency(1)
+        }
+    }
+
+    #[test]
+    fn retry_delay_doubles() {
+        let first_delay = Duration::from_millis(500);
+        assert_eq!(retry_delay(first_delay, 1), Duration::from_millis(500));
+        assert_eq!(retry_delay(first_delay, 2), Duration::from_secs(1));
+        assert_eq!(retry_delay(first_delay, 3), Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn get_with_retry_retries_429_and_5xx_only() {
+        let client = Client::new();
+        let limiter = test_limiter();
+
+        // 503 and 429 are retried with the backoff (20ms + 40ms) until the 200
+        let (url, requests) = serve(vec![(503, ""), (429, ""), (200, "[1,2]")]).await;
+        let start = Instant::now();
+        let result: Result<Vec<i32>, String> = get_with_retry(&client, &url, &limiter).await;
+        assert_eq!(result, Ok(vec![1, 2]));
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert!(start.elapsed() >= Duration::from_millis(60));
+
+        // 4xx fails on the first attempt
+        let (url, requests) = serve(vec![(404, ""), (200, "[1]")]).await;
+        let result: Result<Vec<i32>, String> = get_with_retry(&client, &url, &limiter).await;
+        assert!(result.unwrap_err().contains("404"));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // Gives up after MAX_ATTEMPTS
+        let (url, requests) = serve(vec![(500, ""); 5]).await;
+        let result: Result<Vec<i32>, String> = get_with_retry(&client, &url, &limiter).await;
+        assert!(result.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), MAX_ATTEMPTS as usize);
+    }
+
+    #[tokio::test]
+    async fn get_sku_records_skips_not_found_and_fails_on_errors() {
+        let client = Client::new();
+        let limiter = test_limiter();
+        let urls = |url: &str, sku_ids: &[i32]| -> Vec<(i32, String)> {
+            sku_ids
+                .iter()
+                .map(|sku_id| (*sku_id, url.to_string()))
+                .collect()
+        };
+
+        // A SKU that does not exist (404) is not returned
+        let (url, _) = serve(vec![(200, "[1]"), (404, "")]).await;
+        let records = get_sku_records::<Vec<i32>>(urls(&url, &[1, 2]), &client, &limiter).await;
+        let records = records.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records.get(&1), Some(&vec![1]));
+
+        // A SKU that can not be read fails the read instead of being left out - a page without
+        // the SKU would delete its record in an incremental export
+        let (url, _) = serve(vec![
+            (200, "[1]"),
+            (404, ""),
+            (500, ""),
+            (500, ""),
+            (500, ""),
+            (500, ""),
+        ])
+        .await;
+        let records = get_sku_records::<Vec<i32>>(urls(&url, &[1, 2, 3]), &client, &limiter).await;
+        assert!(records.is_err());
+    }
+
+    #[tokio::test]
+    async fn get_or_none_with_retry_not_found_and_null() {
+        let client = Client::new();
+        let limiter = test_limiter();
+
+        // A 404 and a 200 with a "null" body (ex: productgetbyrefid) are not found
+        let (url, _) = serve(vec![(404, ""), (200, "null")]).await;
+        let result: Result<Option<Option<Vec<i32>>>, String> =
+            get_or_none_with_retry(&client, &url, &limiter, true).await;
+        assert_eq!(result, Ok(None));
+        let result: Result<Option<Option<Vec<i32>>>, String> =
+            get_or_none_with_retry(&client, &url, &limiter, true).await;
+        assert_eq!(result.map(Option::flatten), Ok(None));
+    }
+
+    #[test]
+    fn gtin_lengths_and_digits() {
+        assert!(is_gtin("7891234567895"));
+        assert!(is_gtin("012345678905"));
+        assert!(!is_gtin("P000007188"));
+        assert!(!is_gtin("123"));
+    }
+
+    #[test]
+    fn parse_sku_spec_allowed_values_wide_and_long() {
+        let wide = "ProductRefId,Name,Position,AllowedValue1,AllowedValue2,AllowedValue3\n\
+                    P1,Color,1,RED,BLUE,\n\
+                    P1,Size,2,Sm,Md,Lg\n";
+        let long = "ProductRefId,Name,Position,Value\n\
+                    P1,Color,1,RED\n\
+                    P1,Color,1,BLUE\n\
+                    P1,Size,2,Sm\n\
+                    P1,Size,2,Md\n\
+                    P1,Size,2,Lg\n";
+        let from_wide =
+            parse_sku_spec_allowed_values(&mut csv::Reader::from_reader(wide.as_bytes())).unwrap();
+        let from_long =
+            parse_sku_spec_allowed_values(&mut csv::Reader::from_reader(long.as_bytes())).unwrap();
+        assert_eq!(from_wide.len(), 5);
+        assert_eq!(from_wide, from_long);
+    }
+
+    #[test]
+    fn resolve_spec_value_by_field_type() {
+        let mut field_value_id_lookup: HashMap<String, i32> = HashMap::new();
+        field_value_id_lookup.insert("10|Red".to_string(), 100);
+
+        assert_eq!(
+            resolve_spec_value(FieldType::Radio, 10, " Red ", &field_value_id_lookup),
+            Ok((Some(100), None))
+        );
+        assert!(resolve_spec_value(FieldType::Combo, 10, "Blue", &field_value_id_lookup).is_err());
+        assert_eq!(
+            resolve_spec_value(FieldType::Number, 11, "12.5", &field_value_id_lookup),
+            Ok((None, Some("12.5".to_string())))
+        );
+        assert!(resolve_spec_value(FieldType::Number, 11, "abc", &field_value_id_lookup).is_err());
+        assert_eq!(
+            resolve_spec_value(FieldType::Text, 12, "Cotton", &field_value_id_lookup),
+            Ok((None, Some("Cotton".to_string())))
+        );
+        assert_eq!(
+            "Multi-Line Text".parse::<FieldType>(),
+            Ok(FieldType::MultiLineText)
+        );
+        assert_eq!("6".parse::<FieldType>(), Ok(FieldType::Radio));
+    }
+
+    #[test]
+    fn get_spec_group_id_by_category_with_fallback() {
+        let mut group_lookup: HashMap<String, i32> = HashMap::new();
+        group_lookup.insert("0|Default Specification Group".to_string(), 1);
+        group_lookup.insert("10|Dimensions".to_string(), 2);
+        group_lookup.insert("20|Dimensions".to_string(), 3);
+
+        assert_eq!(get_spec_group_id(&group_lookup, 10, "Dimensions"), Some(2));
+        assert_eq!(get_spec_group_id(&group_lookup, 20, "Dimensions"), Some(3));
+        assert_eq!(get_spec_group_id(&group_lookup, 30, "Dimensions"), None);
+        assert_eq!(
+            get_spec_group_id(&group_lookup, 10, DEFAULT_SPECIFICATION_GROUP),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn write_sku_spec_allowed_values_wide_beyond_60_values() {
+        let allowed_values: Vec<SkuSpecAllowedValue> = (1..=75)
+            .map(|n| SkuSpecAllowedValue {
+                product_ref_id: "P1".to_string(),
+                name: "Shoe Size".to_string(),
+                position: 1,
+                value: n.to_string(),
+                field_type: Some(FieldType::Combo),
+                group_name: Some("Sizing".to_string()),
+            })
+            .collect();
+        let mut writer = csv::Writer::from_writer(vec![]);
+        write_sku_spec_allowed_values_wide(&allowed_values, &mut writer).unwrap();
+        let output = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert!(output.lines().next().unwrap().ends_with("AllowedValue75"));
+
+        let round_trip =
+            parse_sku_spec_allowed_values(&mut csv::Reader::from_reader(output.as_bytes()))
+                .unwrap();
+        assert_eq!(round_trip, allowed_values);
+    }
 }
 
 //     #[test]